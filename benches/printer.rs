@@ -0,0 +1,66 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use printer::{format, Level, Printer};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Package {
+    name: String,
+    version: String,
+    checksum: Option<String>,
+    size_bytes: u64,
+    dependencies: Vec<String>,
+}
+
+fn packages() -> Vec<Package> {
+    (0..100)
+        .map(|index| Package {
+            name: format!("package-{index}"),
+            version: format!("1.{index}.0"),
+            checksum: (index % 2 == 0).then(|| format!("{index:064x}")),
+            size_bytes: index * 1024,
+            dependencies: (0..index % 5).map(|dep| format!("dep-{dep}")).collect(),
+        })
+        .collect()
+}
+
+fn log(criterion: &mut Criterion) {
+    let mut printer = Printer::from_writer(std::io::sink());
+    criterion.bench_function("log", |bencher| {
+        bencher.iter(|| {
+            printer
+                .log(
+                    Level::Info,
+                    black_box("Compiling printer v0.1.0 (/src/printer)"),
+                )
+                .unwrap()
+        })
+    });
+}
+
+fn sanitize(criterion: &mut Criterion) {
+    criterion.bench_function("sanitize plain", |bencher| {
+        bencher.iter(|| format::sanitize(black_box("Compiling printer v0.1.0 (/src/printer)")))
+    });
+    criterion.bench_function("sanitize control", |bencher| {
+        bencher.iter(|| {
+            format::sanitize(black_box(
+                "\x1b[1m\x1b[32mCompiling\x1b[0m printer\tv0.1.0\r [=====>    ] 50%\x08",
+            ))
+        })
+    });
+}
+
+fn print_value(criterion: &mut Criterion) {
+    let packages = packages();
+    let mut printer = Printer::from_writer(std::io::sink());
+    criterion.bench_function("print_field", |bencher| {
+        bencher.iter(|| {
+            printer
+                .print_field("packages", black_box(&packages))
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, log, sanitize, print_value);
+criterion_main!(benches);
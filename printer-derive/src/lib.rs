@@ -0,0 +1,104 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+// Implements printer::Printable for a struct with named fields. Each field is
+// printed as `name: value` in declaration order. Field attributes:
+//
+// #[printable(skip)]            leaves the field out
+// #[printable(rename = "name")] prints the field under another name
+// #[printable(nested)]          prints the field with its own Printable impl,
+//                               indented under its name
+#[proc_macro_derive(Printable, attributes(printable))]
+pub fn derive_printable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+struct FieldOptions {
+    is_skipped: bool,
+    is_nested: bool,
+    rename: Option<String>,
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions {
+        is_skipped: false,
+        is_nested: false,
+        rename: None,
+    };
+    for attribute in field.attrs.iter() {
+        if !attribute.path().is_ident("printable") {
+            continue;
+        }
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                options.is_skipped = true;
+                Ok(())
+            } else if meta.path.is_ident("nested") {
+                options.is_nested = true;
+                Ok(())
+            } else if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                options.rename = Some(name.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip`, `nested` or `rename = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Printable can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Printable can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut statements = Vec::new();
+    for field in fields.iter() {
+        let options = field_options(field)?;
+        if options.is_skipped {
+            continue;
+        }
+        let Some(ident) = field.ident.as_ref() else {
+            continue;
+        };
+        let name = options
+            .rename
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+        statements.push(if options.is_nested {
+            quote! { printer.print_nested(#name, &self.#ident)?; }
+        } else {
+            quote! { printer.print_field(#name, &self.#ident)?; }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::printer::Printable for #ident #type_generics #where_clause {
+            fn print(&self, printer: &mut ::printer::Printer) -> ::printer::printable::Result {
+                #(#statements)*
+                Ok(())
+            }
+        }
+    })
+}
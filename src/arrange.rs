@@ -0,0 +1,354 @@
+use crate::{
+    grid::Grid,
+    snapshot::{BarStatus, SnapshotSlot},
+    MultiProgress, MultiProgressBar,
+};
+use owo_colors::OwoColorize;
+use std::sync::{atomic::Ordering, Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BarOrder {
+    #[default]
+    Insertion,
+    // running bars first, then queued ones, then finished ones
+    State,
+}
+
+// Returned by MultiProgress::add_group to add bars under that group's header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupId(usize);
+
+struct Member {
+    group: Option<usize>,
+    slot: Arc<SnapshotSlot>,
+    // held so finished bars can still be moved below the running ones
+    progress: indicatif::ProgressBar,
+}
+
+struct Group {
+    name: String,
+    header: indicatif::ProgressBar,
+}
+
+#[derive(Default)]
+struct ArrangementState {
+    order: BarOrder,
+    groups: Vec<Group>,
+    members: Vec<Member>,
+    grid: Option<Arc<Grid>>,
+    window: Option<Window>,
+}
+
+struct Window {
+    max_visible: usize,
+    footer: indicatif::ProgressBar,
+}
+
+fn text_line() -> indicatif::ProgressBar {
+    let progress =
+        indicatif::ProgressBar::with_draw_target(None, indicatif::ProgressDrawTarget::hidden());
+    progress.set_style(indicatif::ProgressStyle::with_template("{msg}").unwrap());
+    progress
+}
+
+// Keeps the bars of a MultiProgress under their group headers in the chosen
+// order. Bars call back into it when they start or finish.
+pub(crate) struct Arrangement {
+    multi_progress: indicatif::MultiProgress,
+    // false for plain output, the TUI or hidden bars where nothing is drawn
+    // through the multi progress
+    is_drawn: bool,
+    state: Mutex<ArrangementState>,
+}
+
+fn rank(status: Option<BarStatus>) -> usize {
+    match status {
+        Some(BarStatus::Running) | Some(BarStatus::Paused) => 0,
+        Some(BarStatus::Queued) => 1,
+        Some(BarStatus::Finished) | Some(BarStatus::Failed) | None => 2,
+    }
+}
+
+impl Arrangement {
+    fn new(multi_progress: &indicatif::MultiProgress, is_drawn: bool) -> Self {
+        Self {
+            multi_progress: multi_progress.clone(),
+            is_drawn,
+            state: Mutex::new(ArrangementState::default()),
+        }
+    }
+
+    pub(crate) fn set_grid(&self, grid: Arc<Grid>) {
+        self.state.lock().unwrap().grid = Some(grid);
+    }
+
+    // Re-adds every bar in place. indicatif has no move, so each bar is removed
+    // and added again, then redrawn.
+    pub(crate) fn rearrange(&self) {
+        if !self.is_drawn {
+            return;
+        }
+        let state = self.state.lock().unwrap();
+        if state.grid.as_ref().is_some_and(|grid| grid.is_active()) {
+            // the grid draws every bar itself
+            for group in state.groups.iter() {
+                self.multi_progress.remove(&group.header);
+            }
+            if let Some(window) = state.window.as_ref() {
+                self.multi_progress.remove(&window.footer);
+            }
+            return;
+        }
+        let members: Vec<(Option<usize>, Option<BarStatus>, &indicatif::ProgressBar)> = state
+            .members
+            .iter()
+            .map(|member| {
+                let status = member.slot.state().map(|state| state.status);
+                (member.group, status, &member.progress)
+            })
+            .collect();
+
+        let mut ordered: Vec<&(Option<usize>, Option<BarStatus>, &indicatif::ProgressBar)> =
+            members.iter().collect();
+        if state.order == BarOrder::State {
+            // stable, so insertion order is kept within a rank
+            ordered.sort_by_key(|(_, status, _)| rank(*status));
+        }
+
+        let ungrouped = ordered.iter().filter(|(group, _, _)| group.is_none());
+        // each bar with whether it is queued, headers count as shown
+        let mut sequence: Vec<(&indicatif::ProgressBar, bool)> = ungrouped
+            .map(|(_, status, progress)| (*progress, rank(*status) == 1))
+            .collect();
+        for (index, group) in state.groups.iter().enumerate() {
+            let in_group: Vec<_> = ordered
+                .iter()
+                .filter(|(member_group, _, _)| *member_group == Some(index))
+                .collect();
+            let count = |ranked: usize| {
+                in_group
+                    .iter()
+                    .filter(|(_, status, _)| rank(*status) == ranked)
+                    .count()
+            };
+            group.header.set_message(format!(
+                "{} {}",
+                group.name.bold(),
+                format!(
+                    "({} running, {} queued, {}/{} done)",
+                    count(0),
+                    count(1),
+                    count(2),
+                    in_group.len()
+                )
+                .dimmed()
+            ));
+            sequence.push((&group.header, false));
+            sequence.extend(
+                in_group
+                    .iter()
+                    .map(|(_, status, progress)| (*progress, rank(*status) == 1)),
+            );
+        }
+
+        // running and finished bars always show, queued ones fill what is
+        // left of the window in order
+        let mut queued_slots = match state.window.as_ref() {
+            Some(window) => window.max_visible.saturating_sub(
+                members
+                    .iter()
+                    .filter(|(_, status, _)| rank(*status) != 1)
+                    .count(),
+            ),
+            None => usize::MAX,
+        };
+        let mut hidden = 0;
+        for (progress, _) in sequence.iter() {
+            self.multi_progress.remove(progress);
+        }
+        for (progress, is_queued) in sequence {
+            if is_queued {
+                if queued_slots == 0 {
+                    hidden += 1;
+                    continue;
+                }
+                queued_slots -= 1;
+            }
+            self.multi_progress.add(progress.clone()).tick();
+        }
+
+        if let Some(window) = state.window.as_ref() {
+            self.multi_progress.remove(&window.footer);
+            if hidden > 0 {
+                window
+                    .footer
+                    .set_message(format!("… and {hidden} queued").dimmed().to_string());
+                self.multi_progress.add(window.footer.clone()).tick();
+            }
+        }
+    }
+
+    // Lines logged by a bar hidden by the window go above all the bars
+    pub(crate) fn println(&self, message: &str) -> bool {
+        self.is_drawn && self.multi_progress.println(message).is_ok()
+    }
+}
+
+impl MultiProgress<'_> {
+    fn arrangement(&mut self) -> Arc<Arrangement> {
+        if let Some(arrangement) = self.arrangement.as_ref() {
+            return arrangement.clone();
+        }
+        let is_drawn = self.printer.verbosity.is_show_progress_bars
+            && self.printer.render_mode == crate::RenderMode::Interactive;
+        #[cfg(feature = "tui")]
+        let is_drawn = is_drawn && self.tui.is_none();
+        let arrangement = Arc::new(Arrangement::new(&self.multi_progress, is_drawn));
+        if let Some(grid) = self.grid.as_ref() {
+            arrangement.set_grid(grid.clone());
+        }
+        self.arrangement = Some(arrangement.clone());
+        arrangement
+    }
+
+    // Bars added after this are kept in `order`
+    pub fn set_order(&mut self, order: BarOrder) {
+        let arrangement = self.arrangement();
+        arrangement.state.lock().unwrap().order = order;
+        arrangement.rearrange();
+    }
+
+    // Shows at most `max_visible` bars. Running and finished bars are always
+    // shown, queued bars past the limit are summed up in a footer line.
+    pub fn set_max_visible_bars(&mut self, max_visible: usize) {
+        let arrangement = self.arrangement();
+        {
+            let mut state = arrangement.state.lock().unwrap();
+            match state.window.as_mut() {
+                Some(window) => window.max_visible = max_visible,
+                None => {
+                    state.window = Some(Window {
+                        max_visible,
+                        footer: text_line(),
+                    })
+                }
+            }
+        }
+        arrangement.rearrange();
+    }
+
+    // Adds a header line that bars added with add_progress_to_group are listed
+    // under, with counts of their states
+    pub fn add_group(&mut self, name: &str) -> GroupId {
+        let arrangement = self.arrangement();
+        let header = text_line();
+        let group = {
+            let mut state = arrangement.state.lock().unwrap();
+            state.groups.push(Group {
+                name: name.to_string(),
+                header,
+            });
+            GroupId(state.groups.len() - 1)
+        };
+        arrangement.rearrange();
+        group
+    }
+
+    pub fn add_progress_to_group(
+        &mut self,
+        group: GroupId,
+        prefix: &str,
+        total: Option<u64>,
+        finish_message: Option<&str>,
+    ) -> MultiProgressBar {
+        self.arrangement();
+        let mut progress_bar = self.add_progress(prefix, total, finish_message);
+        self.arrange(&mut progress_bar, Some(group.0));
+        progress_bar
+    }
+
+    // A bar waiting for a free worker. It shows as queued until it is started
+    // or reports progress.
+    pub fn add_queued_progress(
+        &mut self,
+        prefix: &str,
+        total: Option<u64>,
+        finish_message: Option<&str>,
+    ) -> MultiProgressBar {
+        let mut progress_bar = self.add_progress(prefix, total, finish_message);
+        progress_bar.enter_queue();
+        progress_bar
+    }
+
+    pub(crate) fn arrange(&mut self, progress_bar: &mut MultiProgressBar, group: Option<usize>) {
+        let Some(arrangement) = self.arrangement.clone() else {
+            return;
+        };
+        let (Some(slot), Some(progress)) = (
+            progress_bar.snapshot_slot.clone(),
+            progress_bar.progress.clone(),
+        ) else {
+            return;
+        };
+        {
+            let mut state = arrangement.state.lock().unwrap();
+            match state
+                .members
+                .iter_mut()
+                .find(|member| Arc::ptr_eq(&member.slot, &slot))
+            {
+                Some(member) => member.group = group,
+                None => state.members.push(Member {
+                    group,
+                    slot,
+                    progress,
+                }),
+            }
+        }
+        progress_bar.arrangement = Some(arrangement.clone());
+        arrangement.rearrange();
+    }
+}
+
+impl MultiProgressBar {
+    pub(crate) fn enter_queue(&mut self) {
+        self.enter_queue_with("queued");
+    }
+
+    // `reason` is shown dimmed until the bar starts, e.g. what it waits for
+    pub(crate) fn enter_queue_with(&mut self, reason: &str) {
+        self.is_queued = true;
+        if let Some(slot) = self.snapshot_slot.as_ref() {
+            slot.is_queued.store(true, Ordering::Relaxed);
+        }
+        if let Some(progress) = self.progress.as_ref() {
+            let _lock = self.lock.lock().unwrap();
+            progress.set_message(reason.dimmed().to_string());
+        }
+        self.rearrange();
+    }
+
+    // Takes the bar out of the queue; its elapsed time starts from here
+    pub fn start(&mut self) {
+        if !self.is_queued {
+            return;
+        }
+        self.is_queued = false;
+        if let Some(slot) = self.snapshot_slot.as_ref() {
+            slot.is_queued.store(false, Ordering::Relaxed);
+        }
+        if let Some(progress) = self.progress.as_ref() {
+            let _lock = self.lock.lock().unwrap();
+            progress.reset_elapsed();
+            progress.reset_eta();
+            progress.set_message(String::new());
+        }
+        self.rearrange();
+    }
+
+    pub(crate) fn rearrange(&self) {
+        if let Some(arrangement) = self.arrangement.as_ref() {
+            arrangement.rearrange();
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{ExecuteOptions, Printer};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, Write},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// One executed command. Times are milliseconds since the unix epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub label: Arc<str>,
+    pub command: Arc<str>,
+    pub arguments: Vec<Arc<str>>,
+    pub working_directory: Option<Arc<str>>,
+    pub environment_hash: String,
+    pub started_at: u64,
+    pub ended_at: u64,
+    // None when the process was killed by a signal or cancelled
+    pub exit_code: Option<i32>,
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+// FNV-1a is stable across toolchains, unlike std's DefaultHasher, so hashes
+// from different machines and builds can be compared
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// The 128 bit variant names cache entries, where a collision would replay the
+// output of another command. Fields are length prefixed so ("ab", "c") and
+// ("a", "bc") differ.
+pub(crate) struct Fnv1a128 {
+    hash: u128,
+}
+
+impl Fnv1a128 {
+    pub(crate) fn new() -> Self {
+        Self {
+            hash: 0x6c62272e07bb014262b821756295c58d,
+        }
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        self.hash = (bytes.len() as u64)
+            .to_le_bytes()
+            .iter()
+            .chain(bytes.iter())
+            .fold(self.hash, |hash, byte| {
+                (hash ^ *byte as u128).wrapping_mul(0x0000000001000000000000000000013b)
+            });
+    }
+
+    pub(crate) fn finish(&self) -> u128 {
+        self.hash
+    }
+}
+
+// Hash of the environment the child actually saw, sorted so the order
+// variables were set in doesn't matter. Values are never written to the audit.
+fn environment_hash(options: &ExecuteOptions) -> String {
+    let mut environment = std::collections::BTreeMap::new();
+    if !options.clear_environment {
+        environment.extend(std::env::vars_os());
+    }
+    for (key, value) in options.environment.iter() {
+        environment.insert(key.as_ref().into(), value.as_ref().into());
+    }
+    let hash = environment
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, (key, value)| {
+            let hash = fnv1a(hash, key.as_encoded_bytes());
+            let hash = fnv1a(hash, b"=");
+            let hash = fnv1a(hash, value.as_encoded_bytes());
+            fnv1a(hash, b"\0")
+        });
+    format!("{hash:016x}")
+}
+
+impl AuditRecord {
+    pub(crate) fn new(
+        command: &str,
+        options: &ExecuteOptions,
+        started_at: SystemTime,
+        exit_code: Option<i32>,
+    ) -> Self {
+        let working_directory = options.get_working_directory().or_else(|| {
+            std::env::current_dir()
+                .ok()
+                .map(|directory| directory.to_string_lossy().into())
+        });
+        Self {
+            label: options.label.clone(),
+            command: command.into(),
+            arguments: options.get_arguments(),
+            working_directory,
+            environment_hash: environment_hash(options),
+            started_at: unix_millis(started_at),
+            ended_at: unix_millis(SystemTime::now()),
+            exit_code,
+        }
+    }
+}
+
+pub(crate) struct AuditLog {
+    file: Mutex<std::fs::File>,
+}
+
+impl AuditLog {
+    pub(crate) fn record(&self, record: &AuditRecord) {
+        if let Ok(mut line) = serde_json::to_string(record) {
+            line.push('\n');
+            // a single write per line keeps lines whole when several
+            // processes append to the same file
+            let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+        }
+    }
+}
+
+pub(crate) fn record(
+    audit: Option<&Arc<AuditLog>>,
+    command: &str,
+    options: &ExecuteOptions,
+    started_at: SystemTime,
+    exit_code: Option<i32>,
+) {
+    if let Some(audit) = audit {
+        audit.record(&AuditRecord::new(command, options, started_at, exit_code));
+    }
+}
+
+pub fn read_audit_log(path: &std::path::Path) -> anyhow::Result<Vec<AuditRecord>> {
+    let file =
+        std::fs::File::open(path).context(format_context!("while opening {}", path.display()))?;
+    let mut records = Vec::new();
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line.context(format_context!("while reading {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(line.as_str()).context(format_context!(
+            "while parsing {}:{}",
+            path.display(),
+            index + 1
+        ))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+impl Printer {
+    // Appends a JSON line for every command executed from here on. The file is
+    // never truncated and is separate from the human readable logs.
+    pub fn enable_audit_log(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(format_context!("while opening {}", path.display()))?;
+        self.audit = Some(Arc::new(AuditLog {
+            file: Mutex::new(file),
+        }));
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+#[cfg(feature = "progress")]
+use crate::preset::Preset;
+#[cfg(feature = "process")]
+use crate::{cache::ProcessCache, ExecuteOptions, Level, LineDiscipline};
+use crate::{
+    capabilities::Capabilities,
+    ci,
+    theme::{Palette, Theme},
+    HeadingStyle, IndentStyle, ObjectOrder, Printer, RenderMode, Verbosity,
+};
+#[cfg(feature = "process")]
+use std::sync::Arc;
+
+pub struct PrinterBuilder {
+    printer: Printer,
+}
+
+impl PrinterBuilder {
+    pub fn new_stdout() -> Self {
+        Self {
+            printer: Printer::new_stdout(),
+        }
+    }
+
+    pub fn new_null_term() -> Self {
+        Self {
+            printer: Printer::new_null_term(),
+        }
+    }
+
+    pub fn from_writer<W: std::io::Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            printer: Printer::from_writer(writer),
+        }
+    }
+
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.printer.verbosity = verbosity;
+        self
+    }
+
+    pub fn render_mode(mut self, render_mode: RenderMode) -> Self {
+        self.printer.render_mode = render_mode;
+        self
+    }
+
+    #[cfg(feature = "progress")]
+    pub fn progress_preset(mut self, preset: Preset) -> Self {
+        self.printer.progress_preset = preset;
+        self
+    }
+
+    pub fn indent_style(mut self, indent_style: IndentStyle) -> Self {
+        self.printer.indent_style = indent_style;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.printer.set_theme(theme);
+        self
+    }
+
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.printer.set_theme(palette.theme());
+        self
+    }
+
+    pub fn heading_style(mut self, heading_style: HeadingStyle) -> Self {
+        self.printer.heading_style = heading_style;
+        self
+    }
+
+    pub fn object_order(mut self, object_order: ObjectOrder) -> Self {
+        self.printer.object_order = object_order;
+        self
+    }
+
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.printer.set_capabilities(capabilities);
+        self
+    }
+
+    pub fn path_root<Root: Into<std::path::PathBuf>>(mut self, root: Root) -> Self {
+        self.printer.set_path_root(root);
+        self
+    }
+
+    pub fn ci_adapter(mut self, adapter: Box<dyn ci::CiAdapter>) -> Self {
+        self.printer.set_ci_adapter(adapter);
+        self
+    }
+
+    pub fn detect_ci(mut self) -> Self {
+        if let Some(adapter) = ci::detect() {
+            self.printer.set_ci_adapter(adapter);
+        }
+        self
+    }
+
+    pub fn build(self) -> Printer {
+        self.printer
+    }
+}
+
+#[cfg(feature = "process")]
+#[derive(Default)]
+pub struct ExecuteOptionsBuilder {
+    options: ExecuteOptions,
+}
+
+#[cfg(feature = "process")]
+impl ExecuteOptionsBuilder {
+    pub fn label(mut self, label: &str) -> Self {
+        self.options.label = label.into();
+        self
+    }
+
+    pub fn arg<Argument: Into<std::ffi::OsString>>(mut self, argument: Argument) -> Self {
+        self.options.add_argument(argument);
+        self
+    }
+
+    pub fn args<Arguments, Argument>(mut self, arguments: Arguments) -> Self
+    where
+        Arguments: IntoIterator<Item = Argument>,
+        Argument: Into<std::ffi::OsString>,
+    {
+        for argument in arguments {
+            self.options.add_argument(argument);
+        }
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.options.environment.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn clear_env(mut self) -> Self {
+        self.options.clear_environment = true;
+        self
+    }
+
+    pub fn cwd<Directory: Into<std::path::PathBuf>>(mut self, directory: Directory) -> Self {
+        self.options.set_working_directory(directory);
+        self
+    }
+
+    pub fn capture_stdout(mut self) -> Self {
+        self.options.is_return_stdout = true;
+        self
+    }
+
+    pub fn log_to<Path: AsRef<std::path::Path>>(mut self, path: Path) -> Self {
+        self.options.log_file_path = Some(path.as_ref().to_string_lossy().into());
+        self
+    }
+
+    pub fn append_log(mut self) -> Self {
+        self.options.is_append_log = true;
+        self
+    }
+
+    pub fn log_level(mut self, level: Level) -> Self {
+        self.options.log_level = Some(level);
+        self
+    }
+
+    pub fn line_discipline(mut self, line_discipline: LineDiscipline) -> Self {
+        self.options.line_discipline = line_discipline;
+        self
+    }
+
+    pub fn on_started(mut self, callback: fn(&str, u32)) -> Self {
+        self.options.process_started_with_id = Some(callback);
+        self
+    }
+
+    pub fn cache(mut self, cache: Arc<ProcessCache>) -> Self {
+        self.options.cache = Some(cache);
+        self
+    }
+
+    // a file or directory whose contents the output depends on
+    pub fn cache_input<Input: Into<std::path::PathBuf>>(mut self, input: Input) -> Self {
+        self.options.cache_inputs.push(input.into());
+        self
+    }
+
+    // an inherited variable the output depends on
+    pub fn cache_env(mut self, name: &str) -> Self {
+        self.options.cache_environment.push(name.into());
+        self
+    }
+
+    // the child is told it runs under a printer and may drive the bar with
+    // progress records
+    pub fn child_protocol(mut self) -> Self {
+        self.options.is_child_protocol = true;
+        self
+    }
+
+    pub fn build(self) -> ExecuteOptions {
+        self.options
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{audit::Fnv1a128, log_file, ExecuteOptions, MultiProgressBar, Printer};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub stores: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    command: String,
+    stdout: String,
+    created: u64,
+}
+
+// Results of successful runs stored as one JSON file per key. The key covers
+// the command, its arguments and working directory, the explicit environment,
+// the inherited variables named in `cache_environment` and the contents of
+// every path in `cache_inputs`. Changing any of them is a miss, so entries
+// never need to be invalidated by hand. Failed runs are not stored and are
+// always retried.
+#[derive(Debug)]
+pub struct ProcessCache {
+    directory: PathBuf,
+    max_age: Option<Duration>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    stores: AtomicUsize,
+}
+
+impl ProcessCache {
+    pub fn new<Directory: Into<PathBuf>>(directory: Directory) -> Self {
+        Self {
+            directory: directory.into(),
+            max_age: None,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            stores: AtomicUsize::new(0),
+        }
+    }
+
+    // entries older than this are treated as misses and replaced
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            stores: self.stores.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.directory.exists() {
+            std::fs::remove_dir_all(&self.directory).context(format_context!(
+                "while clearing {}",
+                self.directory.display()
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn key(&self, command: &str, options: &ExecuteOptions) -> anyhow::Result<String> {
+        // counts keep a field of one list from passing as the next one
+        let mut hasher = Fnv1a128::new();
+        hasher.write(command.as_bytes());
+        hasher.write(&(options.arguments.len() as u64).to_le_bytes());
+        for argument in options.arguments.iter() {
+            hasher.write(argument.as_encoded_bytes());
+        }
+        hasher.write(&[options.working_directory.is_some() as u8]);
+        if let Some(directory) = options.working_directory.as_ref() {
+            hasher.write(directory.as_os_str().as_encoded_bytes());
+        }
+        hasher.write(&[options.clear_environment as u8]);
+        hasher.write(&(options.environment.len() as u64).to_le_bytes());
+        for (name, value) in options.environment.iter() {
+            hasher.write(name.as_bytes());
+            hasher.write(value.as_bytes());
+        }
+        for name in options.cache_environment.iter() {
+            hasher.write(name.as_bytes());
+            let value = std::env::var_os(name.as_ref());
+            hasher.write(&[value.is_some() as u8]);
+            if let Some(value) = value {
+                hasher.write(value.as_encoded_bytes());
+            }
+        }
+        for input in options.cache_inputs.iter() {
+            hash_path(input, &mut hasher)
+                .context(format_context!("while hashing {}", input.display()))?;
+        }
+        Ok(format!("{:032x}", hasher.finish()))
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{key}.json"))
+    }
+
+    fn lookup(&self, key: &str) -> Option<CacheEntry> {
+        let content = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(content.as_str()).ok()?;
+        let is_expired = self.max_age.is_some_and(|max_age| {
+            let created = UNIX_EPOCH + Duration::from_secs(entry.created);
+            SystemTime::now()
+                .duration_since(created)
+                .is_ok_and(|age| age > max_age)
+        });
+        (!is_expired).then_some(entry)
+    }
+
+    fn store(&self, key: &str, entry: &CacheEntry) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.directory).context(format_context!(
+            "while creating {}",
+            self.directory.display()
+        ))?;
+        let content = serde_json::to_string(entry).context(format_context!(""))?;
+        let path = self.entry_path(key);
+        std::fs::write(&path, content)
+            .context(format_context!("while writing {}", path.display()))?;
+        self.stores.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // Replays a stored run or executes and stores it. stdout is always
+    // captured so a hit can return it later.
+    pub(crate) fn run<Execute>(
+        &self,
+        command: &str,
+        options: &ExecuteOptions,
+        progress_bar: &mut MultiProgressBar,
+        execute: Execute,
+    ) -> anyhow::Result<Option<String>>
+    where
+        Execute: FnOnce(&mut MultiProgressBar, ExecuteOptions) -> anyhow::Result<Option<String>>,
+    {
+        let key = self.key(command, options).context(format_context!(
+            "while computing the cache key of {command}"
+        ))?;
+        if let Some(entry) = self.lookup(key.as_str()) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            replay(command, options, progress_bar, entry.stdout.as_str()).context(
+                format_context!("while replaying the cached output of {command}"),
+            )?;
+            progress_bar
+                .set_message(format!("cached: {}", options.get_full_command(command)).as_str());
+            return Ok(options.is_return_stdout.then_some(entry.stdout));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let mut capturing = options.clone();
+        capturing.is_return_stdout = true;
+        capturing.cache = None;
+        let stdout = execute(progress_bar, capturing)?.unwrap_or_default();
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        let entry = CacheEntry {
+            command: options.get_full_command(command),
+            stdout,
+            created,
+        };
+        // a cache that cannot be written only costs a rerun next time
+        let _ = self.store(key.as_str(), &entry);
+        Ok(options.is_return_stdout.then_some(entry.stdout))
+    }
+}
+
+impl Printer {
+    pub fn cache_report(&mut self, cache: &ProcessCache) -> anyhow::Result<CacheStats> {
+        let stats = cache.stats();
+        self.info(
+            "Process cache",
+            &format!(
+                "{} hits, {} misses, {} stored",
+                stats.hits, stats.misses, stats.stores
+            ),
+        )
+        .context(format_context!(""))?;
+        Ok(stats)
+    }
+}
+
+// A hit shows the stored stdout the way a run would have, on the terminal at
+// the configured level and in the command's log file
+fn replay(
+    command: &str,
+    options: &ExecuteOptions,
+    progress_bar: &mut MultiProgressBar,
+    stdout: &str,
+) -> anyhow::Result<()> {
+    for line in stdout.lines() {
+        let message = crate::format_monitor_log_message("stdout", command, line);
+        if let Some(level) = options.log_level {
+            progress_bar.log(level, message.as_str());
+        } else {
+            progress_bar.log_passthrough(message.as_str());
+        }
+    }
+    if let Some(log_path) = options.log_file_path.as_ref() {
+        let mut writer = log_file::LogWriter::open(log_path.as_ref(), options.is_append_log)
+            .context(format_context!(""))?;
+        let header = format!("cached: {}\n\n", options.get_full_command(command));
+        writer
+            .write_all(header.as_bytes())
+            .and_then(|()| writer.write_all(stdout.as_bytes()))
+            .context(format_context!("while writing {log_path}"))?;
+    }
+    Ok(())
+}
+
+// files hash their contents, directories their sorted entries
+fn hash_path(path: &Path, hasher: &mut Fnv1a128) -> anyhow::Result<()> {
+    hasher.write(path.as_os_str().as_encoded_bytes());
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+            .context(format_context!("while reading {}", path.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        entries.sort();
+        for entry in entries.iter() {
+            hash_path(entry, hasher)?;
+        }
+    } else if path.exists() {
+        let content =
+            std::fs::read(path).context(format_context!("while reading {}", path.display()))?;
+        hasher.write(content.as_slice());
+    } else {
+        // a missing input is a state of its own and hits once it is missing again
+        hasher.write(b"missing");
+    }
+    Ok(())
+}
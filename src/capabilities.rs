@@ -0,0 +1,212 @@
+use crate::{theme::ColorDepth, Printer};
+use serde::{Deserialize, Serialize};
+use std::{io::IsTerminal, sync::Arc};
+
+// Each probe can be forced with 1/true/on or 0/false/off
+pub const TRUECOLOR_ENV_VAR: &str = "PRINTER_TRUECOLOR";
+pub const ANSI256_ENV_VAR: &str = "PRINTER_ANSI256";
+pub const UNICODE_ENV_VAR: &str = "PRINTER_UNICODE";
+pub const HYPERLINKS_ENV_VAR: &str = "PRINTER_HYPERLINKS";
+pub const CURSOR_CONTROL_ENV_VAR: &str = "PRINTER_CURSOR_CONTROL";
+
+// What the attached terminal is known to support. Detection is conservative:
+// a feature is only reported when the environment advertises it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub is_truecolor: bool,
+    pub is_ansi256: bool,
+    pub is_unicode: bool,
+    pub is_hyperlinks: bool,
+    pub is_cursor_control: bool,
+}
+
+// Writers that are not terminals keep unicode and the 256 color palette
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            is_truecolor: false,
+            is_ansi256: true,
+            is_unicode: true,
+            is_hyperlinks: false,
+            is_cursor_control: false,
+        }
+    }
+}
+
+// Symbols drawn by gauges, checklists, spinners, grids and guides. Terminals
+// without unicode get ASCII stand-ins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Glyphs {
+    pub(crate) done: &'static str,
+    pub(crate) failed: &'static str,
+    pub(crate) pending: &'static str,
+    pub(crate) paused: &'static str,
+    pub(crate) queued: &'static str,
+    pub(crate) gauge_filled: &'static str,
+    pub(crate) gauge_empty: &'static str,
+    pub(crate) spinner: &'static [&'static str],
+    pub(crate) tree_branch: &'static str,
+    pub(crate) tree_last: &'static str,
+    pub(crate) tree_guide: &'static str,
+    pub(crate) indent_guide: &'static str,
+}
+
+impl Glyphs {
+    pub(crate) const UNICODE: Glyphs = Glyphs {
+        done: "✓",
+        failed: "✗",
+        pending: "○",
+        paused: "‖",
+        queued: "·",
+        gauge_filled: "▓",
+        gauge_empty: "░",
+        spinner: &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+        tree_branch: "├── ",
+        tree_last: "└── ",
+        tree_guide: "│   ",
+        indent_guide: "│",
+    };
+
+    pub(crate) const ASCII: Glyphs = Glyphs {
+        done: "v",
+        failed: "x",
+        pending: "o",
+        paused: "=",
+        queued: ".",
+        gauge_filled: "#",
+        gauge_empty: "-",
+        spinner: &["|", "/", "-", "\\"],
+        tree_branch: "|-- ",
+        tree_last: "`-- ",
+        tree_guide: "|   ",
+        indent_guide: "|",
+    };
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs::UNICODE
+    }
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        Self::detect_with(
+            |name| std::env::var(name).ok(),
+            std::io::stdout().is_terminal(),
+        )
+    }
+
+    pub(crate) fn detect_with(var: impl Fn(&str) -> Option<String>, is_term: bool) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let term_program = var("TERM_PROGRAM").unwrap_or_default();
+        let is_dumb = term == "dumb";
+        let is_windows_terminal = var("WT_SESSION").is_some();
+        let is_modern_program = matches!(
+            term_program.as_str(),
+            "iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty"
+        );
+
+        let is_truecolor = var("COLORTERM")
+            .map(|value| matches!(value.as_str(), "truecolor" | "24bit"))
+            .unwrap_or(false)
+            || is_windows_terminal
+            || is_modern_program
+            || term.ends_with("-direct");
+
+        // conhost has supported the 256 color palette since Windows 10
+        let is_ansi256 = is_truecolor || term.contains("256color") || cfg!(windows);
+
+        // the legacy Windows console has no UTF-8 fonts unless it is hosted
+        // by a newer terminal
+        let is_unicode = if cfg!(windows) {
+            is_windows_terminal || is_modern_program || var("ConEmuANSI").is_some()
+        } else {
+            !is_dumb
+                && term != "linux"
+                && ["LC_ALL", "LC_CTYPE", "LANG"]
+                    .iter()
+                    .find_map(|name| var(name).filter(|value| !value.is_empty()))
+                    .map(|locale| {
+                        let locale = locale.to_lowercase();
+                        locale.contains("utf-8") || locale.contains("utf8")
+                    })
+                    .unwrap_or(false)
+        };
+
+        // VTE added OSC 8 in 0.50, reported as 5000
+        let is_hyperlinks = is_term
+            && var("CI").is_none()
+            && (is_windows_terminal
+                || is_modern_program
+                || var("KONSOLE_VERSION").is_some()
+                || var("DOMTERM").is_some()
+                || var("VTE_VERSION")
+                    .and_then(|version| version.parse::<u32>().ok())
+                    .is_some_and(|version| version >= 5000));
+
+        let is_cursor_control = is_term && !is_dumb;
+
+        let force = |name: &str, detected: bool| {
+            var(name)
+                .and_then(|value| parse_switch(value.as_str()))
+                .unwrap_or(detected)
+        };
+        Self {
+            is_truecolor: force(TRUECOLOR_ENV_VAR, is_truecolor),
+            is_ansi256: force(ANSI256_ENV_VAR, is_ansi256),
+            is_unicode: force(UNICODE_ENV_VAR, is_unicode),
+            is_hyperlinks: force(HYPERLINKS_ENV_VAR, is_hyperlinks),
+            is_cursor_control: force(CURSOR_CONTROL_ENV_VAR, is_cursor_control),
+        }
+    }
+
+    pub(crate) fn glyphs(&self) -> Glyphs {
+        if self.is_unicode {
+            Glyphs::UNICODE
+        } else {
+            Glyphs::ASCII
+        }
+    }
+
+    pub fn color_depth(&self) -> ColorDepth {
+        if self.is_truecolor {
+            ColorDepth::Truecolor
+        } else if self.is_ansi256 {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "on" | "yes" => Some(true),
+        "0" | "false" | "off" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+impl Printer {
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    // colors already dropped to a lower depth are not restored
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+        self.theme = Arc::new(self.theme.supported(&capabilities));
+    }
+
+    // OSC 8 link where the terminal renders them, otherwise `text (url)`
+    pub fn hyperlink(&self, text: &str, url: &str) -> String {
+        if self.capabilities.is_hyperlinks {
+            format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+        } else if text == url {
+            text.to_string()
+        } else {
+            format!("{text} ({url})")
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::Printer;
+
+pub(crate) struct CaptureState {
+    pub(crate) buffer: String,
+    // also write to the terminal
+    pub(crate) is_tee: bool,
+}
+
+impl Printer {
+    // Runs `function` with everything written through the printer going into
+    // the returned string instead of the terminal. Progress bars still draw on
+    // the terminal.
+    pub fn capture<Return, Function: FnOnce(&mut Printer) -> Return>(
+        &mut self,
+        function: Function,
+    ) -> (Return, String) {
+        self.capture_with(false, function)
+    }
+
+    // Like capture but the output also goes where it would have gone without
+    // it: the terminal or an enclosing capture
+    pub fn capture_and_print<Return, Function: FnOnce(&mut Printer) -> Return>(
+        &mut self,
+        function: Function,
+    ) -> (Return, String) {
+        self.capture_with(true, function)
+    }
+
+    fn capture_with<Return, Function: FnOnce(&mut Printer) -> Return>(
+        &mut self,
+        is_tee: bool,
+        function: Function,
+    ) -> (Return, String) {
+        self.capture_stack.push(CaptureState {
+            buffer: String::new(),
+            is_tee,
+        });
+        let result = function(self);
+        let state = self
+            .capture_stack
+            .pop()
+            .expect("Internal Error: capture stack is empty");
+        (result, state.buffer)
+    }
+}
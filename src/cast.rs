@@ -0,0 +1,158 @@
+use crate::session::{read_session, RecordedEvent, SessionEvent};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::Serialize;
+use std::io::Write;
+
+const CAST_BAR_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CastOptions {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Default for CastOptions {
+    fn default() -> Self {
+        Self {
+            width: 120,
+            height: 32,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CastHeader {
+    version: u8,
+    width: u16,
+    height: u16,
+}
+
+struct CastBar {
+    id: usize,
+    prefix: String,
+    message: String,
+    position: u64,
+    total: Option<u64>,
+}
+
+impl CastBar {
+    fn render(&self) -> String {
+        let bar = match self.total {
+            Some(total) if total > 0 => {
+                let filled = (self.position.min(total) as usize * CAST_BAR_WIDTH) / total as usize;
+                format!(
+                    "[{}{}] {}/{}",
+                    "#".repeat(filled),
+                    "-".repeat(CAST_BAR_WIDTH - filled),
+                    self.position,
+                    total
+                )
+            }
+            _ => "[*]".to_string(),
+        };
+        format!("{} {bar} {}", self.prefix, self.message)
+    }
+}
+
+// Turns recorded events into the bytes a terminal would have received. Live
+// bars are kept below the output and redrawn after every event.
+#[derive(Default)]
+struct CastRenderer {
+    bars: Vec<CastBar>,
+    drawn_lines: usize,
+}
+
+impl CastRenderer {
+    fn render(&mut self, event: SessionEvent) -> String {
+        let mut output = String::new();
+        if self.drawn_lines > 0 {
+            // back to the first bar line and clear everything below it
+            output.push_str(format!("\x1b[{}F\x1b[J", self.drawn_lines).as_str());
+        }
+        match event {
+            SessionEvent::Output { text } => output.push_str(text.replace('\n', "\r\n").as_str()),
+            SessionEvent::BarStarted { id, prefix, total } => self.bars.push(CastBar {
+                id,
+                prefix,
+                message: String::new(),
+                position: 0,
+                total,
+            }),
+            SessionEvent::BarMessage { id, message } => {
+                if let Some(bar) = self.bars.iter_mut().find(|bar| bar.id == id) {
+                    bar.message = message;
+                }
+            }
+            SessionEvent::BarProgress {
+                id,
+                position,
+                total,
+            } => {
+                if let Some(bar) = self.bars.iter_mut().find(|bar| bar.id == id) {
+                    bar.position = position;
+                    bar.total = total;
+                }
+            }
+            SessionEvent::BarFinished { id, .. } => {
+                // finished bars stay on screen above the live ones
+                if let Some(index) = self.bars.iter().position(|bar| bar.id == id) {
+                    let bar = self.bars.remove(index);
+                    output.push_str(format!("{}\r\n", bar.render()).as_str());
+                }
+            }
+        }
+        for bar in self.bars.iter() {
+            output.push_str(format!("{}\r\n", bar.render()).as_str());
+        }
+        self.drawn_lines = self.bars.len();
+        output
+    }
+}
+
+pub fn write_cast<Writer: Write>(
+    events: &[RecordedEvent],
+    options: CastOptions,
+    writer: &mut Writer,
+) -> anyhow::Result<()> {
+    let header = CastHeader {
+        version: 2,
+        width: options.width,
+        height: options.height,
+    };
+    writeln!(
+        writer,
+        "{}",
+        serde_json::to_string(&header).context(format_context!(""))?
+    )
+    .context(format_context!(""))?;
+
+    let mut renderer = CastRenderer::default();
+    for recorded in events {
+        let data = renderer.render(recorded.event.clone());
+        if data.is_empty() {
+            continue;
+        }
+        let seconds = recorded.at as f64 / 1000.0;
+        let line = serde_json::to_string(&(seconds, "o", data)).context(format_context!(""))?;
+        writeln!(writer, "{line}").context(format_context!(""))?;
+    }
+    Ok(())
+}
+
+// Converts a file written by Printer::record_session to an asciinema v2 recording
+pub fn export_cast(
+    session_path: &std::path::Path,
+    cast_path: &std::path::Path,
+    options: CastOptions,
+) -> anyhow::Result<()> {
+    let events = read_session(session_path).context(format_context!(""))?;
+    let file = std::fs::File::create(cast_path)
+        .context(format_context!("while creating {}", cast_path.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+    write_cast(&events, options, &mut writer)
+        .context(format_context!("while writing {}", cast_path.display()))?;
+    writer
+        .flush()
+        .context(format_context!("while writing {}", cast_path.display()))
+}
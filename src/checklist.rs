@@ -0,0 +1,153 @@
+use crate::{theme::Theme, Level, Printer, RenderMode};
+use anyhow_source_location::format_error;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::sync::{Arc, Mutex};
+
+const CHECKLIST_TICK: std::time::Duration = std::time::Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Skipped,
+}
+
+struct Step {
+    name: String,
+    state: StepState,
+}
+
+fn glyph(state: StepState, theme: &Theme, frame: usize) -> String {
+    let glyphs = &theme.glyphs;
+    match state {
+        StepState::Pending => glyphs
+            .pending
+            .if_supports_color(Stdout, |text| text.dimmed())
+            .to_string(),
+        StepState::Running => theme
+            .progress_bar
+            .paint(glyphs.spinner[frame % glyphs.spinner.len()]),
+        StepState::Done => theme.success.paint(glyphs.done),
+        StepState::Failed => theme.level_color(Level::Error).paint(glyphs.failed),
+        StepState::Skipped => "-"
+            .if_supports_color(Stdout, |text| text.dimmed())
+            .to_string(),
+    }
+}
+
+fn render_step(indent: &str, step: &Step, theme: &Theme, frame: usize) -> String {
+    let name = match step.state {
+        StepState::Pending | StepState::Skipped => step
+            .name
+            .if_supports_color(Stdout, |text| text.dimmed())
+            .to_string(),
+        StepState::Running => step
+            .name
+            .if_supports_color(Stdout, |text| text.bold())
+            .to_string(),
+        StepState::Done | StepState::Failed => step.name.clone(),
+    };
+    format!("{indent}{} {name}", glyph(step.state, theme, frame))
+}
+
+// An ordered list of steps declared up front. In a terminal the whole list
+// stays on screen and updates in place; otherwise each step is printed when
+// it ends.
+pub struct Checklist<'a> {
+    pub printer: &'a mut Printer,
+    steps: Arc<Mutex<Vec<Step>>>,
+    progress: Option<indicatif::ProgressBar>,
+}
+
+impl<'a> Checklist<'a> {
+    pub fn new(printer: &'a mut Printer, steps: &[&str]) -> Self {
+        let steps = Arc::new(Mutex::new(
+            steps
+                .iter()
+                .map(|name| Step {
+                    name: name.to_string(),
+                    state: StepState::Pending,
+                })
+                .collect::<Vec<_>>(),
+        ));
+        let is_live = printer.verbosity.is_show_progress_bars
+            && printer.render_mode == RenderMode::Interactive;
+        let progress = is_live.then(|| {
+            let render_steps = steps.clone();
+            let theme = printer.theme.clone();
+            let indent = printer.indentation();
+            let style = indicatif::ProgressStyle::with_template("{checklist}")
+                .unwrap()
+                .with_key(
+                    "checklist",
+                    move |state: &indicatif::ProgressState, writer: &mut dyn std::fmt::Write| {
+                        let frame =
+                            (state.elapsed().as_millis() / CHECKLIST_TICK.as_millis()) as usize;
+                        let lines: Vec<String> = render_steps
+                            .lock()
+                            .unwrap()
+                            .iter()
+                            .map(|step| render_step(indent.as_str(), step, &theme, frame))
+                            .collect();
+                        let _ = write!(writer, "{}", lines.join("\n"));
+                    },
+                );
+            let progress = indicatif::ProgressBar::new_spinner();
+            progress.set_style(style);
+            progress.enable_steady_tick(CHECKLIST_TICK);
+            // printer output goes above the list
+            *printer.spinner.lock().unwrap() = Some(progress.clone());
+            progress
+        });
+        Self {
+            printer,
+            steps,
+            progress,
+        }
+    }
+
+    pub fn set_state(&mut self, step: usize, state: StepState) -> anyhow::Result<()> {
+        let line = {
+            let mut steps = self.steps.lock().unwrap();
+            let count = steps.len();
+            let entry = steps
+                .get_mut(step)
+                .ok_or_else(|| format_error!("step {step} is out of range for {count} steps"))?;
+            entry.state = state;
+            render_step(
+                self.printer.indentation().as_str(),
+                entry,
+                &self.printer.theme,
+                0,
+            )
+        };
+        match self.progress.as_ref() {
+            Some(progress) => progress.tick(),
+            None => {
+                if matches!(
+                    state,
+                    StepState::Done | StepState::Failed | StepState::Skipped
+                ) {
+                    self.printer.write(format!("{line}\n").as_str())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn state(&self, step: usize) -> Option<StepState> {
+        self.steps.lock().unwrap().get(step).map(|step| step.state)
+    }
+}
+
+impl Drop for Checklist<'_> {
+    fn drop(&mut self) {
+        if let Some(progress) = self.progress.take() {
+            // leaves the final list on screen
+            progress.finish();
+            *self.printer.spinner.lock().unwrap() = None;
+        }
+    }
+}
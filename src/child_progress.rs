@@ -0,0 +1,226 @@
+use std::{sync::mpsc, time::Duration};
+
+// A child reports progress to the bar running it by writing lines like
+//
+//   @printer progress 42/100 msg=compiling core
+//
+// to the descriptor named in PRINTER_PROGRESS_FD. The count is `position` or
+// `position/total` and both parts are optional, the message runs to the end
+// of the line. Lines that don't parse are ignored.
+pub const PROGRESS_FD_ENV_VAR: &str = "PRINTER_PROGRESS_FD";
+
+const PROTOCOL_PREFIX: &str = "@printer progress";
+
+// the descriptor children see, after stdin, stdout and stderr
+#[cfg(unix)]
+const CHILD_PROGRESS_FD: i32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProgressUpdate {
+    pub position: Option<u64>,
+    pub total: Option<u64>,
+    pub message: Option<String>,
+}
+
+impl ProgressUpdate {
+    pub fn parse(line: &str) -> Option<Self> {
+        let rest = line.trim_end().strip_prefix(PROTOCOL_PREFIX)?;
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            return None;
+        }
+        let mut rest = rest.trim_start();
+        let mut update = ProgressUpdate::default();
+        if !rest.is_empty() && !rest.starts_with("msg=") {
+            let (count, remaining) = rest.split_once(' ').unwrap_or((rest, ""));
+            let (position, total) = match count.split_once('/') {
+                Some((position, total)) => (position, Some(total.parse().ok()?)),
+                None => (count, None),
+            };
+            update.position = Some(position.parse().ok()?);
+            update.total = total;
+            rest = remaining.trim_start();
+        }
+        if !rest.is_empty() {
+            update.message = Some(rest.strip_prefix("msg=")?.to_string());
+        }
+        Some(update)
+    }
+
+    fn apply(&self, progress_bar: &mut crate::MultiProgressBar) {
+        if let Some(total) = self.total {
+            progress_bar.set_total(total);
+        }
+        if let Some(position) = self.position {
+            progress_bar.set_position(position);
+        }
+        if let Some(message) = self.message.as_ref() {
+            progress_bar.set_message(message.as_str());
+        }
+    }
+}
+
+impl std::fmt::Display for ProgressUpdate {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{PROTOCOL_PREFIX}")?;
+        match (self.position, self.total) {
+            (Some(position), Some(total)) => write!(formatter, " {position}/{total}")?,
+            (Some(position), None) => write!(formatter, " {position}")?,
+            _ => {}
+        }
+        if let Some(message) = self.message.as_ref() {
+            // the protocol is line based
+            write!(formatter, " msg={}", message.replace(['\n', '\r'], " "))?;
+        }
+        Ok(())
+    }
+}
+
+// The child side of the protocol
+pub struct ProgressReporter {
+    file: std::fs::File,
+}
+
+impl ProgressReporter {
+    // None when the process was not started with a progress descriptor. The
+    // descriptor is taken over and the variable removed, so call it once and
+    // before starting threads that read the environment.
+    #[cfg(unix)]
+    pub fn from_env() -> Option<Self> {
+        use std::os::unix::io::FromRawFd;
+        let value = std::env::var(PROGRESS_FD_ENV_VAR).ok()?;
+        // children started without a descriptor of their own must not find
+        // the variable and write into whatever they have open as that number
+        std::env::remove_var(PROGRESS_FD_ENV_VAR);
+        let fd: i32 = value.trim().parse().ok()?;
+        // the parent always hands over a pipe, anything else is unrelated
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        if unsafe { libc::fstat(fd, &mut stat) } == -1
+            || stat.st_mode & libc::S_IFMT != libc::S_IFIFO
+        {
+            return None;
+        }
+        // not inherited any further, grandchildren get their own descriptor
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+            return None;
+        }
+        Some(Self {
+            file: unsafe { std::fs::File::from_raw_fd(fd) },
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_env() -> Option<Self> {
+        None
+    }
+
+    pub fn report(&mut self, update: &ProgressUpdate) -> std::io::Result<()> {
+        use std::io::Write;
+        writeln!(self.file, "{update}")
+    }
+}
+
+// The parent side: a pipe whose write end becomes the child's progress
+// descriptor. The parent's copy is closed by `listen` once the child has been
+// spawned, so the reader ends with the last process holding it.
+pub(crate) struct ProgressChannel {
+    #[cfg(unix)]
+    reader: std::io::PipeReader,
+    #[cfg(unix)]
+    writer: std::io::PipeWriter,
+}
+
+impl ProgressChannel {
+    #[cfg(unix)]
+    pub(crate) fn new() -> Option<Self> {
+        let (reader, writer) = std::io::pipe().ok()?;
+        Some(Self { reader, writer })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn new() -> Option<Self> {
+        None
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn attach(&self, process: &mut std::process::Command) {
+        use std::os::unix::{io::AsRawFd, process::CommandExt};
+        let fd = self.writer.as_raw_fd();
+        process.env(PROGRESS_FD_ENV_VAR, CHILD_PROGRESS_FD.to_string());
+        // runs in the forked child before exec, so only async-signal-safe calls
+        unsafe {
+            process.pre_exec(move || {
+                let result = if fd == CHILD_PROGRESS_FD {
+                    libc::fcntl(fd, libc::F_SETFD, 0)
+                } else {
+                    // the duplicate does not close on exec
+                    libc::dup2(fd, CHILD_PROGRESS_FD)
+                };
+                if result == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn attach(&self, _process: &mut std::process::Command) {}
+
+    #[cfg(unix)]
+    pub(crate) fn listen(self) -> ProgressUpdates {
+        use std::io::BufRead;
+        let Self { reader, writer } = self;
+        drop(writer);
+        let (sender, receiver) = mpsc::channel();
+        // not joined, a grandchild may keep the descriptor open for longer
+        std::thread::spawn(move || {
+            for line in std::io::BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Some(update) = ProgressUpdate::parse(line.as_str()) {
+                    if sender.send(update).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        ProgressUpdates {
+            receiver,
+            is_reported: false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn listen(self) -> ProgressUpdates {
+        let (_, receiver) = mpsc::channel();
+        ProgressUpdates {
+            receiver,
+            is_reported: false,
+        }
+    }
+}
+
+pub(crate) struct ProgressUpdates {
+    receiver: mpsc::Receiver<ProgressUpdate>,
+    is_reported: bool,
+}
+
+impl ProgressUpdates {
+    // Applies what arrived since the last call. Returns true once the child
+    // has reported at least once, the bar is then driven by the child only.
+    pub(crate) fn apply(&mut self, progress_bar: &mut crate::MultiProgressBar) -> bool {
+        for update in self.receiver.try_iter() {
+            update.apply(progress_bar);
+            self.is_reported = true;
+        }
+        self.is_reported
+    }
+
+    // the last lines may still be in flight when the child exits
+    pub(crate) fn finish(&mut self, progress_bar: &mut crate::MultiProgressBar) {
+        while let Ok(update) = self.receiver.recv_timeout(Duration::from_millis(100)) {
+            update.apply(progress_bar);
+        }
+    }
+}
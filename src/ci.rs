@@ -0,0 +1,252 @@
+use crate::Level;
+use std::{io::Write, sync::Mutex};
+
+pub struct SourceLocation<'a> {
+    pub file: &'a str,
+    pub line: usize,
+}
+
+// `depth` is the number of groups already open when a group starts, or still
+// open after it ends
+pub trait CiAdapter: Send + Sync {
+    fn annotate(
+        &self,
+        level: Level,
+        message: &str,
+        location: Option<&SourceLocation>,
+    ) -> Option<String>;
+    fn start_group(&self, name: &str, depth: usize) -> Option<String>;
+    fn end_group(&self, name: &str, depth: usize) -> Option<String>;
+}
+
+pub fn detect() -> Option<Box<dyn CiAdapter>> {
+    if std::env::var("GITHUB_ACTIONS")
+        .map(|value| value == "true")
+        .unwrap_or(false)
+    {
+        Some(Box::new(GithubActions))
+    } else if std::env::var_os("TEAMCITY_VERSION").is_some() {
+        Some(Box::new(TeamCity))
+    } else if std::env::var_os("GITLAB_CI").is_some() {
+        Some(Box::new(GitLab))
+    } else {
+        None
+    }
+}
+
+// CI service messages are parsed from the process stdout, so they bypass the
+// printer writer.
+pub(crate) struct Ci {
+    adapter: Box<dyn CiAdapter>,
+    open_groups: Mutex<OpenGroups>,
+}
+
+#[derive(Default)]
+struct OpenGroups {
+    next_id: u64,
+    groups: Vec<(u64, String)>,
+}
+
+impl Ci {
+    pub(crate) fn new(adapter: Box<dyn CiAdapter>) -> Self {
+        Self {
+            adapter,
+            open_groups: Mutex::new(OpenGroups::default()),
+        }
+    }
+
+    fn emit(&self, command: Option<String>) {
+        if let Some(command) = command {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "{command}");
+        }
+    }
+
+    pub(crate) fn annotate(&self, level: Level, message: &str, location: Option<&SourceLocation>) {
+        self.emit(self.adapter.annotate(level, message, location));
+    }
+
+    // the id closes this group even when groups end out of order
+    pub(crate) fn start_group(&self, name: &str) -> u64 {
+        let mut open_groups = self.open_groups.lock().unwrap();
+        self.emit(self.adapter.start_group(name, open_groups.groups.len()));
+        let id = open_groups.next_id;
+        open_groups.next_id += 1;
+        open_groups.groups.push((id, name.to_string()));
+        id
+    }
+
+    pub(crate) fn end_group(&self, id: u64) {
+        let mut open_groups = self.open_groups.lock().unwrap();
+        let Some(index) = open_groups
+            .groups
+            .iter()
+            .position(|(open_id, _)| *open_id == id)
+        else {
+            return;
+        };
+        let (_, name) = open_groups.groups.remove(index);
+        self.emit(
+            self.adapter
+                .end_group(name.as_str(), open_groups.groups.len()),
+        );
+    }
+}
+
+pub struct GithubActions;
+
+fn escape_github_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_github_property(value: &str) -> String {
+    escape_github_data(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+impl CiAdapter for GithubActions {
+    fn annotate(
+        &self,
+        level: Level,
+        message: &str,
+        location: Option<&SourceLocation>,
+    ) -> Option<String> {
+        let command = match level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            _ => "notice",
+        };
+        let properties = location
+            .map(|location| {
+                format!(
+                    " file={},line={}",
+                    escape_github_property(location.file),
+                    location.line
+                )
+            })
+            .unwrap_or_default();
+        Some(format!(
+            "::{command}{properties}::{}",
+            escape_github_data(message)
+        ))
+    }
+
+    // Actions can't nest groups so only the outermost scope opens one
+    fn start_group(&self, name: &str, depth: usize) -> Option<String> {
+        (depth == 0).then(|| format!("::group::{}", escape_github_data(name)))
+    }
+
+    fn end_group(&self, _name: &str, depth: usize) -> Option<String> {
+        (depth == 0).then(|| "::endgroup::".to_string())
+    }
+}
+
+pub struct TeamCity;
+
+fn escape_teamcity(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '|' => result.push_str("||"),
+            '\'' => result.push_str("|'"),
+            '\n' => result.push_str("|n"),
+            '\r' => result.push_str("|r"),
+            '[' => result.push_str("|["),
+            ']' => result.push_str("|]"),
+            _ => result.push(character),
+        }
+    }
+    result
+}
+
+impl CiAdapter for TeamCity {
+    fn annotate(
+        &self,
+        level: Level,
+        message: &str,
+        location: Option<&SourceLocation>,
+    ) -> Option<String> {
+        let status = match level {
+            Level::Error => "ERROR",
+            Level::Warning => "WARNING",
+            _ => "NORMAL",
+        };
+        let text = match location {
+            Some(location) => format!("{}:{}: {message}", location.file, location.line),
+            None => message.to_string(),
+        };
+        Some(format!(
+            "##teamcity[message text='{}' status='{status}']",
+            escape_teamcity(text.as_str())
+        ))
+    }
+
+    fn start_group(&self, name: &str, _depth: usize) -> Option<String> {
+        Some(format!(
+            "##teamcity[blockOpened name='{}']",
+            escape_teamcity(name)
+        ))
+    }
+
+    fn end_group(&self, name: &str, _depth: usize) -> Option<String> {
+        Some(format!(
+            "##teamcity[blockClosed name='{}']",
+            escape_teamcity(name)
+        ))
+    }
+}
+
+pub struct GitLab;
+
+fn gitlab_section_name(name: &str, depth: usize) -> String {
+    let name: String = name
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || character == '-' {
+                character.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{name}_{depth}")
+}
+
+fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+impl CiAdapter for GitLab {
+    // GitLab has no annotation syntax, the regular output is enough
+    fn annotate(
+        &self,
+        _level: Level,
+        _message: &str,
+        _location: Option<&SourceLocation>,
+    ) -> Option<String> {
+        None
+    }
+
+    fn start_group(&self, name: &str, depth: usize) -> Option<String> {
+        Some(format!(
+            "\x1b[0Ksection_start:{}:{}[collapsed=true]\r\x1b[0K{name}",
+            unix_timestamp(),
+            gitlab_section_name(name, depth)
+        ))
+    }
+
+    fn end_group(&self, name: &str, depth: usize) -> Option<String> {
+        Some(format!(
+            "\x1b[0Ksection_end:{}:{}\r\x1b[0K",
+            unix_timestamp(),
+            gitlab_section_name(name, depth)
+        ))
+    }
+}
@@ -0,0 +1,76 @@
+use indicatif::{FormattedDuration, HumanDuration, ProgressState, ProgressStyle};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// tracks time a bar spent paused so the elapsed keys only show working time
+#[derive(Debug, Default)]
+pub(crate) struct PauseClock {
+    state: Mutex<PauseState>,
+}
+
+#[derive(Debug, Default)]
+struct PauseState {
+    paused: Duration,
+    paused_at: Option<Instant>,
+}
+
+impl PauseClock {
+    pub(crate) fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused_at.is_some()
+    }
+
+    pub(crate) fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.paused_at.is_none() {
+            state.paused_at = Some(Instant::now());
+        }
+    }
+
+    pub(crate) fn resume(&self) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(paused_at) = state.paused_at.take() {
+            state.paused += paused_at.elapsed();
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.paused = Duration::ZERO;
+        state.paused_at = state.paused_at.map(|_| Instant::now());
+    }
+
+    pub(crate) fn paused_duration(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        state.paused
+            + state
+                .paused_at
+                .map(|paused_at| paused_at.elapsed())
+                .unwrap_or_default()
+    }
+
+    pub(crate) fn working_time(&self, elapsed: Duration) -> Duration {
+        elapsed.saturating_sub(self.paused_duration())
+    }
+
+    pub(crate) fn attach(self: &Arc<Self>, style: ProgressStyle) -> ProgressStyle {
+        let precise = self.clone();
+        let human = self.clone();
+        style
+            .with_key(
+                "elapsed_precise",
+                move |state: &ProgressState, writer: &mut dyn std::fmt::Write| {
+                    let elapsed = precise.working_time(state.elapsed());
+                    let _ = write!(writer, "{}", FormattedDuration(elapsed));
+                },
+            )
+            .with_key(
+                "elapsed",
+                move |state: &ProgressState, writer: &mut dyn std::fmt::Write| {
+                    let elapsed = human.working_time(state.elapsed());
+                    let _ = write!(writer, "{:#}", HumanDuration(elapsed));
+                },
+            )
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{
+    format,
+    text::{Table, TreeNode},
+    Printer,
+};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnsOptions {
+    pub gap: usize,
+    pub max_columns: Option<usize>,
+    pub is_column_major: bool,
+}
+
+impl Default for ColumnsOptions {
+    fn default() -> Self {
+        Self {
+            gap: 2,
+            max_columns: None,
+            is_column_major: true,
+        }
+    }
+}
+
+impl Printer {
+    pub fn columns<Item: AsRef<str>>(
+        &mut self,
+        items: &[Item],
+        options: ColumnsOptions,
+    ) -> anyhow::Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let indent = self.indentation();
+        let available = self
+            .max_width
+            .saturating_sub(format::visible_width(&indent));
+        let widths: Vec<usize> = items
+            .iter()
+            .map(|item| format::visible_width(item.as_ref()))
+            .collect();
+        let column_width = widths.iter().copied().max().unwrap_or(0) + options.gap;
+
+        let mut column_count = (available / column_width.max(1)).max(1);
+        if let Some(max_columns) = options.max_columns {
+            column_count = column_count.min(max_columns.max(1));
+        }
+        column_count = column_count.min(items.len());
+        let row_count = items.len().div_ceil(column_count);
+
+        let mut output = String::new();
+        for row in 0..row_count {
+            let mut line = indent.clone();
+            for column in 0..column_count {
+                let index = if options.is_column_major {
+                    column * row_count + row
+                } else {
+                    row * column_count + column
+                };
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                line.push_str(item.as_ref());
+                line.push_str(&" ".repeat(column_width - widths[index]));
+            }
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+        self.write(output.as_str()).context(format_context!(""))?;
+        Ok(())
+    }
+
+    pub fn table(&mut self, table: &Table) -> anyhow::Result<()> {
+        let mut output = String::new();
+        table
+            .write_plain(&mut output, self.indentation().as_str())
+            .context(format_context!(""))?;
+        self.write(output.as_str()).context(format_context!(""))?;
+        Ok(())
+    }
+
+    pub fn tree(&mut self, tree: &TreeNode) -> anyhow::Result<()> {
+        let mut output = String::new();
+        tree.write_with(&mut output, self.indentation().as_str(), &self.theme.glyphs)
+            .context(format_context!(""))?;
+        self.write(output.as_str()).context(format_context!(""))?;
+        Ok(())
+    }
+}
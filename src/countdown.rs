@@ -0,0 +1,63 @@
+use crate::{format::humanize_duration, gauge::Gauge, Level, Printer, RenderMode};
+use std::time::{Duration, Instant};
+
+const COUNTDOWN_TICK: Duration = Duration::from_millis(100);
+const COUNTDOWN_WIDTH: usize = 10;
+
+// Whole seconds, rounded up so the last second shows as 1s rather than 0s
+fn format_remaining(remaining: Duration) -> String {
+    let seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    if seconds >= 60 {
+        humanize_duration(Duration::from_secs(seconds))
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+impl Printer {
+    // Waits for `duration` showing the time left, for retry backoffs and rate
+    // limits. Returns false if it was cut short by a cancel request from the
+    // controls (keyboard, signals or Controls::request_cancel).
+    pub fn countdown(&mut self, label: &str, duration: Duration) -> anyhow::Result<bool> {
+        let _operation = self.controls.begin_operation();
+        let gauge = Gauge::new(label).with_width(COUNTDOWN_WIDTH);
+        let progress = if !self.verbosity.is_show_progress_bars {
+            None
+        } else if self.render_mode == RenderMode::Plain {
+            self.log(
+                Level::Info,
+                format!("{label} ({})", format_remaining(duration)).as_str(),
+            )?;
+            None
+        } else {
+            let progress = indicatif::ProgressBar::new_spinner();
+            progress.set_style(indicatif::ProgressStyle::with_template("{prefix}{msg}").unwrap());
+            progress.set_prefix(self.indentation());
+            *self.spinner.lock().unwrap() = Some(progress.clone());
+            Some(progress)
+        };
+
+        let start = Instant::now();
+        let is_complete = loop {
+            if self.controls.is_cancel_requested() {
+                break false;
+            }
+            let remaining = duration.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                break true;
+            }
+            if let Some(progress) = progress.as_ref() {
+                let fraction = remaining.as_secs_f64() / duration.as_secs_f64();
+                gauge.set_with_text(fraction, format_remaining(remaining).as_str());
+                progress.set_message(gauge.render(&self.theme));
+            }
+            std::thread::sleep(remaining.min(COUNTDOWN_TICK));
+        };
+
+        if let Some(progress) = progress {
+            progress.finish_and_clear();
+            *self.spinner.lock().unwrap() = None;
+        }
+        Ok(is_complete)
+    }
+}
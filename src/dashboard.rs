@@ -0,0 +1,276 @@
+use crate::{
+    format,
+    server::{self, ServerGuard},
+    session::{EventSink, RecordedEvent, SessionEvent},
+    summary::Summary,
+    Printer,
+};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    io::{BufRead, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const LOG_LINES: usize = 500;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// comments keep proxies from closing idle streams and reveal closed browsers
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>printer dashboard</title>
+<style>
+body { font-family: monospace; margin: 1em; background: #111; color: #ddd; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+td { padding: 0 0.6em; }
+progress { width: 16em; }
+.finished { color: #7c7; }
+#logs { white-space: pre-wrap; border-top: 1px solid #444; padding-top: 0.5em; }
+</style>
+</head>
+<body>
+<h3>Progress</h3>
+<table id="bars"></table>
+<h3>Summary</h3>
+<table id="summary"></table>
+<h3>Log</h3>
+<div id="logs"></div>
+<script>
+const text = (value) => document.createTextNode(value);
+const row = (cells) => {
+  const tr = document.createElement("tr");
+  for (const cell of cells) {
+    const td = document.createElement("td");
+    td.append(cell);
+    tr.append(td);
+  }
+  return tr;
+};
+const render = (state) => {
+  const bars = document.getElementById("bars");
+  bars.replaceChildren(...state.bars.map((bar) => {
+    const progress = document.createElement("progress");
+    if (bar.total !== null) {
+      progress.max = bar.total;
+      progress.value = bar.position;
+    }
+    const tr = row([text(bar.prefix), progress, text(bar.message)]);
+    if (bar.is_finished) tr.className = "finished";
+    return tr;
+  }));
+  const steps = state.summary ? state.summary.steps : [];
+  document.getElementById("summary").replaceChildren(
+    ...steps.map((step) => row([text(step.name), text(step.status)])));
+  const logs = document.getElementById("logs");
+  logs.textContent = state.logs.join("\n");
+};
+new EventSource("/events").onmessage = (event) => render(JSON.parse(event.data));
+</script>
+</body>
+</html>
+"#;
+
+#[derive(Debug, Clone, Serialize)]
+struct DashboardBar {
+    id: usize,
+    prefix: String,
+    message: String,
+    position: u64,
+    total: Option<u64>,
+    is_finished: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct DashboardState {
+    version: u64,
+    bars: Vec<DashboardBar>,
+    logs: VecDeque<String>,
+    summary: Option<Summary>,
+}
+
+impl DashboardState {
+    fn apply(&mut self, event: &SessionEvent) {
+        let find =
+            |bars: &mut Vec<DashboardBar>, id: usize| bars.iter_mut().position(|bar| bar.id == id);
+        match event {
+            SessionEvent::Output { text } => {
+                for line in format::strip_ansi_codes(text).lines() {
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    if self.logs.len() == LOG_LINES {
+                        self.logs.pop_front();
+                    }
+                    self.logs.push_back(line.to_string());
+                }
+            }
+            SessionEvent::BarStarted { id, prefix, total } => self.bars.push(DashboardBar {
+                id: *id,
+                prefix: format::strip_ansi_codes(prefix).into_owned(),
+                message: String::new(),
+                position: 0,
+                total: *total,
+                is_finished: false,
+            }),
+            SessionEvent::BarMessage { id, message } => {
+                if let Some(index) = find(&mut self.bars, *id) {
+                    self.bars[index].message = format::strip_ansi_codes(message).into_owned();
+                }
+            }
+            SessionEvent::BarProgress {
+                id,
+                position,
+                total,
+            } => {
+                if let Some(index) = find(&mut self.bars, *id) {
+                    self.bars[index].position = *position;
+                    self.bars[index].total = *total;
+                }
+            }
+            SessionEvent::BarFinished { id, .. } => {
+                if let Some(index) = find(&mut self.bars, *id) {
+                    self.bars[index].is_finished = true;
+                }
+            }
+        }
+        self.version += 1;
+    }
+}
+
+struct DashboardSink {
+    state: Arc<Mutex<DashboardState>>,
+}
+
+impl EventSink for DashboardSink {
+    fn event(&mut self, event: &RecordedEvent) {
+        self.state.lock().unwrap().apply(&event.event);
+    }
+}
+
+// Serves a live view of the printer over HTTP: `/` is the page, `/state` the
+// current state as JSON and `/events` a server-sent event stream of it.
+// Serving stops and open streams are closed when the dashboard is dropped.
+pub struct Dashboard {
+    state: Arc<Mutex<DashboardState>>,
+    server: ServerGuard,
+}
+
+impl Dashboard {
+    pub fn start<Address: ToSocketAddrs>(address: Address) -> anyhow::Result<Self> {
+        let state = Arc::new(Mutex::new(DashboardState::default()));
+        let served_state = state.clone();
+        let server = server::serve(address, "the dashboard", move |stream| {
+            serve(stream, &served_state)
+        })
+        .context(format_context!(""))?;
+        Ok(Self { state, server })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.server.address()
+    }
+
+    pub fn set_summary(&self, summary: &Summary) {
+        let mut state = self.state.lock().unwrap();
+        state.summary = Some(summary.clone());
+        state.version += 1;
+    }
+}
+
+fn state_json(state: &Mutex<DashboardState>) -> (u64, String) {
+    let state = state.lock().unwrap();
+    (
+        state.version,
+        serde_json::to_string(&*state).unwrap_or_default(),
+    )
+}
+
+fn serve(stream: TcpStream, state: &Mutex<DashboardState>) -> std::io::Result<()> {
+    let mut reader = std::io::BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // the headers carry nothing the dashboard needs
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim() != "" {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let mut stream = stream;
+    match path {
+        "/" => respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            INDEX_HTML,
+        ),
+        "/state" => respond(
+            &mut stream,
+            "200 OK",
+            "application/json",
+            state_json(state).1.as_str(),
+        ),
+        "/events" => stream_events(&mut stream, state),
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+fn stream_events(stream: &mut TcpStream, state: &Mutex<DashboardState>) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+    let mut sent_version = None;
+    let mut last_write = Instant::now();
+    loop {
+        let (version, json) = state_json(state);
+        if sent_version != Some(version) {
+            write!(stream, "data: {json}\n\n")?;
+            stream.flush()?;
+            sent_version = Some(version);
+            last_write = Instant::now();
+        } else if last_write.elapsed() >= HEARTBEAT_INTERVAL {
+            write!(stream, ": heartbeat\n\n")?;
+            stream.flush()?;
+            last_write = Instant::now();
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+impl Printer {
+    // Starts the dashboard and feeds it everything this printer outputs until
+    // the returned dashboard is dropped
+    pub fn serve_dashboard<Address: ToSocketAddrs>(
+        &mut self,
+        address: Address,
+    ) -> anyhow::Result<Dashboard> {
+        let dashboard = Dashboard::start(address).context(format_context!(""))?;
+        self.add_event_sink(Box::new(DashboardSink {
+            state: dashboard.state.clone(),
+        }));
+        Ok(dashboard)
+    }
+}
@@ -0,0 +1,223 @@
+use crate::MultiProgressBar;
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use sha2::Digest;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const DOWNLOAD_BUFFER_SIZE: usize = 64 * 1024;
+const DOWNLOAD_TEMPLATE: &str =
+    "{elapsed_precise}|{bar}|{prefix} {bytes}/{total_bytes} {bytes_per_sec} {msg}";
+
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    pub sha256: Option<String>,
+    pub is_resume: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            sha256: None,
+            is_resume: true,
+        }
+    }
+}
+
+fn sha256_of_file(path: &Path) -> anyhow::Result<String> {
+    let mut file =
+        std::fs::File::open(path).context(format_context!("while opening {}", path.display()))?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buffer = vec![0u8; DOWNLOAD_BUFFER_SIZE];
+    loop {
+        let count = file
+            .read(&mut buffer)
+            .context(format_context!("while reading {}", path.display()))?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+fn sibling_path(destination: &Path, extension: &str) -> std::path::PathBuf {
+    let mut path = destination.as_os_str().to_owned();
+    path.push(extension);
+    path.into()
+}
+
+// A 416 answers a Range that starts at the end of the resource, the partial
+// file is then complete. Without Content-Range only the checksum can tell.
+fn is_range_complete(response: &ureq::Response, offset: u64, options: &DownloadOptions) -> bool {
+    match response.header("Content-Range") {
+        Some(range) => {
+            range
+                .strip_prefix("bytes */")
+                .and_then(|total| total.trim().parse::<u64>().ok())
+                == Some(offset)
+        }
+        None => options.sha256.is_some(),
+    }
+}
+
+// Downloads into `<destination>.part` and renames it once complete (and
+// verified), so an interrupted download can be resumed with a Range request.
+// The ETag or Last-Modified of the first response is kept next to it and sent
+// as If-Range, a resource that changed in between is downloaded again whole.
+pub fn download(
+    url: &str,
+    destination: &Path,
+    progress_bar: &mut MultiProgressBar,
+    options: &DownloadOptions,
+) -> anyhow::Result<()> {
+    let _operation = progress_bar.controls.begin_operation();
+    let partial_path = sibling_path(destination, ".part");
+    let validator_path = sibling_path(destination, ".part.validator");
+    let validator = std::fs::read_to_string(&validator_path).ok();
+
+    let mut offset = if options.is_resume {
+        std::fs::metadata(&partial_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0)
+    } else {
+        0
+    };
+    // nothing would tell whether the partial file is still the same resource
+    if validator.is_none() && options.sha256.is_none() {
+        offset = 0;
+    }
+
+    let mut request = ureq::get(url);
+    if offset > 0 {
+        request = request.set("Range", format!("bytes={offset}-").as_str());
+        if let Some(validator) = validator.as_ref() {
+            request = request.set("If-Range", validator.as_str());
+        }
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(416, response))
+            if offset > 0 && is_range_complete(&response, offset, options) =>
+        {
+            progress_bar.set_total(offset);
+            progress_bar.set_position(offset);
+            return finish(url, destination, &partial_path, progress_bar, options);
+        }
+        Err(error) => return Err(error).context(format_context!("while requesting {url}")),
+    };
+    let is_resumed = offset > 0 && response.status() == 206;
+    if !is_resumed {
+        let validator = response
+            .header("ETag")
+            .or_else(|| response.header("Last-Modified"));
+        // a validator that cannot be stored only costs a full download later
+        let _ = match validator {
+            Some(validator) => std::fs::write(&validator_path, validator),
+            None => std::fs::remove_file(&validator_path),
+        };
+    }
+    let start = if is_resumed { offset } else { 0 };
+    let remaining = response
+        .header("Content-Length")
+        .and_then(|length| length.parse::<u64>().ok());
+
+    // converts a spinner into a bar before the download template replaces its style
+    if let Some(remaining) = remaining {
+        progress_bar.set_total(start + remaining);
+    }
+    if let Some(progress) = progress_bar.progress.as_ref() {
+        let template =
+            DOWNLOAD_TEMPLATE.replace("{bar}", progress_bar.theme.bar_placeholder().as_str());
+        if let Ok(style) = indicatif::ProgressStyle::with_template(template.as_str()) {
+            progress.set_style(progress_bar.clock.attach(style.progress_chars("#>-")));
+        }
+        progress_bar.is_custom_style = true;
+        progress_bar.progress_width =
+            crate::preset::template_width(template.as_str(), progress_bar.total());
+    }
+    if let Some(progress) = progress_bar.progress.as_ref() {
+        progress.set_position(start);
+    }
+    progress_bar.set_message(url);
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(is_resumed)
+        .truncate(!is_resumed)
+        .open(&partial_path)
+        .context(format_context!("while opening {}", partial_path.display()))?;
+
+    let mut reader = response.into_reader();
+    let mut buffer = vec![0u8; DOWNLOAD_BUFFER_SIZE];
+    loop {
+        if progress_bar.controls.is_cancel_requested() {
+            return Err(format_error!("Cancelled: download {url}"));
+        }
+        progress_bar.controls.wait_while_paused();
+        let count = reader
+            .read(&mut buffer)
+            .context(format_context!("while downloading {url}"))?;
+        if count == 0 {
+            break;
+        }
+        file.write_all(&buffer[..count])
+            .context(format_context!("while writing {}", partial_path.display()))?;
+        progress_bar.increment(count as u64);
+    }
+    file.flush()
+        .context(format_context!("while writing {}", partial_path.display()))?;
+    drop(file);
+
+    finish(url, destination, &partial_path, progress_bar, options)
+}
+
+// verifies the complete partial file and moves it into place
+fn finish(
+    url: &str,
+    destination: &Path,
+    partial_path: &Path,
+    progress_bar: &mut MultiProgressBar,
+    options: &DownloadOptions,
+) -> anyhow::Result<()> {
+    let validator_path = sibling_path(destination, ".part.validator");
+    if let Some(expected) = options.sha256.as_ref() {
+        let actual = sha256_of_file(partial_path).context(format_context!(""))?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            let _ = std::fs::remove_file(partial_path);
+            let _ = std::fs::remove_file(&validator_path);
+            progress_bar.mark_failed();
+            return Err(format_error!(
+                "Checksum mismatch for {url}: expected {expected}, got {actual}"
+            ));
+        }
+    }
+
+    std::fs::rename(partial_path, destination).context(format_context!(
+        "while moving {} to {}",
+        partial_path.display(),
+        destination.display()
+    ))?;
+    let _ = std::fs::remove_file(&validator_path);
+    Ok(())
+}
+
+impl MultiProgressBar {
+    pub fn download(
+        &mut self,
+        url: &str,
+        destination: &Path,
+        options: &DownloadOptions,
+    ) -> anyhow::Result<()> {
+        let result = download(url, destination, self, options);
+        if result.is_err() {
+            self.mark_failed();
+        }
+        result
+    }
+}
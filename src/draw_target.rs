@@ -0,0 +1,55 @@
+use crate::{redact::Secrets, MultiProgress, RenderMode, SharedWriter};
+use indicatif::ProgressBar;
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+// Lets libraries that create their own indicatif bars render through our
+// MultiProgress instead of running a competing draw loop on the terminal
+#[derive(Clone)]
+pub struct PrinterDrawTarget {
+    multi_progress: indicatif::MultiProgress,
+    is_hidden: bool,
+    lock: Arc<Mutex<()>>,
+    writer: SharedWriter,
+    secrets: Secrets,
+}
+
+impl PrinterDrawTarget {
+    pub fn attach(&self, progress: ProgressBar) -> ProgressBar {
+        if self.is_hidden {
+            progress.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+            progress
+        } else {
+            self.multi_progress.add(progress)
+        }
+    }
+
+    pub fn println(&self, message: &str) -> std::io::Result<()> {
+        if self.is_hidden {
+            let message = self.secrets.redact(message);
+            let _lock = self.lock.lock().unwrap();
+            writeln!(self.writer.lock().unwrap(), "{message}")
+        } else {
+            self.multi_progress.println(message)
+        }
+    }
+
+    pub fn suspend<Function: FnOnce() -> Output, Output>(&self, function: Function) -> Output {
+        self.multi_progress.suspend(function)
+    }
+}
+
+impl MultiProgress<'_> {
+    pub fn draw_target(&self) -> PrinterDrawTarget {
+        PrinterDrawTarget {
+            multi_progress: self.multi_progress.clone(),
+            is_hidden: self.printer.render_mode == RenderMode::Plain
+                || !self.printer.verbosity.is_show_progress_bars,
+            lock: self.printer.lock.clone(),
+            writer: self.printer.writer.clone(),
+            secrets: self.printer.secrets.clone(),
+        }
+    }
+}
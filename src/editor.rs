@@ -0,0 +1,101 @@
+#[cfg(feature = "progress")]
+use crate::MultiProgress;
+use crate::Printer;
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// keeps temp file names unique within the process
+static EDIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// $VISUAL then $EDITOR, which may carry arguments such as `code --wait`
+fn editor_command() -> String {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty());
+    match editor {
+        Some(editor) => editor,
+        None if cfg!(windows) => "notepad".to_string(),
+        None => "vi".to_string(),
+    }
+}
+
+fn run_editor(editor: &str, initial: &str, extension: &str) -> anyhow::Result<String> {
+    let command: Vec<&str> = editor.split_whitespace().collect();
+    if command.is_empty() {
+        return Err(format_error!("No editor command given"));
+    }
+
+    let extension = extension.trim_start_matches('.');
+    let name = format!(
+        "printer-edit-{}-{}",
+        std::process::id(),
+        EDIT_COUNT.fetch_add(1, Ordering::Relaxed)
+    );
+    let path = std::env::temp_dir().join(if extension.is_empty() {
+        name
+    } else {
+        format!("{name}.{extension}")
+    });
+    let path_name = path.display().to_string();
+    std::fs::write(&path, initial).context(format_context!("while creating {path_name}"))?;
+
+    let status = std::process::Command::new(command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status();
+    let content = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    let status = status.context(format_context!("while starting editor {}", command[0]))?;
+    if !status.success() {
+        return Err(format_error!("editor {} exited with {status}", command[0]));
+    }
+    content.context(format_context!("while reading {path_name}"))
+}
+
+impl Printer {
+    // Opens the user's editor on `initial` in a temp file with the given
+    // extension (for syntax highlighting) and returns the saved text
+    pub fn edit_text(&mut self, initial: &str, extension: &str) -> anyhow::Result<String> {
+        self.edit_text_with(editor_command().as_str(), initial, extension)
+    }
+
+    // Like edit_text with an editor command chosen by the caller instead of
+    // $VISUAL or $EDITOR
+    pub fn edit_text_with(
+        &mut self,
+        editor: &str,
+        initial: &str,
+        extension: &str,
+    ) -> anyhow::Result<String> {
+        #[cfg(feature = "progress")]
+        let spinner = self.spinner.lock().unwrap().clone();
+        #[cfg(feature = "progress")]
+        if let Some(spinner) = spinner {
+            return spinner.suspend(|| run_editor(editor, initial, extension));
+        }
+        run_editor(editor, initial, extension)
+    }
+}
+
+#[cfg(feature = "progress")]
+impl MultiProgress<'_> {
+    // Hides the bars while the editor owns the terminal
+    pub fn edit_text(&mut self, initial: &str, extension: &str) -> anyhow::Result<String> {
+        self.edit_text_with(editor_command().as_str(), initial, extension)
+    }
+
+    pub fn edit_text_with(
+        &mut self,
+        editor: &str,
+        initial: &str,
+        extension: &str,
+    ) -> anyhow::Result<String> {
+        let printer = &mut *self.printer;
+        self.multi_progress
+            .suspend(|| printer.edit_text_with(editor, initial, extension))
+            .context(format_context!(""))
+    }
+}
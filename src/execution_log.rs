@@ -0,0 +1,252 @@
+use crate::{log_file, redact, system::SystemReport, ExecuteOptions, Printer};
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Read},
+    path::Path,
+    sync::Arc,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionLogEntry {
+    pub label: Arc<str>,
+    pub command: Arc<str>,
+    pub log_file_path: Arc<str>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureRecord {
+    pub label: Arc<str>,
+    pub command: Arc<str>,
+    pub arguments: Vec<Arc<str>>,
+    pub working_directory: Option<Arc<str>>,
+    pub environment: Vec<(Arc<str>, Arc<str>)>,
+    pub exit_code: Option<i32>,
+    pub log_file_path: Option<Arc<str>>,
+    pub stderr_tail: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionLog {
+    pub entries: Vec<ExecutionLogEntry>,
+    #[serde(default)]
+    pub failures: Vec<FailureRecord>,
+}
+
+const FAILURE_OUTPUT_TAIL_BYTES: usize = 64 * 1024;
+
+fn tail_of(content: &[u8], max_bytes: usize) -> String {
+    let start = content.len().saturating_sub(max_bytes);
+    String::from_utf8_lossy(&content[start..]).into_owned()
+}
+
+// parts of variable names that usually hold credentials
+const SENSITIVE_NAME_PARTS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "KEY",
+    "AUTH",
+    "CREDENTIAL",
+    "COOKIE",
+];
+
+// A bundle is meant to be shared, so values of credential-like variables are
+// masked even when they were never registered as secrets
+fn redact_variable(name: &str, value: &str, secrets: &redact::Secrets) -> String {
+    let name = name.to_ascii_uppercase();
+    if SENSITIVE_NAME_PARTS.iter().any(|part| name.contains(part)) {
+        redact::REDACTED.to_string()
+    } else {
+        secrets.redact(value).into_owned()
+    }
+}
+
+pub(crate) fn redact_environment<Variables: IntoIterator<Item = (String, String)>>(
+    variables: Variables,
+    secrets: &redact::Secrets,
+) -> BTreeMap<String, String> {
+    variables
+        .into_iter()
+        .map(|(name, value)| {
+            let value = redact_variable(name.as_str(), value.as_str(), secrets);
+            (name, value)
+        })
+        .collect()
+}
+
+fn write_json_file<Type: Serialize>(
+    directory: &Path,
+    name: &str,
+    value: &Type,
+) -> anyhow::Result<()> {
+    let path = directory.join(name);
+    let content = serde_json::to_string_pretty(value).context(format_context!(""))?;
+    std::fs::write(&path, content).context(format_context!("while writing {}", path.display()))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepMatch {
+    pub label: Arc<str>,
+    pub log_file_path: Arc<str>,
+    pub line_number: usize,
+    pub line: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+impl ExecutionLog {
+    pub fn add(&mut self, label: &str, command: &str, log_file_path: &str) {
+        // appended logs (scripts) are registered once so grep doesn't repeat matches
+        if self
+            .entries
+            .iter()
+            .any(|entry| entry.log_file_path.as_ref() == log_file_path)
+        {
+            return;
+        }
+        self.entries.push(ExecutionLogEntry {
+            label: label.into(),
+            command: command.into(),
+            log_file_path: log_file_path.into(),
+        });
+    }
+
+    pub fn add_failure(
+        &mut self,
+        command: &str,
+        options: &ExecuteOptions,
+        exit_code: Option<i32>,
+        stderr: &str,
+    ) {
+        self.failures.push(FailureRecord {
+            label: options.label.clone(),
+            command: command.into(),
+            arguments: options.get_arguments(),
+            working_directory: options.get_working_directory(),
+            environment: options.environment.clone(),
+            exit_code,
+            log_file_path: options.log_file_path.clone(),
+            stderr_tail: tail_of(stderr.as_bytes(), FAILURE_OUTPUT_TAIL_BYTES),
+        });
+    }
+
+    pub fn grep(&self, pattern: &str, context_lines: usize) -> anyhow::Result<Vec<GrepMatch>> {
+        let mut matches = Vec::new();
+        for entry in self.entries.iter() {
+            let reader =
+                log_file::open_reader(entry.log_file_path.as_ref()).context(format_context!(""))?;
+            let lines = std::io::BufReader::new(reader)
+                .lines()
+                .collect::<Result<Vec<_>, _>>()
+                .context(format_context!("while reading {}", entry.log_file_path))?;
+
+            for (index, line) in lines.iter().enumerate() {
+                if line.contains(pattern) {
+                    let start = index.saturating_sub(context_lines);
+                    let end = (index + 1 + context_lines).min(lines.len());
+                    matches.push(GrepMatch {
+                        label: entry.label.clone(),
+                        log_file_path: entry.log_file_path.clone(),
+                        line_number: index + 1,
+                        line: line.clone(),
+                        before: lines[start..index].to_vec(),
+                        after: lines[index + 1..end].to_vec(),
+                    });
+                }
+            }
+        }
+        Ok(matches)
+    }
+}
+
+impl Printer {
+    pub fn execution_log(&self) -> ExecutionLog {
+        self.execution_log.lock().unwrap().clone()
+    }
+
+    pub fn grep_logs(&mut self, pattern: &str, context_lines: usize) -> anyhow::Result<usize> {
+        let matches = self
+            .execution_log()
+            .grep(pattern, context_lines)
+            .context(format_context!("while searching logs for {pattern}"))?;
+        self.print_grep_matches(&matches)
+            .context(format_context!(""))?;
+        Ok(matches.len())
+    }
+
+    pub fn write_failure_bundle(&self, directory: &Path) -> anyhow::Result<()> {
+        let mut execution_log = self.execution_log();
+        for failure in execution_log.failures.iter_mut() {
+            for (name, value) in failure.environment.iter_mut() {
+                *value = redact_variable(name, value, &self.secrets).into();
+            }
+        }
+        let failure = execution_log
+            .failures
+            .last()
+            .ok_or(format_error!("No failed command has been recorded"))?;
+
+        std::fs::create_dir_all(directory)
+            .context(format_context!("while creating {}", directory.display()))?;
+
+        write_json_file(directory, "failure.json", failure)?;
+        write_json_file(directory, "execution_log.json", &execution_log)?;
+        let environment = redact_environment(std::env::vars(), &self.secrets);
+        write_json_file(directory, "environment.json", &environment)?;
+        let working_directory = failure.working_directory.as_deref().unwrap_or(".");
+        let system = SystemReport::collect(Path::new(working_directory), &[]);
+        write_json_file(directory, "system.json", &system)?;
+
+        let output = match failure.log_file_path.as_ref() {
+            Some(log_file_path) => log_file::open_reader(log_file_path.as_ref())
+                .and_then(|mut reader| {
+                    let mut content = Vec::new();
+                    reader
+                        .read_to_end(&mut content)
+                        .context(format_context!("while reading {log_file_path}"))?;
+                    Ok(content)
+                })
+                .map(|content| tail_of(&content, FAILURE_OUTPUT_TAIL_BYTES))
+                .unwrap_or_else(|_| failure.stderr_tail.clone()),
+            None => failure.stderr_tail.clone(),
+        };
+        let output_path = directory.join("output.txt");
+        std::fs::write(&output_path, output)
+            .context(format_context!("while writing {}", output_path.display()))?;
+        Ok(())
+    }
+
+    pub fn print_grep_matches(&mut self, matches: &[GrepMatch]) -> anyhow::Result<()> {
+        let indent = self.indentation();
+        for grep_match in matches {
+            let mut block = format!(
+                "{indent}{}:{}:{}\n",
+                grep_match
+                    .label
+                    .if_supports_color(Stdout, |text| text.bold()),
+                grep_match.log_file_path,
+                grep_match.line_number
+            );
+            for line in grep_match.before.iter() {
+                block.push_str(&format!(
+                    "{indent}  {}\n",
+                    line.if_supports_color(Stdout, |text| text.dimmed())
+                ));
+            }
+            block.push_str(&format!("{indent}  {}\n", grep_match.line));
+            for line in grep_match.after.iter() {
+                block.push_str(&format!(
+                    "{indent}  {}\n",
+                    line.if_supports_color(Stdout, |text| text.dimmed())
+                ));
+            }
+            self.write(block.as_str()).context(format_context!(""))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+pub use crate::text::{humanize_bytes, humanize_count, humanize_duration};
+use std::borrow::Cow;
+use unicode_width::UnicodeWidthStr;
+
+const TAB_WIDTH: usize = 4;
+const ESCAPE: char = '\x1b';
+
+// Columns the text occupies on a terminal, ignoring ANSI escape sequences
+pub fn visible_width(text: &str) -> usize {
+    strip_ansi_codes(text).width()
+}
+
+// The text as a terminal would show it, without colors and hyperlinks
+pub fn strip_ansi_codes(text: &str) -> Cow<'_, str> {
+    if !text.contains(ESCAPE) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(
+        AnsiSegments::new(text)
+            .filter(|(_, is_escape)| !is_escape)
+            .map(|(segment, _)| segment)
+            .collect(),
+    )
+}
+
+// Splits text into runs of plain text and escape sequences, flagging the
+// sequences. Handles CSI sequences like colors and cursor movement, OSC
+// sequences like hyperlinks, and two character escapes.
+pub(crate) struct AnsiSegments<'a> {
+    rest: &'a str,
+}
+
+impl<'a> AnsiSegments<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self { rest: text }
+    }
+}
+
+// byte length of the escape sequence at the start of `text`, which begins with
+// ESC. A sequence cut off by the end of the text runs to the end.
+fn escape_length(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    match bytes.get(1) {
+        Some(b'[') => bytes[2..]
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map_or(bytes.len(), |index| index + 3),
+        // terminated by BEL or ESC \
+        Some(b']') => {
+            let mut index = 2;
+            while index < bytes.len() {
+                match bytes[index] {
+                    0x07 => return index + 1,
+                    0x1b if bytes.get(index + 1) == Some(&b'\\') => return index + 2,
+                    _ => index += 1,
+                }
+            }
+            bytes.len()
+        }
+        Some(byte) if byte.is_ascii() => 2,
+        _ => 1,
+    }
+}
+
+impl<'a> Iterator for AnsiSegments<'a> {
+    type Item = (&'a str, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        let length = if self.rest.starts_with(ESCAPE) {
+            escape_length(self.rest)
+        } else {
+            self.rest.find(ESCAPE).unwrap_or(self.rest.len())
+        };
+        let (segment, rest) = self.rest.split_at(length);
+        self.rest = rest;
+        Some((segment, segment.starts_with(ESCAPE)))
+    }
+}
+
+// Cleans output from child tools for single line display. Carriage returns and
+// backspaces are applied the way a terminal would, tabs expand to spaces, other
+// control characters are dropped, and only color sequences are kept.
+pub fn sanitize(input: &str) -> String {
+    // plain text, the common case, has nothing to apply
+    if !input.chars().any(char::is_control) {
+        return input.to_string();
+    }
+    // each cell is a visible character or a color sequence, so backspaces only
+    // erase what would have been displayed
+    let mut cells: Vec<Cell> = Vec::with_capacity(input.len());
+    let visible_count = |cells: &Vec<Cell>| {
+        cells
+            .iter()
+            .filter(|cell| matches!(cell, Cell::Visible(_)))
+            .count()
+    };
+    for (text, is_escape) in AnsiSegments::new(input) {
+        if is_escape {
+            if text.ends_with('m') {
+                cells.push(Cell::Color(text));
+            }
+            continue;
+        }
+        let mut characters = text.chars().peekable();
+        while let Some(character) = characters.next() {
+            match character {
+                '\r' if matches!(characters.peek(), None | Some('\n')) => {}
+                '\r' => cells.retain(|cell| matches!(cell, Cell::Color(_))),
+                '\x08' => {
+                    if let Some(index) = cells
+                        .iter()
+                        .rposition(|cell| matches!(cell, Cell::Visible(_)))
+                    {
+                        cells.remove(index);
+                    }
+                }
+                '\t' => {
+                    let column = visible_count(&cells);
+                    for _ in 0..TAB_WIDTH - column % TAB_WIDTH {
+                        cells.push(Cell::Visible(' '));
+                    }
+                }
+                '\n' => cells.push(Cell::Visible(' ')),
+                character if character.is_control() => {}
+                character => cells.push(Cell::Visible(character)),
+            }
+        }
+    }
+    let mut output = String::with_capacity(input.len());
+    for cell in cells {
+        match cell {
+            Cell::Visible(character) => output.push(character),
+            Cell::Color(sequence) => output.push_str(sequence),
+        }
+    }
+    output
+}
+
+#[derive(Clone, Copy)]
+enum Cell<'a> {
+    Visible(char),
+    Color(&'a str),
+}
+
+// Sanitizes the input and pads or truncates it to exactly `width` columns.
+// Truncated text ends with an ellipsis.
+pub fn fit_to_width(input: &str, width: usize) -> String {
+    let sanitized = sanitize(input);
+    let sanitized_width = visible_width(sanitized.as_str());
+    if sanitized_width <= width {
+        return format!("{sanitized}{}", " ".repeat(width - sanitized_width));
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    let mut is_colored = false;
+    'segments: for (text, is_escape) in AnsiSegments::new(sanitized.as_str()) {
+        if is_escape {
+            result.push_str(text);
+            is_colored = true;
+            continue;
+        }
+        for character in text.chars() {
+            let character_width = visible_width(character.encode_utf8(&mut [0; 4]));
+            // leave a column for the ellipsis
+            if used + character_width >= width {
+                break 'segments;
+            }
+            result.push(character);
+            used += character_width;
+        }
+    }
+    if width > 0 {
+        result.push('…');
+        used += 1;
+    }
+    if is_colored {
+        result.push_str("\x1b[0m");
+    }
+    result.push_str(" ".repeat(width.saturating_sub(used)).as_str());
+    result
+}
@@ -0,0 +1,100 @@
+use crate::{format, theme::Theme, MultiProgress};
+use std::sync::{Arc, Mutex};
+
+const DEFAULT_GAUGE_WIDTH: usize = 5;
+
+struct GaugeState {
+    fraction: f64,
+    // shown after the gauge, the percentage unless set_with_text was used
+    text: String,
+}
+
+// A small meter such as `mem ▓▓▓░░ 62%` for a value the caller samples, like
+// bandwidth, disk usage or queue saturation. Clones share the value.
+#[derive(Clone)]
+pub struct Gauge {
+    label: Arc<str>,
+    width: usize,
+    state: Arc<Mutex<GaugeState>>,
+}
+
+fn clamp_fraction(fraction: f64) -> f64 {
+    if fraction.is_nan() {
+        0.0
+    } else {
+        fraction.clamp(0.0, 1.0)
+    }
+}
+
+impl Gauge {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.into(),
+            width: DEFAULT_GAUGE_WIDTH,
+            state: Arc::new(Mutex::new(GaugeState {
+                fraction: 0.0,
+                text: "0%".to_string(),
+            })),
+        }
+    }
+
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    // `fraction` is clamped to 0..=1
+    pub fn set(&self, fraction: f64) {
+        let fraction = clamp_fraction(fraction);
+        self.set_with_text(fraction, format!("{:.0}%", fraction * 100.0).as_str());
+    }
+
+    // For values that read better in their own unit, like `3.2 MB/s`
+    pub fn set_with_text(&self, fraction: f64, text: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.fraction = clamp_fraction(fraction);
+        state.text = text.to_string();
+    }
+
+    pub fn fraction(&self) -> f64 {
+        self.state.lock().unwrap().fraction
+    }
+
+    pub(crate) fn render(&self, theme: &Theme) -> String {
+        let state = self.state.lock().unwrap();
+        let filled = (state.fraction * self.width as f64).round() as usize;
+        format!(
+            "{} {}{} {}",
+            self.label,
+            theme
+                .progress_bar
+                .paint(theme.glyphs.gauge_filled.repeat(filled).as_str()),
+            theme.progress_background.paint(
+                theme
+                    .glyphs
+                    .gauge_empty
+                    .repeat(self.width - filled)
+                    .as_str()
+            ),
+            state.text
+        )
+    }
+}
+
+impl std::fmt::Display for Gauge {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rendered = self.render(&Theme::default());
+        write!(formatter, "{}", format::strip_ansi_codes(rendered.as_str()))
+    }
+}
+
+impl MultiProgress<'_> {
+    // Shows the gauge in the jobs header, enabling the header if needed. The
+    // header redraws every second with the latest value.
+    pub fn add_gauge(&mut self, gauge: &Gauge) {
+        self.enable_jobs_header();
+        if let Some(jobs_header) = self.jobs_header.as_ref() {
+            jobs_header.add_gauge(gauge.clone());
+        }
+    }
+}
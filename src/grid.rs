@@ -0,0 +1,152 @@
+use crate::{
+    format,
+    snapshot::{BarState, BarStatus, Registry},
+    theme::Theme,
+    Level, MultiProgress, MultiProgressBar,
+};
+use std::sync::{Arc, OnceLock};
+
+const CELL_NAME_WIDTH: usize = 12;
+// name, a space, four columns of status and two spaces between cells
+const CELL_WIDTH: usize = CELL_NAME_WIDTH + 7;
+const GRID_TICK: std::time::Duration = std::time::Duration::from_millis(100);
+
+// Shows many similar jobs as a grid of small cells in place of one bar each.
+// The individual bars keep running hidden and the grid is drawn from their
+// snapshots on every tick.
+pub(crate) struct Grid {
+    threshold: usize,
+    progress: OnceLock<indicatif::ProgressBar>,
+}
+
+fn render_cell(state: &BarState, theme: &Theme) -> String {
+    let name = state.prefix.trim_end().trim_end_matches(':');
+    let glyphs = &theme.glyphs;
+    let status = match state.status {
+        BarStatus::Finished => theme.success.paint(format!("   {}", glyphs.done).as_str()),
+        BarStatus::Failed => theme
+            .level_color(Level::Error)
+            .paint(format!("   {}", glyphs.failed).as_str()),
+        BarStatus::Paused => format!("   {}", glyphs.paused),
+        BarStatus::Queued => format!("   {}", glyphs.queued),
+        BarStatus::Running => match state.total {
+            Some(total) if total > 0 => {
+                format!("{:>3}%", state.position.min(total) * 100 / total)
+            }
+            _ => " ...".to_string(),
+        },
+    };
+    format!("{} {status}", format::fit_to_width(name, CELL_NAME_WIDTH))
+}
+
+fn render(states: &[BarState], theme: &Theme, width: usize) -> String {
+    let count = |status: BarStatus| states.iter().filter(|state| state.status == status).count();
+    let mut output = format!(
+        "{} jobs: {} running, {} queued, {} done, {} failed",
+        states.len(),
+        count(BarStatus::Running) + count(BarStatus::Paused),
+        count(BarStatus::Queued),
+        count(BarStatus::Finished),
+        count(BarStatus::Failed)
+    );
+    let columns = (width / CELL_WIDTH).max(1);
+    for row in states.chunks(columns) {
+        output.push('\n');
+        let cells: Vec<String> = row.iter().map(|state| render_cell(state, theme)).collect();
+        output.push_str(cells.join("  ").as_str());
+    }
+    output
+}
+
+impl Grid {
+    pub(crate) fn is_active(&self) -> bool {
+        self.progress.get().is_some()
+    }
+
+    // Logs from hidden bars are printed above the grid. Returns false when the
+    // grid isn't showing and the bar prints for itself.
+    pub(crate) fn println(&self, message: &str) -> bool {
+        match self.progress.get() {
+            Some(progress) => {
+                progress.println(message);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn activate(
+        &self,
+        multi_progress: &indicatif::MultiProgress,
+        registry: &Registry,
+        theme: Arc<Theme>,
+        width: usize,
+    ) {
+        let grid_registry = registry.clone();
+        let style = indicatif::ProgressStyle::with_template("{grid}")
+            .unwrap()
+            .with_key(
+                "grid",
+                move |_: &indicatif::ProgressState, writer: &mut dyn std::fmt::Write| {
+                    let states = grid_registry.snapshot();
+                    let _ = write!(writer, "{}", render(&states, &theme, width));
+                },
+            );
+        let progress = multi_progress.add(indicatif::ProgressBar::new_spinner());
+        progress.set_style(style);
+        progress.enable_steady_tick(GRID_TICK);
+        for bar in registry.live_bars() {
+            multi_progress.remove(&bar);
+        }
+        let _ = self.progress.set(progress);
+    }
+}
+
+impl Drop for Grid {
+    fn drop(&mut self) {
+        if let Some(progress) = self.progress.get() {
+            // leaves the final grid on screen
+            progress.finish();
+        }
+    }
+}
+
+impl MultiProgress<'_> {
+    // Switches to a compact grid once more than `threshold` bars have been
+    // added. Call it before adding bars so their logs print above the grid.
+    pub fn enable_grid(&mut self, threshold: usize) {
+        let is_drawn = self.printer.verbosity.is_show_progress_bars
+            && self.printer.render_mode == crate::RenderMode::Interactive;
+        #[cfg(feature = "tui")]
+        let is_drawn = is_drawn && self.tui.is_none();
+        if self.grid.is_none() && is_drawn {
+            let grid = Arc::new(Grid {
+                threshold,
+                progress: OnceLock::new(),
+            });
+            if let Some(arrangement) = self.arrangement.as_ref() {
+                arrangement.set_grid(grid.clone());
+            }
+            self.grid = Some(grid);
+        }
+    }
+
+    pub(crate) fn add_to_grid(&self, progress_bar: &mut MultiProgressBar) {
+        let Some(grid) = self.grid.as_ref() else {
+            return;
+        };
+        progress_bar.grid = Some(grid.clone());
+        if grid.is_active() {
+            if let Some(progress) = progress_bar.progress.as_ref() {
+                self.multi_progress.remove(progress);
+            }
+        } else if self.registry.len() > grid.threshold {
+            grid.activate(
+                &self.multi_progress,
+                &self.registry,
+                self.printer.theme.clone(),
+                self.printer.max_width,
+            );
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{
+    prefix_column::PrefixColumn, snapshot, HeadingGuard, IndentGuard, Level, MultiProgressBar,
+    Printer, QuietGuard, SectionGuard,
+};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+// A cloneable handle to a Printer that can move into threads or tokio tasks.
+// Every call locks the printer for its duration only. Bars created through it
+// share one draw target, and section and heading guards don't hold the lock.
+// Indentation is kept per thread, so tasks nesting sections at the same time
+// don't add to each other's depth.
+#[derive(Clone)]
+pub struct PrinterHandle {
+    printer: Arc<Mutex<Printer>>,
+    multi_progress: indicatif::MultiProgress,
+    prefix_column: Arc<PrefixColumn>,
+    pub(crate) registry: snapshot::Registry,
+}
+
+const _: fn() = || {
+    fn assert_send_sync<Type: Send + Sync + 'static>() {}
+    assert_send_sync::<PrinterHandle>();
+    assert_send_sync::<MultiProgressBar>();
+    assert_send_sync::<SectionGuard>();
+    assert_send_sync::<HeadingGuard>();
+};
+
+impl PrinterHandle {
+    pub fn new(printer: Printer) -> Self {
+        Self {
+            printer: Arc::new(Mutex::new(printer)),
+            multi_progress: indicatif::MultiProgress::new(),
+            prefix_column: Arc::new(PrefixColumn::default()),
+            registry: snapshot::Registry::default(),
+        }
+    }
+
+    pub fn with<Return>(&self, function: impl FnOnce(&mut Printer) -> Return) -> Return {
+        let mut printer = self.printer.lock().unwrap();
+        function(&mut printer)
+    }
+
+    pub fn log(&self, level: Level, message: &str) -> anyhow::Result<()> {
+        self.with(|printer| printer.log(level, message))
+            .context(format_context!(""))
+    }
+
+    pub fn info<Type: Serialize>(&self, name: &str, value: &Type) -> anyhow::Result<()> {
+        self.with(|printer| printer.info(name, value))
+            .context(format_context!(""))
+    }
+
+    pub fn warning<Type: Serialize>(&self, name: &str, value: &Type) -> anyhow::Result<()> {
+        self.with(|printer| printer.warning(name, value))
+            .context(format_context!(""))
+    }
+
+    pub fn error<Type: Serialize>(&self, name: &str, value: &Type) -> anyhow::Result<()> {
+        self.with(|printer| printer.error(name, value))
+            .context(format_context!(""))
+    }
+
+    pub fn begin_heading(&self, name: &str) -> anyhow::Result<HeadingGuard> {
+        self.with(|printer| printer.begin_heading(name))
+            .context(format_context!(""))
+    }
+
+    pub fn begin_section(&self, name: &str) -> anyhow::Result<SectionGuard> {
+        self.with(|printer| printer.begin_section(name))
+            .context(format_context!(""))
+    }
+
+    pub fn quiet_scope(&self) -> QuietGuard {
+        self.with(|printer| printer.quiet_scope())
+    }
+
+    pub fn indented(&self) -> IndentGuard {
+        self.with(|printer| printer.indented())
+    }
+
+    pub fn add_progress(
+        &self,
+        prefix: &str,
+        total: Option<u64>,
+        finish_message: Option<&str>,
+    ) -> MultiProgressBar {
+        let mut progress_bar = self.with(|printer| {
+            printer.create_progress_bar(
+                &self.multi_progress,
+                &self.prefix_column,
+                prefix,
+                total,
+                finish_message,
+            )
+        });
+        self.registry.register(&mut progress_bar);
+        progress_bar
+    }
+
+    // Pads the prefix of every bar to `width`, `None` sizes it to the widest
+    pub fn set_prefix_width(&self, width: Option<usize>) {
+        self.prefix_column.set_width(width);
+    }
+}
+
+impl From<Printer> for PrinterHandle {
+    fn from(printer: Printer) -> Self {
+        Self::new(printer)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{format, Printer};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::Serialize;
+use std::{collections::HashMap, time::Duration};
+
+// How a numeric field is shown on the terminal. The serialized value keeps the
+// raw number, so only the rendering changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Hint {
+    #[default]
+    Raw,
+    Bytes,
+    Milliseconds,
+    Seconds,
+    Count,
+}
+
+impl Hint {
+    // Naming convention used when a field has no explicit hint
+    pub fn from_key(key: &str) -> Self {
+        if key.ends_with("_bytes") {
+            Hint::Bytes
+        } else if key.ends_with("_ms") {
+            Hint::Milliseconds
+        } else if key.ends_with("_secs") || key.ends_with("_seconds") {
+            Hint::Seconds
+        } else {
+            Hint::Raw
+        }
+    }
+
+    pub(crate) fn humanize(self, value: &serde_json::Number) -> Option<String> {
+        match self {
+            Hint::Raw => None,
+            Hint::Bytes => value.as_u64().map(format::humanize_bytes),
+            Hint::Milliseconds => value
+                .as_u64()
+                .map(|millis| format::humanize_duration(Duration::from_millis(millis))),
+            Hint::Seconds => value
+                .as_f64()
+                .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                .map(|seconds| format::humanize_duration(Duration::from_secs_f64(seconds))),
+            Hint::Count => value.as_u64().map(format::humanize_count),
+        }
+    }
+}
+
+// Explicit hints by field name. These win over the naming convention and apply
+// at any depth of the object.
+#[derive(Debug, Clone, Default)]
+pub struct Hints {
+    hints: HashMap<String, Hint>,
+}
+
+impl Hints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, key: &str, hint: Hint) -> Self {
+        self.insert(key, hint);
+        self
+    }
+
+    pub fn insert(&mut self, key: &str, hint: Hint) {
+        self.hints.insert(key.to_string(), hint);
+    }
+
+    pub(crate) fn resolve(&self, key: &str) -> Hint {
+        self.hints
+            .get(key)
+            .copied()
+            .unwrap_or_else(|| Hint::from_key(key))
+    }
+}
+
+impl Printer {
+    // Like the level methods but numeric fields are humanized using `hints`
+    pub fn object_with_hints<Type: Serialize>(
+        &mut self,
+        name: &str,
+        value: &Type,
+        hints: &Hints,
+    ) -> anyhow::Result<()> {
+        let value = serde_json::to_value(value).context(format_context!(""))?;
+        self.print_object(name, &value, hints)
+    }
+}
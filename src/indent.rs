@@ -0,0 +1,36 @@
+use std::{collections::HashMap, sync::Mutex, thread::ThreadId};
+
+// Indent depth kept per thread. Clones of a PrinterHandle nesting sections
+// on different threads each get their own depth instead of adding to each
+// other's. A guard remembers the thread that shifted so dropping it elsewhere
+// still undoes the right one.
+#[derive(Default)]
+pub(crate) struct IndentLevels {
+    levels: Mutex<HashMap<ThreadId, usize>>,
+}
+
+impl IndentLevels {
+    pub(crate) fn get(&self) -> usize {
+        let levels = self.levels.lock().unwrap();
+        levels
+            .get(&std::thread::current().id())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn increase(&self) -> ThreadId {
+        let thread = std::thread::current().id();
+        *self.levels.lock().unwrap().entry(thread).or_insert(0) += 1;
+        thread
+    }
+
+    pub(crate) fn decrease(&self, thread: ThreadId) {
+        let mut levels = self.levels.lock().unwrap();
+        if let Some(level) = levels.get_mut(&thread) {
+            *level = level.saturating_sub(1);
+            if *level == 0 {
+                levels.remove(&thread);
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{gauge::Gauge, theme::Theme};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+pub(crate) struct JobsHeader {
+    running: AtomicUsize,
+    done: AtomicUsize,
+    failed: AtomicUsize,
+    gauges: Arc<Mutex<Vec<Gauge>>>,
+    progress: indicatif::ProgressBar,
+    is_shown: AtomicBool,
+}
+
+impl JobsHeader {
+    // Counts from the start but stays hidden until `show`, so bars added
+    // before the header is shown are still counted
+    pub(crate) fn new(theme: Arc<Theme>) -> Self {
+        let progress =
+            indicatif::ProgressBar::with_draw_target(None, indicatif::ProgressDrawTarget::hidden());
+        let gauges: Arc<Mutex<Vec<Gauge>>> = Arc::default();
+        let render_gauges = gauges.clone();
+        progress.set_style(
+            indicatif::ProgressStyle::with_template(
+                "Jobs: {msg} | elapsed {elapsed_precise}{gauges}",
+            )
+            .unwrap()
+            .with_key(
+                "gauges",
+                move |_: &indicatif::ProgressState, writer: &mut dyn std::fmt::Write| {
+                    for gauge in render_gauges.lock().unwrap().iter() {
+                        let _ = write!(writer, " | {}", gauge.render(&theme));
+                    }
+                },
+            ),
+        );
+        let header = Self {
+            running: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            gauges,
+            progress,
+            is_shown: AtomicBool::new(false),
+        };
+        header.update();
+        header
+    }
+
+    pub(crate) fn show(&self, multi_progress: &indicatif::MultiProgress) {
+        if !self.is_shown.swap(true, Ordering::Relaxed) {
+            multi_progress.insert(0, self.progress.clone());
+            self.progress
+                .enable_steady_tick(std::time::Duration::from_secs(1));
+        }
+    }
+
+    pub(crate) fn is_shown(&self) -> bool {
+        self.is_shown.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn start_job(&self) {
+        self.running.fetch_add(1, Ordering::Relaxed);
+        self.update();
+    }
+
+    pub(crate) fn finish_job(&self, is_failed: bool) {
+        self.running.fetch_sub(1, Ordering::Relaxed);
+        if is_failed {
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.done.fetch_add(1, Ordering::Relaxed);
+        }
+        self.update();
+    }
+
+    pub(crate) fn add_gauge(&self, gauge: Gauge) {
+        self.gauges.lock().unwrap().push(gauge);
+    }
+
+    // running, done and failed
+    pub(crate) fn counts(&self) -> (usize, usize, usize) {
+        (
+            self.running.load(Ordering::Relaxed),
+            self.done.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+        )
+    }
+
+    fn update(&self) {
+        let (running, done, failed) = self.counts();
+        self.progress
+            .set_message(format!("{running} running, {done} done, {failed} failed"));
+    }
+}
+
+impl Drop for JobsHeader {
+    fn drop(&mut self) {
+        self.progress.finish();
+    }
+}
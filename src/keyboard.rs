@@ -0,0 +1,226 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc,
+};
+
+#[cfg(all(unix, feature = "progress"))]
+const HELP_MESSAGE: &str = "[v] verbose passthrough  [p] pause new jobs  [q] cancel  [?] help";
+const POLL_INTERVAL_MS: i32 = 100;
+#[cfg(all(unix, feature = "progress"))]
+const CTRL_C: u8 = 0x03;
+
+#[derive(Debug, Default)]
+pub struct Controls {
+    is_passthrough: AtomicBool,
+    is_paused: AtomicBool,
+    is_cancel_requested: AtomicBool,
+    active_operations: AtomicUsize,
+}
+
+impl Controls {
+    pub fn is_passthrough(&self) -> bool {
+        self.is_passthrough.load(Ordering::Relaxed)
+    }
+
+    pub fn set_passthrough(&self, value: bool) {
+        self.is_passthrough.store(value, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, value: bool) {
+        self.is_paused.store(value, Ordering::Relaxed);
+    }
+
+    pub fn is_cancel_requested(&self) -> bool {
+        self.is_cancel_requested.load(Ordering::Relaxed)
+    }
+
+    pub fn request_cancel(&self) {
+        self.is_cancel_requested.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset_cancel(&self) {
+        self.is_cancel_requested.store(false, Ordering::Relaxed);
+    }
+
+    // Held while an operation that honours cancel runs. The request is
+    // cleared when the outermost one finishes so the next can start.
+    pub fn begin_operation(self: &Arc<Self>) -> OperationGuard {
+        self.active_operations.fetch_add(1, Ordering::Relaxed);
+        OperationGuard {
+            controls: self.clone(),
+        }
+    }
+
+    // Blocks at a safe point: schedulers call it before starting a job and
+    // long running work like downloads between chunks
+    pub fn wait_while_paused(&self) {
+        while self.is_paused() && !self.is_cancel_requested() {
+            std::thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS as u64));
+        }
+    }
+}
+
+pub struct OperationGuard {
+    controls: Arc<Controls>,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if self
+            .controls
+            .active_operations
+            .fetch_sub(1, Ordering::Relaxed)
+            == 1
+        {
+            self.controls.reset_cancel();
+        }
+    }
+}
+
+#[cfg(all(unix, feature = "progress"))]
+mod raw_input {
+    pub(super) struct RawInput {
+        original: libc::termios,
+    }
+
+    impl RawInput {
+        // Only line buffering, echo and signals are disabled so output
+        // processing (newline translation) keeps working for the renderer
+        pub(super) fn enable() -> Option<Self> {
+            unsafe {
+                if libc::isatty(libc::STDIN_FILENO) == 0 {
+                    return None;
+                }
+                let mut original: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                    return None;
+                }
+                let mut raw = original;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+                raw.c_cc[libc::VMIN] = 0;
+                raw.c_cc[libc::VTIME] = 0;
+                if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                    return None;
+                }
+                Some(Self { original })
+            }
+        }
+
+        pub(super) fn read_key(&self, timeout_ms: i32) -> Option<u8> {
+            unsafe {
+                let mut poll_fd = libc::pollfd {
+                    fd: libc::STDIN_FILENO,
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                if libc::poll(&mut poll_fd, 1, timeout_ms) <= 0 {
+                    return None;
+                }
+                let mut key = 0_u8;
+                if libc::read(libc::STDIN_FILENO, (&mut key as *mut u8).cast(), 1) == 1 {
+                    Some(key)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    impl Drop for RawInput {
+        fn drop(&mut self) {
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "progress")]
+pub(crate) struct KeyboardListener {
+    is_running: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+    footer: indicatif::ProgressBar,
+}
+
+#[cfg(feature = "progress")]
+impl KeyboardListener {
+    #[cfg(unix)]
+    pub(crate) fn start(
+        controls: Arc<Controls>,
+        multi_progress: &indicatif::MultiProgress,
+    ) -> Option<Self> {
+        let raw_input = raw_input::RawInput::enable()?;
+
+        let footer = multi_progress.add(indicatif::ProgressBar::new_spinner());
+        footer.set_style(indicatif::ProgressStyle::with_template("{msg}").unwrap());
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let is_running = is_running.clone();
+            let footer = footer.clone();
+            std::thread::spawn(move || {
+                let mut is_help_visible = false;
+                while is_running.load(Ordering::Relaxed) {
+                    let key = match raw_input.read_key(POLL_INTERVAL_MS) {
+                        Some(key) => key,
+                        None => continue,
+                    };
+                    match key {
+                        b'v' => controls.set_passthrough(!controls.is_passthrough()),
+                        b'p' => controls.set_paused(!controls.is_paused()),
+                        b'q' => controls.request_cancel(),
+                        CTRL_C => {
+                            if controls.is_cancel_requested() {
+                                // a second Ctrl+C exits immediately
+                                drop(raw_input);
+                                std::process::exit(130);
+                            }
+                            controls.request_cancel();
+                        }
+                        b'?' => is_help_visible = !is_help_visible,
+                        _ => continue,
+                    }
+                    footer.set_message(if is_help_visible {
+                        format!(
+                            "{HELP_MESSAGE} | passthrough: {} paused: {} cancelled: {}",
+                            controls.is_passthrough(),
+                            controls.is_paused(),
+                            controls.is_cancel_requested()
+                        )
+                    } else {
+                        String::new()
+                    });
+                }
+            })
+        };
+
+        Some(Self {
+            is_running,
+            thread: Some(thread),
+            footer,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn start(
+        _controls: Arc<Controls>,
+        _multi_progress: &indicatif::MultiProgress,
+    ) -> Option<Self> {
+        None
+    }
+}
+
+#[cfg(feature = "progress")]
+impl Drop for KeyboardListener {
+    fn drop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.footer.finish_and_clear();
+    }
+}
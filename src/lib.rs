@@ -1,19 +1,141 @@
 use anyhow::Context;
-use anyhow_source_location::{format_context, format_error};
-use indicatif::ProgressStyle;
+use anyhow_source_location::format_context;
+#[cfg(feature = "process")]
+use anyhow_source_location::format_error;
 use owo_colors::{OwoColorize, Stream::Stdout};
 use serde::{Deserialize, Serialize};
 use std::{
-    io::{BufRead, Write},
-    sync::{mpsc, Arc, Mutex},
+    cell::RefCell,
+    fmt::Write as _,
+    io::Write,
+    sync::{
+        atomic::{AtomicU8, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
-use strum::Display;
-
+#[cfg(feature = "process")]
+use std::{io::BufRead, sync::mpsc};
+use strum::{Display, IntoStaticStr};
+
+#[cfg(feature = "progress")]
+pub mod arrange;
+#[cfg(feature = "process")]
+pub mod audit;
+pub mod builder;
+#[cfg(feature = "process")]
+pub mod cache;
+pub mod capabilities;
+mod capture;
+pub mod cast;
+#[cfg(feature = "progress")]
+pub mod checklist;
+#[cfg(feature = "process")]
+pub mod child_progress;
+pub mod ci;
+#[cfg(feature = "progress")]
+mod clock;
+pub mod columns;
+#[cfg(feature = "progress")]
+mod countdown;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+#[cfg(feature = "download")]
+pub mod download;
+#[cfg(feature = "progress")]
+pub mod draw_target;
+mod editor;
+#[cfg(feature = "process")]
+pub mod execution_log;
+pub mod format;
+#[cfg(feature = "progress")]
+pub mod gauge;
+#[cfg(feature = "progress")]
+mod grid;
+#[cfg(feature = "progress")]
+pub mod handle;
+pub mod hints;
+mod indent;
+#[cfg(feature = "progress")]
+mod jobs;
+pub mod keyboard;
+mod log_file;
+pub mod logs;
+mod macros;
+#[cfg(feature = "markdown")]
 pub mod markdown;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod nested;
 mod null_term;
+pub mod output_lock;
+pub mod path;
+#[cfg(feature = "progress")]
+pub mod phases;
+#[cfg(feature = "process")]
+pub mod pipeline;
+#[cfg(feature = "process")]
+pub mod pool;
+#[cfg(feature = "progress")]
+mod prefix_column;
+#[cfg(feature = "progress")]
+pub mod preset;
+pub mod printable;
+#[cfg(feature = "progress")]
+pub mod progress_io;
+pub mod prompt;
+pub mod raw;
+mod recent;
+mod redact;
+pub mod report;
+pub mod scope;
+#[cfg(feature = "process")]
+pub mod script;
+#[cfg(any(feature = "dashboard", feature = "metrics"))]
+pub mod server;
+pub mod session;
+#[cfg(all(unix, feature = "signals"))]
+pub mod signals;
+#[cfg(feature = "progress")]
+pub mod snapshot;
+#[cfg(feature = "progress")]
+pub mod spinner;
+mod stream;
+pub mod suggest;
+pub mod summary;
+pub mod system;
+#[cfg(feature = "process")]
+pub mod template;
+pub mod text;
+pub mod theme;
+pub mod transform;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm_term;
+#[cfg(feature = "process")]
+pub mod watch;
+mod writer_term;
+
+pub use printable::Printable;
+#[cfg(feature = "derive")]
+pub use printer_derive::Printable;
+
+// lets #[derive(Printable)] name `::printer` inside this crate too
+extern crate self as printer;
 
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Display, Default, Serialize, Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Display,
+    IntoStaticStr,
+    Default,
+    Serialize,
+    Deserialize,
 )]
 
 pub enum Level {
@@ -35,58 +157,414 @@ pub struct Verbosity {
     pub is_tty: bool,
 }
 
-const PROGRESS_PREFIX_WIDTH: usize = 0;
+const LEVELS: [Level; 8] = [
+    Level::Trace,
+    Level::Debug,
+    Level::Message,
+    Level::Info,
+    Level::App,
+    Level::Warning,
+    Level::Error,
+    Level::Silent,
+];
+const LEVEL_UNSET: u8 = u8::MAX;
+
+// Runtime level shared by a printer and all of its live progress bars. Once
+// set it takes precedence over `Verbosity::level`.
+#[derive(Debug, Clone)]
+pub struct LevelHandle {
+    level: Arc<AtomicU8>,
+    quiet_count: Arc<AtomicUsize>,
+}
+
+impl Default for LevelHandle {
+    fn default() -> Self {
+        Self {
+            level: Arc::new(AtomicU8::new(LEVEL_UNSET)),
+            quiet_count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl LevelHandle {
+    pub fn get(&self) -> Option<Level> {
+        LEVELS
+            .get(self.level.load(Ordering::Relaxed) as usize)
+            .copied()
+    }
+
+    pub fn set(&self, level: Level) {
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        self.level.store(LEVEL_UNSET, Ordering::Relaxed);
+    }
+
+    fn resolve(&self, verbosity: Verbosity) -> Verbosity {
+        let level = if self.quiet_count.load(Ordering::Relaxed) > 0 {
+            Level::Silent
+        } else {
+            self.get().unwrap_or(verbosity.level)
+        };
+        Verbosity { level, ..verbosity }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RenderMode {
+    #[default]
+    Interactive,
+    Plain,
+}
+
+impl RenderMode {
+    pub fn detect() -> Self {
+        if std::env::var_os("CI").is_some() {
+            RenderMode::Plain
+        } else {
+            RenderMode::Interactive
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndentStyle {
+    pub width: usize,
+    pub is_guide_visible: bool,
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self {
+            width: 2,
+            is_guide_visible: false,
+        }
+    }
+}
+
+impl IndentStyle {
+    fn render(&self, depth: usize, guide: &str) -> String {
+        if self.is_guide_visible && self.width > 0 {
+            let guide = format!("{guide}{}", " ".repeat(self.width - 1));
+            guide
+                .repeat(depth)
+                .if_supports_color(Stdout, |text| text.dimmed())
+                .to_string()
+        } else {
+            " ".repeat(depth * self.width)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display, Serialize, Deserialize)]
+pub enum HeadingStyle {
+    #[default]
+    Markdown,
+    Underline,
+    Boxed,
+    Banner,
+}
+
+// Key order for printed objects. Declaration follows struct fields and the
+// insertion order of maps, so config dumps match the source file layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display, Serialize, Deserialize)]
+pub enum ObjectOrder {
+    #[default]
+    Declaration,
+    Alphabetical,
+}
+
+impl HeadingStyle {
+    fn render(&self, level: usize, name: &str, max_width: usize) -> Vec<String> {
+        let name_width = format::visible_width(name);
+        match self {
+            HeadingStyle::Markdown => {
+                let overflow = "›".repeat(level.saturating_sub(MAX_HEADING_LEVEL));
+                let marker = "#".repeat(level.min(MAX_HEADING_LEVEL));
+                if overflow.is_empty() {
+                    vec![format!("{marker} {name}")]
+                } else {
+                    vec![format!("{marker} {overflow} {name}")]
+                }
+            }
+            HeadingStyle::Underline => {
+                let rule = if level == 1 { "=" } else { "-" };
+                vec![name.to_string(), rule.repeat(name_width)]
+            }
+            HeadingStyle::Boxed => {
+                let border = "─".repeat(name_width + 2);
+                vec![
+                    format!("┌{border}┐"),
+                    format!("│ {name} │"),
+                    format!("└{border}┘"),
+                ]
+            }
+            HeadingStyle::Banner => {
+                let fill = max_width.saturating_sub(name_width + 2);
+                let left = fill / 2;
+                vec![format!(
+                    "{} {name} {}",
+                    "=".repeat(left),
+                    "=".repeat(fill - left)
+                )]
+            }
+        }
+    }
+}
+
+const MAX_HEADING_LEVEL: usize = 6;
+const OBJECT_KEY_MAX_WIDTH: usize = 32;
+const OBJECT_STRING_PREVIEW_LINES: usize = 8;
+#[cfg(feature = "progress")]
+const PLAIN_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
 
 fn is_verbosity_active(printer_level: Verbosity, verbosity: Level) -> bool {
     verbosity >= printer_level.level
 }
 
-fn format_log(indent: usize, max_width: usize, verbosity: Level, message: &str) -> String {
-    let mut result = format!(
-        "{}{}: {message}",
-        " ".repeat(indent),
-        verbosity
-            .to_string()
-            .if_supports_color(Stdout, |text| text.bold())
-    );
-    while result.len() < max_width {
-        result.push(' ');
-    }
-    result.push('\n');
-    result
+thread_local! {
+    // reused for every log line so verbose runs don't allocate a String per line
+    static LOG_BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+    // the message of a log_fmt call, before it is formatted into LOG_BUFFER
+    static MESSAGE_BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn with_log_buffer<Output>(function: impl FnOnce(&mut String) -> Output) -> Output {
+    with_buffer(&LOG_BUFFER, function)
+}
+
+fn with_message_buffer<Output>(
+    arguments: std::fmt::Arguments,
+    function: impl FnOnce(&str) -> Output,
+) -> Output {
+    with_buffer(&MESSAGE_BUFFER, |message| {
+        // writing to a String can't fail
+        let _ = std::fmt::Write::write_fmt(message, arguments);
+        function(message.as_str())
+    })
+}
+
+fn with_buffer<Output>(
+    buffer: &'static std::thread::LocalKey<RefCell<String>>,
+    function: impl FnOnce(&mut String) -> Output,
+) -> Output {
+    buffer.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut buffer) => {
+            buffer.clear();
+            function(&mut buffer)
+        }
+        // a log emitted while another is being written gets its own buffer
+        Err(_) => function(&mut String::new()),
+    })
+}
+
+// Writes the line padded with spaces to `max_width` display columns so it
+// covers whatever was drawn on the row before
+fn format_log<Output: std::fmt::Write>(
+    output: &mut Output,
+    indent: &str,
+    max_width: usize,
+    theme: &theme::Theme,
+    transformers: &transform::LevelTransformers,
+    verbosity: Level,
+    message: &str,
+) {
+    let color = theme.level_color(verbosity);
+    let mut write_line = |tag: &str, message: &str| {
+        let width = format::visible_width(indent)
+            + format::visible_width(tag)
+            + 2
+            + format::visible_width(message);
+        let padding = max_width.saturating_sub(width);
+        let _ = writeln!(
+            output,
+            "{indent}{}: {message}{:padding$}",
+            color.bold(tag),
+            ""
+        );
+    };
+    if transformers.is_empty() {
+        write_line(verbosity.into(), message);
+    } else {
+        let mut parts = transform::LogParts {
+            tag: verbosity.to_string(),
+            message: message.to_string(),
+        };
+        transformers.apply(verbosity, &mut parts);
+        write_line(parts.tag.as_str(), parts.message.as_str());
+    }
+}
+
+struct CollapseState {
+    buffer: String,
+    is_failed: bool,
+}
+
+struct SectionCollapse {
+    name: String,
+    start_time: std::time::Instant,
+    max_duration: std::time::Duration,
 }
 
 pub struct Section<'a> {
     pub printer: &'a mut Printer,
+    collapse: Option<SectionCollapse>,
+    scope: Option<scope::ScopeToken>,
 }
 
 impl<'a> Section<'a> {
     pub fn new(printer: &'a mut Printer, name: &str) -> anyhow::Result<Self> {
-        printer
-            .write(format!("{}{}:", " ".repeat(printer.indent), name.bold()).as_str())
+        let scope = printer
+            .write_section_header(name)
             .context(format_context!(""))?;
         printer.shift_right();
-        Ok(Self { printer })
+        Ok(Self {
+            printer,
+            collapse: None,
+            scope: Some(scope),
+        })
+    }
+
+    // The body is buffered and replaced by a single summary line if it
+    // finishes within max_duration without logging a Warning or Error
+    pub fn new_collapsible(
+        printer: &'a mut Printer,
+        name: &str,
+        max_duration: std::time::Duration,
+    ) -> anyhow::Result<Self> {
+        // the header goes into the buffer too
+        printer.collapse_stack.push(CollapseState {
+            buffer: String::new(),
+            is_failed: false,
+        });
+        let scope = match printer.write_section_header(name) {
+            Ok(scope) => scope,
+            Err(error) => {
+                printer.collapse_stack.pop();
+                return Err(error).context(format_context!(""));
+            }
+        };
+        printer.shift_right();
+        Ok(Self {
+            printer,
+            collapse: Some(SectionCollapse {
+                name: name.to_string(),
+                start_time: std::time::Instant::now(),
+                max_duration,
+            }),
+            scope: Some(scope),
+        })
+    }
+
+    pub fn mark_failed(&mut self) {
+        self.printer.mark_collapse_failed();
+    }
+
+    fn finish_collapse(&mut self, collapse: SectionCollapse) -> anyhow::Result<()> {
+        let state = match self.printer.collapse_stack.pop() {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let elapsed = collapse.start_time.elapsed();
+        let is_collapsed =
+            !state.is_failed && !std::thread::panicking() && elapsed <= collapse.max_duration;
+        if is_collapsed {
+            self.printer
+                .write(
+                    format!(
+                        "{}{} ... {} ({})\n",
+                        self.printer.indentation(),
+                        collapse.name.bold(),
+                        self.printer.theme.success.paint("done"),
+                        format::humanize_duration(elapsed)
+                    )
+                    .as_str(),
+                )
+                .context(format_context!(""))?;
+        } else {
+            self.printer
+                .write(state.buffer.as_str())
+                .context(format_context!(""))?;
+        }
+        Ok(())
     }
 }
 
 impl Drop for Section<'_> {
     fn drop(&mut self) {
         self.printer.shift_left();
+        // the buffered body belongs inside the CI group the scope closes
+        if let Some(collapse) = self.collapse.take() {
+            let _ = self.finish_collapse(collapse);
+        }
+        self.scope.take();
     }
 }
 
+#[cfg(feature = "progress")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishStyle {
+    // removes the bar from the screen
+    Clear,
+    // leaves the bar where it stopped and marks the job as failed
+    AbandonWithMessage(Arc<str>),
+    // fills the bar and shows the ending message if one was set
+    FinishAtTotal,
+    // leaves the bar and its message exactly as they are
+    Keep,
+}
+
+#[cfg(feature = "progress")]
+struct PlainStatus {
+    last_report: Option<std::time::Instant>,
+    is_counted: bool,
+}
+
+#[cfg(feature = "progress")]
 pub struct MultiProgressBar {
     lock: Arc<Mutex<()>>,
     printer_verbosity: Verbosity,
-    indent: usize,
+    level: LevelHandle,
+    indent: String,
     max_width: usize,
+    is_live_width: bool,
+    theme: Arc<theme::Theme>,
+    transformers: Arc<transform::LevelTransformers>,
     progress_width: usize,
+    preset: preset::Preset,
+    layout: preset::Layout,
+    capabilities: capabilities::Capabilities,
+    prefix_slot: Option<prefix_column::PrefixSlot>,
+    is_custom_style: bool,
     progress: Option<indicatif::ProgressBar>,
     final_message: Option<Arc<str>>,
     is_increasing: bool,
+    is_determinate: bool,
+    is_failed: bool,
+    clock: Arc<clock::PauseClock>,
+    jobs_header: Option<Arc<jobs::JobsHeader>>,
+    #[cfg(any(feature = "process", feature = "download"))]
+    controls: Arc<keyboard::Controls>,
+    #[cfg(feature = "process")]
+    execution_log: Arc<Mutex<execution_log::ExecutionLog>>,
+    #[cfg(feature = "process")]
+    audit: Option<Arc<audit::AuditLog>>,
+    log_writers: log_file::LogWriters,
+    suppressed: Arc<recent::SuppressedLines>,
+    secrets: redact::Secrets,
+    writer: SharedWriter,
+    plain: Option<PlainStatus>,
+    session: Option<session::BarSession>,
+    snapshot_slot: Option<Arc<snapshot::SnapshotSlot>>,
+    grid: Option<Arc<grid::Grid>>,
+    is_queued: bool,
+    arrangement: Option<Arc<arrange::Arrangement>>,
+    #[cfg(feature = "tui")]
+    tui_job: Option<tui::TuiJob>,
 }
 
+#[cfg(feature = "progress")]
 impl MultiProgressBar {
     pub fn total(&self) -> Option<u64> {
         if let Some(progress) = self.progress.as_ref() {
@@ -96,13 +574,135 @@ impl MultiProgressBar {
         }
     }
 
+    pub fn position(&self) -> Option<u64> {
+        self.progress.as_ref().map(|progress| progress.position())
+    }
+
+    // None for spinners, which have no meaningful completion ratio
+    pub fn percent(&self) -> Option<f64> {
+        if !self.is_determinate {
+            return None;
+        }
+        let progress = self.progress.as_ref()?;
+        let total = progress.length()?;
+        if total == 0 {
+            return Some(100.0);
+        }
+        Some((progress.position().min(total) as f64 / total as f64) * 100.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress
+            .as_ref()
+            .map(|progress| progress.is_finished())
+            .unwrap_or(false)
+    }
+
+    pub fn eta(&self) -> Option<std::time::Duration> {
+        if !self.is_determinate || self.is_finished() {
+            return None;
+        }
+        self.progress.as_ref().map(|progress| progress.eta())
+    }
+
+    pub fn set_preset(&mut self, preset: preset::Preset) {
+        self.preset = preset.supported(&self.capabilities);
+        self.is_custom_style = false;
+        self.update_progress_width();
+        self.layout = self.fitting_layout();
+        self.restyle();
+    }
+
+    fn restyle(&self) {
+        if let Some(progress) = self.progress.as_ref() {
+            let _lock = self.lock.lock().unwrap();
+            progress.set_style(self.clock.attach(self.preset.layout_style(
+                self.is_determinate,
+                &self.theme,
+                self.layout,
+                self.capabilities.color_depth(),
+            )));
+        }
+    }
+
+    fn fitting_layout(&self) -> preset::Layout {
+        let prefix_width = self
+            .progress
+            .as_ref()
+            .map(|progress| format::visible_width(progress.prefix().as_str()))
+            .unwrap_or(0);
+        self.preset.layout(
+            self.is_determinate,
+            self.total(),
+            self.line_width(),
+            prefix_width,
+        )
+    }
+
+    // steps down to a smaller layout in tiny terminals instead of wrapping
+    fn update_layout(&mut self) {
+        if self.is_custom_style || self.is_failed {
+            return;
+        }
+        let layout = self.fitting_layout();
+        if layout != self.layout {
+            self.layout = layout;
+            self.restyle();
+        }
+    }
+
     pub fn reset_elapsed(&mut self) {
+        self.clock.reset();
         if let Some(progress) = self.progress.as_mut() {
             progress.reset_elapsed();
         }
     }
 
+    // Stops the elapsed clock while the job is waiting on something other than work
+    pub fn pause(&mut self) {
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        if !self.clock.is_paused() {
+            return;
+        }
+        self.clock.resume();
+        if let Some(progress) = self.progress.as_ref() {
+            progress.reset_eta();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    // Time the bar has been running, excluding paused intervals
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.progress
+            .as_ref()
+            .map(|progress| self.clock.working_time(progress.elapsed()))
+            .unwrap_or_default()
+    }
+
     pub fn set_total(&mut self, total: u64) {
+        if !self.is_determinate {
+            // the spinner learned its total: switch to the counted template
+            self.is_determinate = true;
+            self.is_increasing = true;
+            if let Some(slot) = self.snapshot_slot.as_ref() {
+                slot.is_determinate.store(true, Ordering::Relaxed);
+            }
+            if !self.is_custom_style {
+                self.layout = self.fitting_layout();
+                self.restyle();
+            }
+            if let Some(progress) = self.progress.as_ref() {
+                let _lock = self.lock.lock().unwrap();
+                progress.set_length(total);
+                progress.set_position(0);
+            }
+        }
         if let Some(progress) = self.progress.as_mut() {
             if let Some(length) = progress.length() {
                 if length != total {
@@ -112,53 +712,238 @@ impl MultiProgressBar {
                 }
             }
         }
+        self.update_progress_width();
+        self.record_progress();
+        if let Some(plain) = self.plain.as_mut() {
+            plain.is_counted = true;
+        }
+    }
+
+    fn record_progress(&mut self) {
+        if let (Some(session), Some(progress)) = (self.session.as_mut(), self.progress.as_ref()) {
+            let total = self.is_determinate.then(|| progress.length()).flatten();
+            session.progress(progress.position(), total);
+        }
     }
 
     pub fn log(&mut self, verbosity: Level, message: &str) {
-        if is_verbosity_active(self.printer_verbosity, verbosity) {
-            let formatted_message = format_log(self.indent, self.max_width, verbosity, message);
-            let _lock = self.lock.lock().unwrap();
-            if let Some(progress) = self.progress.as_ref() {
-                progress.println(formatted_message.as_str());
-            } else {
-                print!("{formatted_message}");
+        let message = self.secrets.redact(message);
+        let message = message.as_ref();
+        let is_terminal_active =
+            is_verbosity_active(self.level.resolve(self.printer_verbosity), verbosity);
+        if !is_terminal_active {
+            self.suppressed.push(verbosity, message);
+        }
+        if is_terminal_active || self.log_writers.is_active(verbosity) {
+            with_log_buffer(|formatted_message| {
+                format_log(
+                    formatted_message,
+                    self.indent.as_str(),
+                    self.max_width,
+                    &self.theme,
+                    &self.transformers,
+                    verbosity,
+                    message,
+                );
+                self.log_writers
+                    .write(verbosity, formatted_message.as_str());
+                if !is_terminal_active {
+                    return;
+                }
+                if verbosity == Level::Error {
+                    let context = self.suppressed.take_formatted(
+                        self.indent.as_str(),
+                        self.max_width,
+                        &self.theme,
+                        &self.transformers,
+                    );
+                    if !context.is_empty() {
+                        self.print_line(context.as_str());
+                    }
+                }
+                self.print_line(formatted_message.as_str());
+            });
+        }
+    }
+
+    pub fn log_fmt(&mut self, verbosity: Level, arguments: std::fmt::Arguments) {
+        if is_verbosity_active(self.level.resolve(self.printer_verbosity), verbosity)
+            || self.log_writers.is_active(verbosity)
+            || self.suppressed.is_enabled()
+        {
+            with_message_buffer(arguments, |message| self.log(verbosity, message));
+        }
+    }
+
+    // The message is only built when the level passes the filter
+    pub fn log_with<Function: FnOnce() -> String>(&mut self, verbosity: Level, message: Function) {
+        if is_verbosity_active(self.level.resolve(self.printer_verbosity), verbosity)
+            || self.log_writers.is_active(verbosity)
+            || self.suppressed.is_enabled()
+        {
+            self.log(verbosity, message().as_str());
+        }
+    }
+
+    fn print_line(&self, formatted_message: &str) {
+        let _lock = self.lock.lock().unwrap();
+        if let Some(session) = self.session.as_ref() {
+            session.output(formatted_message);
+        }
+        #[cfg(feature = "tui")]
+        if let Some(tui_job) = self.tui_job.as_ref() {
+            tui_job.println(formatted_message);
+            return;
+        }
+        if let Some(grid) = self.grid.as_ref() {
+            if grid.println(formatted_message) {
+                return;
             }
         }
+        if let (true, Some(arrangement)) = (self.is_queued, self.arrangement.as_ref()) {
+            if arrangement.println(formatted_message) {
+                return;
+            }
+        }
+        if let (Some(progress), None) = (self.progress.as_ref(), self.plain.as_ref()) {
+            progress.println(formatted_message);
+        } else {
+            let _ = self
+                .writer
+                .lock()
+                .unwrap()
+                .write_all(formatted_message.as_bytes());
+        }
+    }
+
+    #[cfg(feature = "process")]
+    fn log_passthrough(&self, message: &str) {
+        if self.controls.is_passthrough() {
+            with_log_buffer(|formatted_message| {
+                format_log(
+                    formatted_message,
+                    self.indent.as_str(),
+                    self.max_width,
+                    &self.theme,
+                    &self.transformers,
+                    Level::Message,
+                    message,
+                );
+                self.print_line(formatted_message.as_str());
+            });
+        }
     }
 
     pub fn set_prefix(&mut self, message: &str) {
         if let Some(progress) = self.progress.as_mut() {
             let _lock = self.lock.lock().unwrap();
-            progress.set_prefix(message.to_owned());
+            match self.prefix_slot.as_ref() {
+                Some(slot) => slot.relabel(message),
+                None => progress.set_prefix(message.to_owned()),
+            }
+        }
+    }
+
+    // the prefix without the column padding
+    pub(crate) fn prefix_label(&self) -> String {
+        match (self.prefix_slot.as_ref(), self.progress.as_ref()) {
+            (Some(slot), _) => slot.label(),
+            (None, Some(progress)) => progress.prefix(),
+            (None, None) => String::new(),
         }
     }
 
     fn construct_message(&self, message: &str) -> String {
         let prefix_size = if let Some(progress) = self.progress.as_ref() {
-            progress.prefix().len()
-        } else {
-            0_usize
-        };
-        let length = if self.max_width > self.progress_width + prefix_size {
-            self.max_width - self.progress_width - prefix_size
+            format::visible_width(progress.prefix().as_str())
         } else {
             0_usize
         };
-        sanitize_output(message, length)
+        let length = self
+            .line_width()
+            .saturating_sub(self.progress_width + prefix_size);
+        format::fit_to_width(message, length)
+    }
+
+    // follows terminal resizes for stdout bars, otherwise the printer's width
+    fn line_width(&self) -> usize {
+        if self.is_live_width {
+            if let Some(width) = terminal_width() {
+                return width;
+            }
+        }
+        self.max_width
+    }
+
+    fn update_progress_width(&mut self) {
+        self.progress_width = self.preset.width(self.is_determinate, self.total());
     }
 
     pub fn set_message(&mut self, message: &str) {
-        let constructed_message = self.construct_message(message);
+        self.start();
+        self.update_layout();
+        if let Some(session) = self.session.as_ref() {
+            session.message(message);
+        }
+        let constructed_message = self.construct_message(self.secrets.redact(message).as_ref());
         if let Some(progress) = self.progress.as_mut() {
             let _lock = self.lock.lock().unwrap();
             progress.set_message(constructed_message);
         }
+        self.report_plain(false);
     }
 
     pub fn set_ending_message(&mut self, message: &str) {
         self.final_message = Some(self.construct_message(message).into());
     }
 
+    pub(crate) fn mark_failed(&mut self) {
+        self.is_failed = true;
+        if let Some(slot) = self.snapshot_slot.as_ref() {
+            slot.is_failed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // failed bars stay on screen in the error color so they stand out in a pool
+    fn show_failed(&mut self) {
+        if let Some(progress) = self.progress.as_ref() {
+            let _lock = self.lock.lock().unwrap();
+            progress.set_style(
+                self.clock
+                    .attach(self.preset.failed_style(self.is_determinate, &self.theme)),
+            );
+        }
+    }
+
+    pub fn finish_with(&mut self, style: FinishStyle) {
+        if let FinishStyle::AbandonWithMessage(_) = &style {
+            self.mark_failed();
+            self.show_failed();
+        }
+        let message = match &style {
+            FinishStyle::AbandonWithMessage(message) => Some(self.construct_message(message)),
+            _ => self
+                .final_message
+                .as_ref()
+                .map(|message| self.construct_message(message)),
+        };
+        if let Some(progress) = self.progress.as_mut() {
+            let _lock = self.lock.lock().unwrap();
+            match style {
+                FinishStyle::Clear => progress.finish_and_clear(),
+                FinishStyle::AbandonWithMessage(_) => {
+                    progress.abandon_with_message(message.unwrap_or_default())
+                }
+                FinishStyle::FinishAtTotal => match message {
+                    Some(message) => progress.finish_with_message(message.bold().to_string()),
+                    None => progress.finish(),
+                },
+                FinishStyle::Keep => progress.abandon(),
+            }
+        }
+        self.rearrange();
+    }
+
     pub fn increment_with_overflow(&mut self, count: u64) {
         let progress_total = self.total();
         if let Some(progress) = self.progress.as_mut() {
@@ -175,6 +960,7 @@ impl MultiProgressBar {
                 self.is_increasing = true;
             }
         }
+        self.record_progress();
     }
 
     pub fn decrement(&mut self, count: u64) {
@@ -186,64 +972,168 @@ impl MultiProgressBar {
                 progress.set_position(0);
             }
         }
+        self.record_progress();
     }
 
     pub fn increment(&mut self, count: u64) {
+        self.start();
         if let Some(progress) = self.progress.as_mut() {
             let _lock = self.lock.lock().unwrap();
             progress.inc(count);
         }
+        self.record_progress();
+        self.report_plain(false);
+    }
+
+    pub fn set_position(&mut self, position: u64) {
+        self.start();
+        if let Some(progress) = self.progress.as_mut() {
+            let _lock = self.lock.lock().unwrap();
+            progress.set_position(position);
+        }
+        self.record_progress();
+        self.report_plain(false);
+    }
+
+    fn report_plain(&mut self, is_final: bool) {
+        let plain = match (self.plain.as_mut(), self.progress.as_ref()) {
+            (Some(plain), Some(_)) => plain,
+            _ => return,
+        };
+        let is_due = plain
+            .last_report
+            .map(|last_report| last_report.elapsed() >= PLAIN_REPORT_INTERVAL)
+            .unwrap_or(true);
+        if !is_due && !is_final {
+            return;
+        }
+        plain.last_report = Some(std::time::Instant::now());
+
+        if let Some(line) = self.plain_status() {
+            let line = self.secrets.redact(line.as_str()).into_owned();
+            self.print_line(format!("{line}\n").as_str());
+        }
     }
 
+    // the line plain mode prints, like `[12/40] building sdk (45s)`
+    fn plain_status(&self) -> Option<String> {
+        let (plain, progress) = (self.plain.as_ref()?, self.progress.as_ref()?);
+        let count = if plain.is_counted {
+            format!(
+                "[{}/{}] ",
+                progress.position(),
+                progress.length().unwrap_or(0)
+            )
+        } else {
+            String::new()
+        };
+        Some(format!(
+            "{}{count}{} {} ({})",
+            self.indent,
+            progress.prefix(),
+            progress.message().trim_end(),
+            format::humanize_duration(self.clock.working_time(progress.elapsed()))
+        ))
+    }
+
+    #[cfg(feature = "process")]
     fn start_process(
         &mut self,
         command: &str,
         options: &ExecuteOptions,
+        progress: Option<&child_progress::ProgressChannel>,
     ) -> anyhow::Result<std::process::Child> {
         if let Some(directory) = &options.working_directory {
-            if !std::path::Path::new(directory.as_ref()).exists() {
-                return Err(format_error!("Directory does not exist: {directory}"));
+            if !directory.exists() {
+                return Err(format_error!(
+                    "Directory does not exist: {}",
+                    directory.display()
+                ));
             }
         }
 
-        let child_process = options.spawn(command).context(format_context!(
+        let child_process = options.spawn(command, progress).context(format_context!(
             "Failed to spawn a child process using {command}"
         ))?;
         Ok(child_process)
     }
 
+    #[cfg(feature = "process")]
     pub fn execute_process(
         &mut self,
         command: &str,
         options: ExecuteOptions,
     ) -> anyhow::Result<Option<String>> {
+        if let Some(cache) = options.cache.clone() {
+            return cache.run(command, &options, self, |progress_bar, options| {
+                progress_bar.execute_process(command, options)
+            });
+        }
         self.set_message(&options.get_full_command(command));
+        let progress = options
+            .is_child_protocol
+            .then(child_progress::ProgressChannel::new)
+            .flatten();
         let child_process = self
-            .start_process(command, &options)
+            .start_process(command, &options, progress.as_ref())
             .context(format_context!("Failed to start process {command}"))?;
-        let result =
-            monitor_process(command, child_process, self, &options).context(format_context!(""))?;
-        Ok(result)
+        let updates = progress.map(child_progress::ProgressChannel::listen);
+        let result = monitor_process(command, child_process, self, &options, updates);
+        if result.is_err() {
+            self.mark_failed();
+            self.show_failed();
+            if let Some(progress) = self.progress.as_ref() {
+                let _lock = self.lock.lock().unwrap();
+                progress.abandon();
+            }
+        }
+        result.context(format_context!(""))
     }
 }
 
+#[cfg(feature = "progress")]
 impl Drop for MultiProgressBar {
     fn drop(&mut self) {
         if let Some(message) = &self.final_message {
             let constructed_message = self.construct_message(message);
             if let Some(progress) = self.progress.as_mut() {
-                let _lock = self.lock.lock().unwrap();
-                progress.finish_with_message(constructed_message.bold().to_string());
+                // an explicit finish_with already decided how the bar ends
+                if !progress.is_finished() {
+                    let _lock = self.lock.lock().unwrap();
+                    progress.finish_with_message(constructed_message.bold().to_string());
+                }
             }
         }
+        self.report_plain(true);
+        if let (Some(slot), Some(progress)) = (self.snapshot_slot.as_ref(), self.progress.as_ref())
+        {
+            slot.finish(progress);
+        }
+        if let Some(session) = self.session.as_mut() {
+            session.is_failed = self.is_failed;
+        }
+        if let Some(jobs_header) = self.jobs_header.as_ref() {
+            jobs_header.finish_job(self.is_failed);
+        }
+        self.rearrange();
     }
 }
 
+#[cfg(feature = "progress")]
 pub struct MultiProgress<'a> {
     pub printer: &'a mut Printer,
     multi_progress: indicatif::MultiProgress,
+    jobs_header: Option<Arc<jobs::JobsHeader>>,
+    keyboard: Option<keyboard::KeyboardListener>,
+    registry: snapshot::Registry,
+    grid: Option<Arc<grid::Grid>>,
+    arrangement: Option<Arc<arrange::Arrangement>>,
+    prefix_column: Arc<prefix_column::PrefixColumn>,
+    #[cfg(feature = "tui")]
+    tui: Option<Arc<tui::TuiRenderer>>,
 }
 
+#[cfg(feature = "progress")]
 impl<'a> MultiProgress<'a> {
     pub fn new(printer: &'a mut Printer) -> Self {
         let locker = printer.lock.clone();
@@ -252,106 +1142,235 @@ impl<'a> MultiProgress<'a> {
         Self {
             printer,
             multi_progress: indicatif::MultiProgress::new(),
+            jobs_header: None,
+            keyboard: None,
+            registry: snapshot::Registry::default(),
+            grid: None,
+            arrangement: None,
+            prefix_column: Arc::new(prefix_column::PrefixColumn::default()),
+            #[cfg(feature = "tui")]
+            tui: None,
         }
     }
 
-    pub fn add_progress(
-        &mut self,
-        prefix: &str,
-        total: Option<u64>,
-        finish_message: Option<&str>,
-    ) -> MultiProgressBar {
-        let _lock = self.printer.lock.lock().unwrap();
+    // Pads every prefix to `width` columns instead of the widest prefix.
+    // `None` returns to automatic sizing.
+    pub fn set_prefix_width(&mut self, width: Option<usize>) {
+        self.prefix_column.set_width(width);
+    }
 
-        let template_string = "{elapsed_precise}|{bar:.cyan/blue}|{prefix} {msg}";
+    pub fn enable_keyboard_controls(&mut self) -> bool {
+        if self.keyboard.is_none() {
+            self.keyboard = keyboard::KeyboardListener::start(
+                self.printer.controls.clone(),
+                &self.multi_progress,
+            );
+        }
+        self.keyboard.is_some()
+    }
 
-        let (progress, progress_chars) = if let Some(total) = total {
-            let progress = indicatif::ProgressBar::new(total);
-            (progress, "#>-")
-        } else {
-            let progress = indicatif::ProgressBar::new(200);
-            (progress, "*>-")
-        };
+    #[cfg(feature = "tui")]
+    pub fn enable_tui(&mut self) -> bool {
+        if self.tui.is_none() && self.printer.verbosity.is_show_progress_bars {
+            if let Some(renderer) = tui::TuiRenderer::start() {
+                self.multi_progress
+                    .set_draw_target(indicatif::ProgressDrawTarget::hidden());
+                self.tui = Some(renderer);
+            }
+        }
+        self.tui.is_some()
+    }
 
-        progress.set_style(
-            ProgressStyle::with_template(template_string)
-                .unwrap()
-                .progress_chars(progress_chars),
-        );
+    // The header also shows up on its own once more than one bar is running
+    pub fn enable_jobs_header(&mut self) {
+        if let Some(jobs_header) = self.counted_jobs_header() {
+            jobs_header.show(&self.multi_progress);
+        }
+    }
 
-        let progress = if self.printer.verbosity.is_show_progress_bars {
-            let progress = self.multi_progress.add(progress);
-            let prefix = format!("{prefix}:");
-            progress.set_prefix(
-                format!("{prefix:width$}", width = PROGRESS_PREFIX_WIDTH)
-                    .if_supports_color(Stdout, |text| text.bold())
-                    .to_string(),
-            );
-            Some(progress)
-        } else {
-            None
-        };
+    fn counted_jobs_header(&mut self) -> Option<Arc<jobs::JobsHeader>> {
+        if self.jobs_header.is_none() && self.printer.verbosity.is_show_progress_bars {
+            self.jobs_header = Some(Arc::new(jobs::JobsHeader::new(self.printer.theme.clone())));
+        }
+        self.jobs_header.clone()
+    }
 
-        MultiProgressBar {
-            lock: self.printer.lock.clone(),
-            printer_verbosity: self.printer.verbosity,
-            indent: self.printer.indent,
-            progress,
-            progress_width: 28, // This is the default from indicatif?
-            max_width: self.printer.max_width,
-            final_message: finish_message.map(|s| s.into()),
-            is_increasing: true,
+    pub fn add_progress(
+        &mut self,
+        prefix: &str,
+        total: Option<u64>,
+        finish_message: Option<&str>,
+    ) -> MultiProgressBar {
+        let mut progress_bar = self.printer.create_progress_bar(
+            &self.multi_progress,
+            &self.prefix_column,
+            prefix,
+            total,
+            finish_message,
+        );
+        if let Some(jobs_header) = self.counted_jobs_header() {
+            jobs_header.start_job();
+            // plain mode already reports every bar on its own lines
+            let (running, _, _) = jobs_header.counts();
+            if !jobs_header.is_shown()
+                && running > 1
+                && self.printer.render_mode == RenderMode::Interactive
+            {
+                jobs_header.show(&self.multi_progress);
+            }
+            progress_bar.jobs_header = Some(jobs_header);
+        }
+        #[cfg(feature = "tui")]
+        if let (Some(renderer), Some(progress)) =
+            (self.tui.as_ref(), progress_bar.progress.as_ref())
+        {
+            progress_bar.tui_job = Some(renderer.add_job(progress.clone()));
         }
+        self.registry.register(&mut progress_bar);
+        self.add_to_grid(&mut progress_bar);
+        self.arrange(&mut progress_bar, None);
+        progress_bar
+    }
+
+    pub fn add_progress_with_preset(
+        &mut self,
+        prefix: &str,
+        total: Option<u64>,
+        finish_message: Option<&str>,
+        preset: preset::Preset,
+    ) -> MultiProgressBar {
+        let mut progress_bar = self.add_progress(prefix, total, finish_message);
+        progress_bar.set_preset(preset);
+        progress_bar
     }
 }
 
 pub struct Heading<'a> {
     pub printer: &'a mut Printer,
+    level: usize,
+    scope: Option<scope::ScopeToken>,
 }
 
 impl<'a> Heading<'a> {
     pub fn new(printer: &'a mut Printer, name: &str) -> anyhow::Result<Self> {
-        printer.newline().context(format_context!(""))?;
-        printer.enter_heading();
-        {
-            let heading = if printer.heading_count == 1 {
-                format!("{} {name}", "#".repeat(printer.heading_count))
-                    .yellow()
-                    .bold()
-                    .to_string()
-            } else {
-                format!("{} {name}", "#".repeat(printer.heading_count))
-                    .bold()
-                    .to_string()
-            };
-            printer
-                .write(heading.as_str())
-                .context(format_context!(""))?;
-            printer.write("\n").context(format_context!(""))?;
-        }
-        Ok(Self { printer })
+        let (level, scope) = printer
+            .write_heading(name, None)
+            .context(format_context!(""))?;
+        Ok(Self {
+            printer,
+            level,
+            scope: Some(scope),
+        })
+    }
+
+    // renders at `level` regardless of nesting; nesting is still tracked
+    pub fn new_with_level(
+        printer: &'a mut Printer,
+        name: &str,
+        level: usize,
+    ) -> anyhow::Result<Self> {
+        let (level, scope) = printer
+            .write_heading(name, Some(level))
+            .context(format_context!(""))?;
+        Ok(Self {
+            printer,
+            level,
+            scope: Some(scope),
+        })
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
     }
 }
 
 impl Drop for Heading<'_> {
     fn drop(&mut self) {
         self.printer.exit_heading();
+        self.scope.take();
+    }
+}
+
+pub struct HeadingGuard {
+    heading_count: Arc<AtomicUsize>,
+    scope: Option<scope::ScopeToken>,
+}
+
+impl Drop for HeadingGuard {
+    fn drop(&mut self) {
+        self.heading_count.fetch_sub(1, Ordering::Relaxed);
+        self.scope.take();
+    }
+}
+
+pub struct SectionGuard {
+    indent: Arc<indent::IndentLevels>,
+    thread: std::thread::ThreadId,
+    scope: Option<scope::ScopeToken>,
+}
+
+impl Drop for SectionGuard {
+    fn drop(&mut self) {
+        self.indent.decrease(self.thread);
+        self.scope.take();
+    }
+}
+
+pub struct QuietGuard {
+    quiet_count: Arc<AtomicUsize>,
+}
+
+impl Drop for QuietGuard {
+    fn drop(&mut self) {
+        self.quiet_count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub struct IndentGuard {
+    indent: Arc<indent::IndentLevels>,
+    thread: std::thread::ThreadId,
+}
+
+impl Drop for IndentGuard {
+    fn drop(&mut self) {
+        self.indent.decrease(self.thread);
     }
 }
 
+#[cfg(feature = "process")]
 #[derive(Clone, Debug)]
 pub struct ExecuteOptions {
     pub label: Arc<str>,
     pub is_return_stdout: bool,
-    pub working_directory: Option<Arc<str>>,
+    pub working_directory: Option<std::path::PathBuf>,
     pub environment: Vec<(Arc<str>, Arc<str>)>,
-    pub arguments: Vec<Arc<str>>,
+    pub arguments: Vec<std::ffi::OsString>,
     pub log_file_path: Option<Arc<str>>,
+    pub is_append_log: bool,
     pub clear_environment: bool,
     pub process_started_with_id: Option<fn(&str, u32)>,
     pub log_level: Option<Level>,
+    pub line_discipline: LineDiscipline,
+    pub cache: Option<Arc<cache::ProcessCache>>,
+    pub cache_inputs: Vec<std::path::PathBuf>,
+    pub cache_environment: Vec<Arc<str>>,
+    // tells the child it runs under a printer: PRINTER_NESTED is set and a
+    // progress descriptor is attached, see child_progress
+    pub is_child_protocol: bool,
+}
+
+#[cfg(feature = "process")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineDiscipline {
+    // every newline terminated line is a message
+    #[default]
+    Newline,
+    // `\r` redraws the current message like curl or wget do on a terminal; only
+    // completed lines are logged
+    CarriageReturn,
 }
 
+#[cfg(feature = "process")]
 impl Default for ExecuteOptions {
     fn default() -> Self {
         Self {
@@ -361,51 +1380,84 @@ impl Default for ExecuteOptions {
             environment: vec![],
             arguments: vec![],
             log_file_path: None,
+            is_append_log: false,
             clear_environment: false,
             process_started_with_id: None,
             log_level: None,
+            line_discipline: LineDiscipline::Newline,
+            cache: None,
+            cache_inputs: vec![],
+            cache_environment: vec![],
+            is_child_protocol: false,
         }
     }
 }
 
+#[cfg(feature = "process")]
 impl ExecuteOptions {
-    fn process_child_output<OutputType: std::io::Read + Send + 'static>(
+    pub(crate) fn process_child_output<OutputType: std::io::Read + Send + 'static>(
         output: OutputType,
+        redraws: Option<mpsc::Sender<String>>,
     ) -> anyhow::Result<(std::thread::JoinHandle<()>, mpsc::Receiver<String>)> {
         let (tx, rx) = mpsc::channel::<String>();
 
         let thread = std::thread::spawn(move || {
             use std::io::BufReader;
-            let reader = BufReader::new(output);
-            for line in reader.lines() {
-                let line = line.unwrap();
-                tx.send(line).unwrap();
+            match redraws {
+                Some(redraws) => read_redrawn_lines(output, &tx, &redraws),
+                None => {
+                    let reader = BufReader::new(output);
+                    for line in reader.lines() {
+                        let line = line.unwrap();
+                        tx.send(line).unwrap();
+                    }
+                }
             }
         });
 
         Ok((thread, rx))
     }
 
-    fn spawn(&self, command: &str) -> anyhow::Result<std::process::Child> {
-        use std::process::{Command, Stdio};
-        let mut process = Command::new(command);
+    pub(crate) fn create_command(&self, command: &str) -> std::process::Command {
+        let mut process = std::process::Command::new(command);
 
         if self.clear_environment {
             process.env_clear();
         }
 
-        for argument in &self.arguments {
-            process.arg(argument.as_ref());
+        // children that use this crate report in plain mode under our bars
+        if self.is_child_protocol {
+            process.env(nested::NESTED_ENV_VAR, nested::child_depth());
         }
 
+        process.args(&self.arguments);
+
         if let Some(directory) = &self.working_directory {
-            process.current_dir(directory.as_ref());
+            process.current_dir(directory);
         }
 
         for (key, value) in self.environment.iter() {
             process.env(key.as_ref(), value.as_ref());
         }
+        process
+    }
+
+    pub(crate) fn notify_started(&self, process_id: u32) {
+        if let Some(callback) = self.process_started_with_id.as_ref() {
+            callback(self.label.as_ref(), process_id);
+        }
+    }
 
+    fn spawn(
+        &self,
+        command: &str,
+        progress: Option<&child_progress::ProgressChannel>,
+    ) -> anyhow::Result<std::process::Child> {
+        use std::process::Stdio;
+        let mut process = self.create_command(command);
+        if let Some(progress) = progress {
+            progress.attach(&mut process);
+        }
         let result = process
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -413,73 +1465,254 @@ impl ExecuteOptions {
             .spawn()
             .context(format_context!("{command}"))?;
 
-        if let Some(callback) = self.process_started_with_id.as_ref() {
-            callback(self.label.as_ref(), result.id());
-        }
+        self.notify_started(result.id());
 
         Ok(result)
     }
 
+    pub fn builder() -> builder::ExecuteOptionsBuilder {
+        builder::ExecuteOptionsBuilder::default()
+    }
+
+    pub fn add_argument<Argument: Into<std::ffi::OsString>>(&mut self, argument: Argument) {
+        self.arguments.push(argument.into());
+    }
+
+    pub fn set_working_directory<Directory: Into<std::path::PathBuf>>(
+        &mut self,
+        directory: Directory,
+    ) {
+        self.working_directory = Some(directory.into());
+    }
+
+    // lossy, for display and logs only
+    pub fn get_arguments(&self) -> Vec<Arc<str>> {
+        self.arguments
+            .iter()
+            .map(|argument| argument.to_string_lossy().into())
+            .collect()
+    }
+
+    pub fn get_working_directory(&self) -> Option<Arc<str>> {
+        self.working_directory
+            .as_ref()
+            .map(|directory| directory.to_string_lossy().into())
+    }
+
     pub fn get_full_command(&self, command: &str) -> String {
-        format!("{command} {}", self.arguments.join(" "))
+        format!("{command} {}", self.get_arguments().join(" "))
     }
 
     pub fn get_full_command_in_working_directory(&self, command: &str) -> String {
         format!(
             "{} {command} {}",
-            if let Some(directory) = &self.working_directory {
-                directory
-            } else {
-                ""
-            },
-            self.arguments.join(" "),
+            self.get_working_directory().as_deref().unwrap_or(""),
+            self.get_arguments().join(" "),
         )
     }
 }
 
+// bars draw through the writer, so with progress it must also be a terminal
+#[cfg(feature = "progress")]
 trait PrinterTrait: std::io::Write + indicatif::TermLike {}
+#[cfg(feature = "progress")]
 impl<W: std::io::Write + indicatif::TermLike> PrinterTrait for W {}
+#[cfg(not(feature = "progress"))]
+trait PrinterTrait: std::io::Write + Send {}
+#[cfg(not(feature = "progress"))]
+impl<W: std::io::Write + Send> PrinterTrait for W {}
+
+// shared with the printer's bars so plain mode reports reach the same target
+type SharedWriter = Arc<Mutex<Box<dyn PrinterTrait>>>;
 
 pub struct Printer {
     pub verbosity: Verbosity,
+    pub render_mode: RenderMode,
+    #[cfg(feature = "progress")]
+    pub progress_preset: preset::Preset,
+    theme: Arc<theme::Theme>,
+    transformers: Arc<transform::LevelTransformers>,
+    pub indent_style: IndentStyle,
+    pub heading_style: HeadingStyle,
+    pub object_order: ObjectOrder,
+    capabilities: capabilities::Capabilities,
+    path_root: Option<std::path::PathBuf>,
+    #[cfg(feature = "markdown")]
+    heading_records: Option<Vec<markdown::HeadingRecord>>,
     lock: Arc<Mutex<()>>,
-    indent: usize,
-    heading_count: usize,
+    indent: Arc<indent::IndentLevels>,
+    heading_count: Arc<AtomicUsize>,
+    level: LevelHandle,
     max_width: usize,
-    writer: Box<dyn PrinterTrait>,
+    is_live_width: bool,
+    recorder: Option<session::SharedRecorder>,
+    collapse_stack: Vec<CollapseState>,
+    capture_stack: Vec<capture::CaptureState>,
+    controls: Arc<keyboard::Controls>,
+    #[cfg(feature = "process")]
+    execution_log: Arc<Mutex<execution_log::ExecutionLog>>,
+    #[cfg(feature = "process")]
+    audit: Option<Arc<audit::AuditLog>>,
+    log_writers: log_file::LogWriters,
+    suppressed: Arc<recent::SuppressedLines>,
+    secrets: redact::Secrets,
+    scopes: Arc<scope::Scopes>,
+    ci: Option<Arc<ci::Ci>>,
+    #[cfg(feature = "progress")]
+    spinner: spinner::ActiveSpinner,
+    output_lock: Option<Arc<output_lock::GlobalOutputLock>>,
+    output_lock_path: Option<std::path::PathBuf>,
+    writer: SharedWriter,
 }
 
 impl Printer {
     pub fn new_stdout() -> Self {
-        let mut max_width = 80_usize;
-        if let Some((width, _)) = terminal_size::terminal_size() {
-            // leave a buffer of 8 characters
-            max_width = width.0 as usize - 8;
+        let max_width = terminal_width().unwrap_or(80);
+        let capabilities = capabilities::Capabilities::detect();
+        // bars are redrawn in place, which needs cursor movement
+        let mut render_mode = if capabilities.is_cursor_control {
+            RenderMode::detect()
+        } else {
+            RenderMode::Plain
+        };
+        if nested::is_nested() {
+            render_mode = RenderMode::Plain;
         }
-        Self {
-            indent: 0,
-            lock: Arc::new(Mutex::new(())),
-            verbosity: Verbosity::default(),
-            heading_count: 0,
-            max_width,
-            writer: Box::new(console::Term::stdout()),
+        #[cfg(feature = "progress")]
+        let stdout = console::Term::stdout();
+        #[cfg(not(feature = "progress"))]
+        let stdout = std::io::stdout();
+        let mut printer = Self::new_with_writer(Box::new(stdout), render_mode, max_width);
+        if render_mode == RenderMode::Interactive {
+            printer.output_lock_path = output_lock::GlobalOutputLock::terminal_path();
+        }
+        printer.is_live_width = true;
+        printer.set_capabilities(capabilities);
+        if let Some(palette) = theme::Palette::from_env() {
+            printer.set_theme(palette.theme());
+        }
+        printer
+    }
+
+    // Some when this printer owns the terminal's redraws
+    pub fn output_lock(&self) -> Option<&output_lock::GlobalOutputLock> {
+        self.output_lock.as_deref()
+    }
+
+    // Only one printer per terminal redraws, any nested one goes plain. The
+    // lock is taken with the first bar so printers that never draw one
+    // leave no lock file behind.
+    #[cfg(feature = "progress")]
+    fn claim_screen(&mut self) {
+        let Some(path) = self.output_lock_path.take() else {
+            return;
+        };
+        if self.render_mode != RenderMode::Interactive {
+            return;
+        }
+        match output_lock::GlobalOutputLock::acquire_shared(path.as_path()) {
+            Ok(Some(lock)) => self.output_lock = Some(lock),
+            Ok(None) => self.render_mode = RenderMode::Plain,
+            // a lock file that cannot be used is no reason to stop output
+            Err(_) => {}
         }
     }
 
     pub fn new_null_term() -> Self {
+        Self::new_with_writer(
+            Box::new(null_term::NullTerm {}),
+            RenderMode::Interactive,
+            80,
+        )
+    }
+
+    // Backs the printer with any writer such as an in-memory buffer or a socket.
+    // There is no cursor to redraw bars on, so progress is reported in plain mode.
+    pub fn from_writer<W: std::io::Write + Send + 'static>(writer: W) -> Self {
+        Self::new_with_writer(
+            Box::new(writer_term::WriterTerm::new(writer, 80)),
+            RenderMode::Plain,
+            80,
+        )
+    }
+
+    fn new_with_writer(
+        writer: Box<dyn PrinterTrait>,
+        render_mode: RenderMode,
+        max_width: usize,
+    ) -> Self {
         Self {
-            indent: 0,
+            indent: Arc::new(indent::IndentLevels::default()),
             lock: Arc::new(Mutex::new(())),
             verbosity: Verbosity::default(),
-            heading_count: 0,
-            max_width: 80,
-            writer: Box::new(null_term::NullTerm {}),
+            render_mode,
+            #[cfg(feature = "progress")]
+            progress_preset: preset::Preset::default(),
+            theme: Arc::new(theme::Theme::default()),
+            transformers: Arc::new(transform::LevelTransformers::default()),
+            indent_style: IndentStyle::default(),
+            heading_style: HeadingStyle::default(),
+            object_order: ObjectOrder::default(),
+            capabilities: capabilities::Capabilities::default(),
+            path_root: None,
+            #[cfg(feature = "markdown")]
+            heading_records: None,
+            heading_count: Arc::new(AtomicUsize::new(0)),
+            level: LevelHandle::default(),
+            max_width,
+            is_live_width: false,
+            recorder: None,
+            collapse_stack: Vec::new(),
+            capture_stack: Vec::new(),
+            controls: Arc::new(keyboard::Controls::default()),
+            #[cfg(feature = "process")]
+            execution_log: Arc::new(Mutex::new(execution_log::ExecutionLog::default())),
+            #[cfg(feature = "process")]
+            audit: None,
+            log_writers: log_file::LogWriters::default(),
+            suppressed: Arc::new(recent::SuppressedLines::default()),
+            secrets: redact::Secrets::default(),
+            scopes: Arc::new(scope::Scopes::default()),
+            ci: None,
+            #[cfg(feature = "progress")]
+            spinner: spinner::ActiveSpinner::default(),
+            output_lock: None,
+            output_lock_path: None,
+            writer: Arc::new(Mutex::new(writer)),
         }
     }
 
     pub(crate) fn write(&mut self, message: &str) -> anyhow::Result<()> {
+        let message = self.secrets.redact(message);
+        let message = message.as_ref();
+        if let Some(state) = self.collapse_stack.last_mut() {
+            state.buffer.push_str(message);
+            return Ok(());
+        }
+        // a tee passes the output on to the enclosing capture or the terminal
+        for state in self.capture_stack.iter_mut().rev() {
+            state.buffer.push_str(message);
+            if !state.is_tee {
+                return Ok(());
+            }
+        }
         let _lock = self.lock.lock().unwrap();
-        write!(self.writer, "{}", message).context(format_context!(""))?;
+        session::record(
+            self.recorder.as_ref(),
+            session::SessionEvent::Output {
+                text: message.to_string(),
+            },
+        );
+        #[cfg(feature = "progress")]
+        let spinner = self.spinner.lock().unwrap().clone();
+        let mut writer = self.writer.lock().unwrap();
+        #[cfg(feature = "progress")]
+        if let Some(spinner) = spinner {
+            return spinner
+                .suspend(|| write!(writer, "{}", message))
+                .context(format_context!(""));
+        }
+        write!(writer, "{}", message).context(format_context!(""))?;
         Ok(())
     }
 
@@ -489,7 +1722,7 @@ impl Printer {
     }
 
     pub fn trace<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
-        if is_verbosity_active(self.verbosity, Level::Trace) {
+        if is_verbosity_active(self.active_verbosity(), Level::Trace) {
             self.object(name, value)
         } else {
             Ok(())
@@ -497,7 +1730,7 @@ impl Printer {
     }
 
     pub fn debug<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
-        if is_verbosity_active(self.verbosity, Level::Debug) {
+        if is_verbosity_active(self.active_verbosity(), Level::Debug) {
             self.object(name, value)
         } else {
             Ok(())
@@ -505,7 +1738,7 @@ impl Printer {
     }
 
     pub fn message<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
-        if is_verbosity_active(self.verbosity, Level::Message) {
+        if is_verbosity_active(self.active_verbosity(), Level::Message) {
             self.object(name, value)
         } else {
             Ok(())
@@ -513,7 +1746,7 @@ impl Printer {
     }
 
     pub fn info<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
-        if is_verbosity_active(self.verbosity, Level::Info) {
+        if is_verbosity_active(self.active_verbosity(), Level::Info) {
             self.object(name, value)
         } else {
             Ok(())
@@ -521,25 +1754,142 @@ impl Printer {
     }
 
     pub fn warning<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
-        if is_verbosity_active(self.verbosity, Level::Warning) {
-            self.object(name.yellow().to_string().as_str(), value)
+        self.warning_with_location(None, name, value)
+    }
+
+    pub fn error<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
+        self.error_with_location(None, name, value)
+    }
+
+    pub fn warning_at<Type: Serialize>(
+        &mut self,
+        location: &ci::SourceLocation,
+        name: &str,
+        value: &Type,
+    ) -> anyhow::Result<()> {
+        self.warning_with_location(Some(location), name, value)
+    }
+
+    pub fn error_at<Type: Serialize>(
+        &mut self,
+        location: &ci::SourceLocation,
+        name: &str,
+        value: &Type,
+    ) -> anyhow::Result<()> {
+        self.error_with_location(Some(location), name, value)
+    }
+
+    fn warning_with_location<Type: Serialize>(
+        &mut self,
+        location: Option<&ci::SourceLocation>,
+        name: &str,
+        value: &Type,
+    ) -> anyhow::Result<()> {
+        self.mark_collapse_failed();
+        self.annotate(Level::Warning, name, value, location);
+        if is_verbosity_active(self.active_verbosity(), Level::Warning) {
+            let name = self.theme.level_color(Level::Warning).paint(name);
+            self.object(name.as_str(), value)
         } else {
             Ok(())
         }
     }
 
-    pub fn error<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
-        if is_verbosity_active(self.verbosity, Level::Error) {
-            self.object(name.red().to_string().as_str(), value)
+    fn error_with_location<Type: Serialize>(
+        &mut self,
+        location: Option<&ci::SourceLocation>,
+        name: &str,
+        value: &Type,
+    ) -> anyhow::Result<()> {
+        self.mark_collapse_failed();
+        self.annotate(Level::Error, name, value, location);
+        if is_verbosity_active(self.active_verbosity(), Level::Error) {
+            let name = self.theme.level_color(Level::Error).paint(name);
+            self.object(name.as_str(), value)
         } else {
             Ok(())
         }
     }
 
+    fn annotate<Type: Serialize>(
+        &self,
+        level: Level,
+        name: &str,
+        value: &Type,
+        location: Option<&ci::SourceLocation>,
+    ) {
+        if let Some(ci) = self.ci.as_ref() {
+            let message = match serde_json::to_value(value) {
+                Ok(serde_json::Value::String(value)) => format!("{name}: {value}"),
+                Ok(value) => format!("{name}: {value}"),
+                Err(_) => name.to_string(),
+            };
+            ci.annotate(level, message.as_str(), location);
+        }
+    }
+
+    pub fn enable_github_actions(&mut self) {
+        self.set_ci_adapter(Box::new(ci::GithubActions));
+    }
+
+    pub fn set_ci_adapter(&mut self, adapter: Box<dyn ci::CiAdapter>) {
+        self.ci = Some(Arc::new(ci::Ci::new(adapter)));
+    }
+
     pub fn log(&mut self, level: Level, message: &str) -> anyhow::Result<()> {
-        if is_verbosity_active(self.verbosity, level) {
-            self.write(format_log(self.indent, self.max_width, level, message).as_str())
+        if level >= Level::Warning && level != Level::Silent {
+            self.mark_collapse_failed();
+        }
+        let is_terminal_active = is_verbosity_active(self.active_verbosity(), level);
+        let is_suppressed = !is_terminal_active && self.suppressed.is_enabled();
+        if !is_terminal_active && !is_suppressed && !self.log_writers.is_active(level) {
+            return Ok(());
+        }
+        let message = self.shorten_paths(self.secrets.redact(message).as_ref());
+        if is_suppressed {
+            self.suppressed.push(level, &message);
+        }
+        let indentation = self.indentation();
+        with_log_buffer(|formatted_message| {
+            format_log(
+                formatted_message,
+                indentation.as_str(),
+                self.max_width,
+                &self.theme,
+                &self.transformers,
+                level,
+                &message,
+            );
+            self.log_writers.write(level, formatted_message.as_str());
+            if !is_terminal_active {
+                return Ok(());
+            }
+            if level == Level::Error {
+                let context = self.suppressed.take_formatted(
+                    indentation.as_str(),
+                    self.max_width,
+                    &self.theme,
+                    &self.transformers,
+                );
+                if !context.is_empty() {
+                    self.write(context.as_str()).context(format_context!(""))?;
+                }
+            }
+            self.write(formatted_message.as_str())
+        })
+    }
+
+    pub fn log_fmt(&mut self, level: Level, arguments: std::fmt::Arguments) -> anyhow::Result<()> {
+        if is_verbosity_active(self.active_verbosity(), level)
+            || self.log_writers.is_active(level)
+            || self.suppressed.is_enabled()
+        {
+            with_message_buffer(arguments, |message| self.log(level, message))
         } else {
+            // filtered warnings still fail a collapsed section
+            if level >= Level::Warning && level != Level::Silent {
+                self.mark_collapse_failed();
+            }
             Ok(())
         }
     }
@@ -553,60 +1903,323 @@ impl Printer {
     fn object<Type: Serialize>(&mut self, name: &str, value: &Type) -> anyhow::Result<()> {
         let value = serde_json::to_value(value).context(format_context!(""))?;
 
-        if self.verbosity.level <= Level::Message && value == serde_json::Value::Null {
+        if self.active_verbosity().level <= Level::Message && value == serde_json::Value::Null {
             return Ok(());
         }
 
+        self.print_object(name, &value, &hints::Hints::default())
+    }
+
+    fn print_object(
+        &mut self,
+        name: &str,
+        value: &serde_json::Value,
+        hints: &hints::Hints,
+    ) -> anyhow::Result<()> {
         self.write(
             format!(
                 "{}{}: ",
-                " ".repeat(self.indent),
+                self.indentation(),
                 name.if_supports_color(Stdout, |text| text.bold())
             )
             .as_str(),
         )?;
 
-        self.print_value(&value).context(format_context!(""))?;
+        self.print_value(value, hints.resolve(name), hints)
+            .context(format_context!(""))?;
         Ok(())
     }
 
+    fn mark_collapse_failed(&mut self) {
+        self.scopes.mark_failed();
+        for state in self.collapse_stack.iter_mut() {
+            state.is_failed = true;
+        }
+    }
+
+    #[cfg(feature = "progress")]
+    fn create_progress_bar(
+        &mut self,
+        multi_progress: &indicatif::MultiProgress,
+        prefix_column: &Arc<prefix_column::PrefixColumn>,
+        prefix: &str,
+        total: Option<u64>,
+        finish_message: Option<&str>,
+    ) -> MultiProgressBar {
+        if self.verbosity.is_show_progress_bars {
+            self.claim_screen();
+        }
+        let _lock = self.lock.lock().unwrap();
+
+        let clock = Arc::new(clock::PauseClock::default());
+        let preset = self.progress_preset.supported(&self.capabilities);
+        let progress = indicatif::ProgressBar::new(total.unwrap_or(200));
+        progress.set_style(clock.attach(preset.layout_style(
+            total.is_some(),
+            &self.theme,
+            preset::Layout::Full,
+            self.capabilities.color_depth(),
+        )));
+
+        let is_plain = self.render_mode == RenderMode::Plain;
+        let progress = if self.verbosity.is_show_progress_bars {
+            let progress = if is_plain {
+                progress.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+                progress
+            } else {
+                multi_progress.add(progress)
+            };
+            Some(progress)
+        } else {
+            None
+        };
+        let prefix_slot = progress.as_ref().map(|progress| {
+            let label = format!("{prefix}:")
+                .if_supports_color(Stdout, |text| text.bold())
+                .to_string();
+            prefix_column.register(progress, label.as_str())
+        });
+
+        let mut progress_bar = MultiProgressBar {
+            lock: self.lock.clone(),
+            printer_verbosity: self.verbosity,
+            level: self.level.clone(),
+            indent: self.indentation(),
+            theme: self.theme.clone(),
+            transformers: self.transformers.clone(),
+            progress,
+            progress_width: preset.width(total.is_some(), total),
+            preset,
+            layout: preset::Layout::Full,
+            capabilities: self.capabilities,
+            prefix_slot,
+            is_custom_style: false,
+            is_determinate: total.is_some(),
+            max_width: self.max_width,
+            is_live_width: self.is_live_width,
+            final_message: finish_message.map(|s| s.into()),
+            is_increasing: true,
+            is_failed: false,
+            clock,
+            jobs_header: None,
+            #[cfg(any(feature = "process", feature = "download"))]
+            controls: self.controls.clone(),
+            #[cfg(feature = "process")]
+            execution_log: self.execution_log.clone(),
+            #[cfg(feature = "process")]
+            audit: self.audit.clone(),
+            log_writers: self.log_writers.clone(),
+            suppressed: self.suppressed.clone(),
+            secrets: self.secrets.clone(),
+            writer: self.writer.clone(),
+            plain: is_plain.then_some(PlainStatus {
+                last_report: None,
+                is_counted: total.is_some(),
+            }),
+            session: self
+                .recorder
+                .as_ref()
+                .map(|recorder| session::BarSession::start(recorder, prefix, total)),
+            snapshot_slot: None,
+            grid: None,
+            is_queued: false,
+            arrangement: None,
+            #[cfg(feature = "tui")]
+            tui_job: None,
+        };
+        progress_bar.update_layout();
+        progress_bar
+    }
+
+    pub fn controls(&self) -> Arc<keyboard::Controls> {
+        self.controls.clone()
+    }
+
+    fn write_heading(
+        &mut self,
+        name: &str,
+        level: Option<usize>,
+    ) -> anyhow::Result<(usize, scope::ScopeToken)> {
+        let scope = self
+            .scopes
+            .start(name, scope::ScopeKind::Heading, self.ci.as_ref());
+        self.newline().context(format_context!(""))?;
+        self.enter_heading();
+        let level = level.unwrap_or(self.heading_count()).max(1);
+        #[cfg(feature = "markdown")]
+        self.record_heading(name, level);
+        for line in self.heading_style.render(level, name, self.max_width) {
+            let heading = self
+                .theme
+                .heading_color(level)
+                .paint(line.as_str())
+                .bold()
+                .to_string();
+            self.write(heading.as_str()).context(format_context!(""))?;
+            self.write("\n").context(format_context!(""))?;
+        }
+        Ok((level, scope))
+    }
+
+    fn write_section_header(&mut self, name: &str) -> anyhow::Result<scope::ScopeToken> {
+        let scope = self
+            .scopes
+            .start(name, scope::ScopeKind::Section, self.ci.as_ref());
+        self.write(format!("{}{}:", self.indentation(), name.bold()).as_str())
+            .context(format_context!(""))?;
+        Ok(scope)
+    }
+
+    pub fn begin_heading(&mut self, name: &str) -> anyhow::Result<HeadingGuard> {
+        let (_, scope) = self
+            .write_heading(name, None)
+            .context(format_context!(""))?;
+        Ok(HeadingGuard {
+            heading_count: self.heading_count.clone(),
+            scope: Some(scope),
+        })
+    }
+
+    pub fn end_heading(&mut self, guard: HeadingGuard) {
+        drop(guard);
+    }
+
+    pub fn begin_section(&mut self, name: &str) -> anyhow::Result<SectionGuard> {
+        let scope = self
+            .write_section_header(name)
+            .context(format_context!(""))?;
+        Ok(SectionGuard {
+            indent: self.indent.clone(),
+            thread: self.indent.increase(),
+            scope: Some(scope),
+        })
+    }
+
+    pub fn end_section(&mut self, guard: SectionGuard) {
+        drop(guard);
+    }
+
+    // mutes all output, including warnings and errors, until the guard drops
+    pub fn quiet_scope(&mut self) -> QuietGuard {
+        self.level.quiet_count.fetch_add(1, Ordering::Relaxed);
+        QuietGuard {
+            quiet_count: self.level.quiet_count.clone(),
+        }
+    }
+
+    // applies to this printer and its live progress bars immediately
+    pub fn set_level(&mut self, level: Level) {
+        self.verbosity.level = level;
+        self.level.set(level);
+    }
+
+    pub fn level_handle(&self) -> LevelHandle {
+        self.level.clone()
+    }
+
+    fn active_verbosity(&self) -> Verbosity {
+        self.level.resolve(self.verbosity)
+    }
+
+    // nests subsequent output without printing a section header
+    pub fn indented(&mut self) -> IndentGuard {
+        IndentGuard {
+            indent: self.indent.clone(),
+            thread: self.indent.increase(),
+        }
+    }
+
+    fn indent(&self) -> usize {
+        self.indent.get()
+    }
+
+    fn indentation(&self) -> String {
+        self.indent_style
+            .render(self.indent(), self.theme.indent_guide())
+    }
+
+    fn heading_count(&self) -> usize {
+        self.heading_count.load(Ordering::Relaxed)
+    }
+
+    pub fn theme(&self) -> &theme::Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: theme::Theme) {
+        self.heading_style = theme.heading_style;
+        self.theme = Arc::new(theme.supported(&self.capabilities));
+    }
+
+    pub fn heading_depth(&self) -> usize {
+        self.heading_count()
+    }
+
     fn enter_heading(&mut self) {
-        self.heading_count += 1;
+        self.heading_count.fetch_add(1, Ordering::Relaxed);
     }
 
     fn exit_heading(&mut self) {
-        self.heading_count -= 1;
+        self.heading_count.fetch_sub(1, Ordering::Relaxed);
     }
 
     fn shift_right(&mut self) {
-        self.indent += 2;
+        self.indent.increase();
     }
 
     fn shift_left(&mut self) {
-        self.indent -= 2;
+        self.indent.decrease(std::thread::current().id());
     }
 
-    fn print_value(&mut self, value: &serde_json::Value) -> anyhow::Result<()> {
+    fn print_value(
+        &mut self,
+        value: &serde_json::Value,
+        hint: hints::Hint,
+        hints: &hints::Hints,
+    ) -> anyhow::Result<()> {
         match value {
             serde_json::Value::Object(map) => {
                 self.write("\n").context(format_context!(""))?;
                 self.shift_right();
-                for (key, value) in map {
-                    let is_skip =
-                        *value == serde_json::Value::Null && self.verbosity.level > Level::Message;
+                let is_scalar = |value: &serde_json::Value| {
+                    !matches!(
+                        value,
+                        serde_json::Value::Object(_) | serde_json::Value::Array(_)
+                    )
+                };
+                let mut entries: Vec<(&String, &serde_json::Value)> = map.iter().collect();
+                if self.object_order == ObjectOrder::Alphabetical {
+                    entries.sort_by_key(|(key, _)| *key);
+                }
+                let key_width = entries
+                    .iter()
+                    .filter(|(_, value)| is_scalar(value))
+                    .map(|(key, _)| format::visible_width(key))
+                    .max()
+                    .unwrap_or(0)
+                    .min(OBJECT_KEY_MAX_WIDTH);
+                let indentation = self.indentation();
+                let mut line = String::new();
+                for (key, value) in entries {
+                    let is_skip = *value == serde_json::Value::Null
+                        && self.active_verbosity().level > Level::Message;
                     if !is_skip {
-                        {
-                            self.write(
-                                format!(
-                                    "{}{}: ",
-                                    " ".repeat(self.indent),
-                                    key.if_supports_color(Stdout, |text| text.bold())
-                                )
-                                .as_str(),
-                            )
-                            .context(format_context!(""))?;
-                        }
-                        self.print_value(value).context(format_context!(""))?;
+                        let padding = if is_scalar(value) {
+                            key_width.saturating_sub(format::visible_width(key))
+                        } else {
+                            0
+                        };
+                        line.clear();
+                        let _ = write!(
+                            line,
+                            "{indentation}{}:{:padding$} ",
+                            key.if_supports_color(Stdout, |text| text.bold()),
+                            ""
+                        );
+                        self.write(line.as_str())
+                            .with_context(|| format_context!(""))?;
+                        self.print_value(value, hints.resolve(key), hints)
+                            .with_context(|| format_context!(""))?;
                     }
                 }
                 self.shift_left();
@@ -614,105 +2227,230 @@ impl Printer {
             serde_json::Value::Array(array) => {
                 self.write("\n").context(format_context!(""))?;
                 self.shift_right();
+                let indentation = self.indentation();
+                let mut line = String::new();
                 for (index, value) in array.iter().enumerate() {
-                    self.write(format!("{}[{index}]: ", " ".repeat(self.indent)).as_str())?;
-                    self.print_value(value).context(format_context!(""))?;
+                    line.clear();
+                    let _ = write!(line, "{indentation}[{index}]: ");
+                    self.write(line.as_str())?;
+                    self.print_value(value, hint, hints)
+                        .with_context(|| format_context!(""))?;
                 }
                 self.shift_left();
             }
             serde_json::Value::Null => {
-                self.write("null\n").context(format_context!(""))?;
+                self.write("null\n").with_context(|| format_context!(""))?;
             }
             serde_json::Value::Bool(value) => {
                 self.write(format!("{value}\n").as_str())
-                    .context(format_context!(""))?;
+                    .with_context(|| format_context!(""))?;
             }
             serde_json::Value::Number(value) => {
+                let value = hint.humanize(value).unwrap_or_else(|| value.to_string());
                 self.write(format!("{value}\n").as_str())
-                    .context(format_context!(""))?;
+                    .with_context(|| format_context!(""))?;
             }
             serde_json::Value::String(value) => {
-                self.write(format!("{value}\n").as_str())
-                    .context(format_context!(""))?;
+                self.print_string_value(value)
+                    .with_context(|| format_context!(""))?;
             }
         }
 
         Ok(())
     }
 
-    pub fn start_process(
-        &mut self,
-        command: &str,
-        options: &ExecuteOptions,
-    ) -> anyhow::Result<std::process::Child> {
-        let args = options.arguments.join(" ");
-        let full_command = format!("{command} {args}");
-
-        self.info("execute", &full_command)
-            .context(format_context!(""))?;
-        if let Some(directory) = &options.working_directory {
-            self.info("directory", &directory)
-                .context(format_context!(""))?;
-            if !std::path::Path::new(directory.as_ref()).exists() {
-                return Err(format_error!("Directory does not exist: {directory}"));
-            }
+    fn print_string_value(&mut self, value: &str) -> anyhow::Result<()> {
+        let value = self.shorten_paths(value);
+        let value = value.as_str();
+        let is_full = self.active_verbosity().level <= Level::Debug;
+        if value.contains('\n') {
+            let lines: Vec<&str> = value.lines().collect();
+            let shown = if is_full {
+                lines.len()
+            } else {
+                lines.len().min(OBJECT_STRING_PREVIEW_LINES)
+            };
+            self.write("|\n").context(format_context!(""))?;
+            self.shift_right();
+            let indent = self.indentation();
+            let mut block = String::new();
+            for line in lines[..shown].iter() {
+                block.push_str(&format!("{indent}{line}\n"));
+            }
+            if shown < lines.len() {
+                let marker = format!("(+ {} lines)", lines.len() - shown);
+                block.push_str(&format!(
+                    "{indent}{}\n",
+                    marker.if_supports_color(Stdout, |text| text.dimmed())
+                ));
+            }
+            self.shift_left();
+            self.write(block.as_str()).context(format_context!(""))?;
+            return Ok(());
+        }
+
+        let available = self
+            .max_width
+            .saturating_sub(format::visible_width(&self.indentation()) + OBJECT_KEY_MAX_WIDTH);
+        let length = value.chars().count();
+        if !is_full && available > 0 && length > available {
+            let preview: String = value.chars().take(available).collect();
+            let marker = format!("(+ {} chars)", length - available);
+            self.write(
+                format!(
+                    "{preview}... {}\n",
+                    marker.if_supports_color(Stdout, |text| text.dimmed())
+                )
+                .as_str(),
+            )
+            .context(format_context!(""))?;
+        } else {
+            self.write(format!("{value}\n").as_str())
+                .context(format_context!(""))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "process")]
+    pub fn start_process(
+        &mut self,
+        command: &str,
+        options: &ExecuteOptions,
+    ) -> anyhow::Result<std::process::Child> {
+        self.start_process_with(command, options, None)
+    }
+
+    #[cfg(feature = "process")]
+    fn start_process_with(
+        &mut self,
+        command: &str,
+        options: &ExecuteOptions,
+        progress: Option<&child_progress::ProgressChannel>,
+    ) -> anyhow::Result<std::process::Child> {
+        let full_command = options.get_full_command(command);
+
+        self.info("execute", &full_command)
+            .context(format_context!(""))?;
+        if let Some(directory) = &options.working_directory {
+            self.info("directory", &directory.display().to_string())
+                .context(format_context!(""))?;
+            if !directory.exists() {
+                return Err(format_error!(
+                    "Directory does not exist: {}",
+                    directory.display()
+                ));
+            }
         }
 
         let child_process = options
-            .spawn(command)
+            .spawn(command, progress)
             .context(format_context!("{command}"))?;
         Ok(child_process)
     }
 
+    #[cfg(feature = "process")]
     pub fn execute_process(
         &mut self,
         command: &str,
         options: ExecuteOptions,
     ) -> anyhow::Result<Option<String>> {
         let section = Section::new(self, command).context(format_context!(""))?;
+        if options.cache.is_some() {
+            let mut multi_progress = MultiProgress::new(section.printer);
+            let mut progress_bar = multi_progress.add_progress("progress", None, None);
+            return progress_bar
+                .execute_process(command, options)
+                .context(format_context!(""));
+        }
+        let progress = options
+            .is_child_protocol
+            .then(child_progress::ProgressChannel::new)
+            .flatten();
         let child_process = section
             .printer
-            .start_process(command, &options)
+            .start_process_with(command, &options, progress.as_ref())
             .context(format_context!("Faild to execute process: {command}"))?;
+        let updates = progress.map(child_progress::ProgressChannel::listen);
         let mut multi_progress = MultiProgress::new(section.printer);
         let mut progress_bar = multi_progress.add_progress("progress", None, None);
-        let result = monitor_process(command, child_process, &mut progress_bar, &options)
-            .context(format_context!(""))?;
-
-        Ok(result)
+        let result = monitor_process(command, child_process, &mut progress_bar, &options, updates);
+        if result.is_err() {
+            progress_bar.mark_failed();
+        }
+        result.context(format_context!(""))
     }
 }
 
-fn sanitize_output(input: &str, max_length: usize) -> String {
-    //remove all backspaces and truncate
+// leave a buffer of 8 characters
+const TERMINAL_WIDTH_BUFFER: usize = 8;
 
-    let escaped: Vec<_> = input.chars().flat_map(|c| c.escape_default()).collect();
+fn terminal_width() -> Option<usize> {
+    terminal_size::terminal_size()
+        .map(|(width, _)| (width.0 as usize).saturating_sub(TERMINAL_WIDTH_BUFFER))
+}
 
-    let mut result = String::new();
-    let mut length = 0usize;
-    for character in escaped.into_iter() {
-        if length < max_length {
-            result.push(character);
-            length += 1;
+// Splits output on `\n` and `\r` as bytes arrive so a redrawn line reaches the
+// bar without waiting for a newline. The last redraw before `\r\n` is what a
+// terminal would have left on screen, so it is sent as the completed line.
+#[cfg(feature = "process")]
+fn read_redrawn_lines<OutputType: std::io::Read>(
+    mut output: OutputType,
+    lines: &mpsc::Sender<String>,
+    redraws: &mpsc::Sender<String>,
+) {
+    let mut buffer = [0_u8; 4096];
+    let mut pending = Vec::new();
+    let mut last_redraw = Vec::new();
+    let mut is_after_return = false;
+    while let Ok(count) = output.read(&mut buffer) {
+        if count == 0 {
+            break;
+        }
+        for byte in &buffer[..count] {
+            match byte {
+                b'\n' => {
+                    let line = if pending.is_empty() && is_after_return {
+                        std::mem::take(&mut last_redraw)
+                    } else {
+                        std::mem::take(&mut pending)
+                    };
+                    let _ = lines.send(String::from_utf8_lossy(&line).into_owned());
+                    pending.clear();
+                    is_after_return = false;
+                }
+                b'\r' => {
+                    if !pending.is_empty() {
+                        let _ = redraws.send(String::from_utf8_lossy(&pending).into_owned());
+                        last_redraw = std::mem::take(&mut pending);
+                    }
+                    is_after_return = true;
+                }
+                byte => {
+                    pending.push(*byte);
+                    is_after_return = false;
+                }
+            }
         }
     }
-    while result.len() < max_length {
-        result.push(' ');
+    if !pending.is_empty() {
+        let _ = lines.send(String::from_utf8_lossy(&pending).into_owned());
     }
-
-    result
 }
 
+#[cfg(feature = "process")]
 fn format_monitor_log_message(source: &str, command: &str, message: &str) -> String {
     format!("[{source}:{command}] {message}")
 }
 
+#[cfg(feature = "process")]
 fn monitor_process(
     command: &str,
     mut child_process: std::process::Child,
     progress_bar: &mut MultiProgressBar,
     options: &ExecuteOptions,
+    mut updates: Option<child_progress::ProgressUpdates>,
 ) -> anyhow::Result<Option<String>> {
+    let _operation = progress_bar.controls.begin_operation();
     let child_stdout = child_process
         .stdout
         .take()
@@ -723,14 +2461,25 @@ fn monitor_process(
         .take()
         .ok_or(format_error!("Internal Error: Child has no stderr"))?;
 
+    let started_at = std::time::SystemTime::now();
     let log_level_stdout = options.log_level;
     let log_level_stderr = options.log_level;
 
-    let (stdout_thread, stdout_rx) = ExecuteOptions::process_child_output(child_stdout)?;
-    let (stderr_thread, stderr_rx) = ExecuteOptions::process_child_output(child_stderr)?;
+    let (redraw_tx, redraw_rx) = mpsc::channel::<String>();
+    let redraws = (options.line_discipline == LineDiscipline::CarriageReturn).then_some(redraw_tx);
+    let (stdout_thread, stdout_rx) =
+        ExecuteOptions::process_child_output(child_stdout, redraws.clone())?;
+    let (stderr_thread, stderr_rx) = ExecuteOptions::process_child_output(child_stderr, redraws)?;
+
+    // only the newest redraw matters, older ones were already overwritten
+    let handle_redraws = |progress: &mut MultiProgressBar| {
+        if let Some(message) = redraw_rx.try_iter().last() {
+            progress.set_message(message.as_str());
+        }
+    };
 
     let handle_stdout = |progress: &mut MultiProgressBar,
-                         writer: Option<&mut std::fs::File>,
+                         writer: Option<&mut log_file::LogWriter>,
                          content: Option<&mut String>|
      -> anyhow::Result<()> {
         let mut stdout = String::new();
@@ -745,6 +2494,10 @@ fn monitor_process(
                     *level,
                     format_monitor_log_message("stdout", command, message.as_str()).as_str(),
                 );
+            } else {
+                progress.log_passthrough(
+                    format_monitor_log_message("stdout", command, message.as_str()).as_str(),
+                );
             }
         }
 
@@ -759,7 +2512,7 @@ fn monitor_process(
     };
 
     let handle_stderr = |progress: &mut MultiProgressBar,
-                         writer: Option<&mut std::fs::File>,
+                         writer: Option<&mut log_file::LogWriter>,
                          content: &mut String|
      -> anyhow::Result<()> {
         let mut stderr = String::new();
@@ -770,7 +2523,11 @@ fn monitor_process(
             if let Some(level) = log_level_stderr.as_ref() {
                 progress.log(
                     *level,
-                    format_monitor_log_message("stdout", command, message.as_str()).as_str(),
+                    format_monitor_log_message("stderr", command, message.as_str()).as_str(),
+                );
+            } else {
+                progress.log_passthrough(
+                    format_monitor_log_message("stderr", command, message.as_str()).as_str(),
                 );
             }
         }
@@ -788,13 +2545,18 @@ fn monitor_process(
     let mut stdout_content = String::new();
 
     let mut output_file = if let Some(log_path) = options.log_file_path.as_ref() {
-        let mut file = std::fs::File::create(log_path.as_ref())
-            .context(format_context!("while creating {log_path}"))?;
+        let mut file = log_file::LogWriter::open(log_path.as_ref(), options.is_append_log)
+            .context(format_context!(""))?;
+        progress_bar.execution_log.lock().unwrap().add(
+            options.label.as_ref(),
+            options.get_full_command(command).as_str(),
+            log_path.as_ref(),
+        );
 
         let command = format!("command: {}\n", command);
         let working_directory = format!(
             "directory: {}\n",
-            options.working_directory.as_deref().unwrap_or("")
+            options.get_working_directory().as_deref().unwrap_or("")
         );
         let mut environment = "environment:\n".to_string();
         if !options.clear_environment {
@@ -807,9 +2569,9 @@ fn monitor_process(
         for (key, value) in options.environment.iter() {
             environment.push_str(format!("    {}: {}\n", key, value).as_str());
         }
-        let arguments = format!("arguments: {}\n\n", options.arguments.join(" "));
+        let arguments = format!("arguments: {}\n\n", options.get_arguments().join(" "));
 
-        file.write(format!("{command}{working_directory}{environment}{arguments}").as_bytes())
+        file.write_all(format!("{command}{working_directory}{environment}{arguments}").as_bytes())
             .context(format_context!("while writing {log_path}"))?;
 
         Some(file)
@@ -819,10 +2581,32 @@ fn monitor_process(
 
     loop {
         if let Ok(Some(status)) = child_process.try_wait() {
+            audit::record(
+                progress_bar.audit.as_ref(),
+                command,
+                options,
+                started_at,
+                status.code(),
+            );
             exit_status = Some(status);
             break;
         }
 
+        if progress_bar.controls.is_cancel_requested() {
+            let _ = child_process.kill();
+            let _ = child_process.wait();
+            audit::record(
+                progress_bar.audit.as_ref(),
+                command,
+                options,
+                started_at,
+                None,
+            );
+            let _ = stdout_thread.join();
+            let _ = stderr_thread.join();
+            return Err(format_error!("Cancelled: {command}"));
+        }
+
         let stdout_content = if options.is_return_stdout {
             Some(&mut stdout_content)
         } else {
@@ -833,8 +2617,15 @@ fn monitor_process(
             .context(format_context!("failed to handle stdout"))?;
         handle_stderr(progress_bar, output_file.as_mut(), &mut stderr_content)
             .context(format_context!("failed to handle stderr"))?;
+        handle_redraws(progress_bar);
+        // a child reporting its own progress owns the bar from then on
+        let is_reported = updates
+            .as_mut()
+            .is_some_and(|updates| updates.apply(progress_bar));
         std::thread::sleep(std::time::Duration::from_millis(100));
-        progress_bar.increment_with_overflow(1);
+        if !is_reported {
+            progress_bar.increment_with_overflow(1);
+        }
     }
 
     let _ = stdout_thread.join();
@@ -853,9 +2644,18 @@ fn monitor_process(
 
     handle_stderr(progress_bar, output_file.as_mut(), &mut stderr_content)
         .context(format_context!("while handling stderr"))?;
+    if let Some(updates) = updates.as_mut() {
+        updates.finish(progress_bar);
+    }
 
     if let Some(exit_status) = exit_status {
         if !exit_status.success() {
+            progress_bar.execution_log.lock().unwrap().add_failure(
+                command,
+                options,
+                exit_status.code(),
+                stderr_content.as_str(),
+            );
             if let Some(code) = exit_status.code() {
                 let exit_message = format!("Command failed with exit code: {code}");
                 return Err(format_error!("{exit_message} : {stderr_content}"));
@@ -878,6 +2678,7 @@ fn monitor_process(
 mod tests {
     use super::*;
 
+    #[cfg(feature = "process")]
     #[derive(Serialize)]
     pub struct Test {
         pub name: String,
@@ -887,6 +2688,7 @@ mod tests {
         pub children: f64,
     }
 
+    #[cfg(feature = "process")]
     #[test]
     fn printer() {
         let mut printer = Printer::new_stdout();
@@ -909,9 +2711,9 @@ mod tests {
         printer.execute_process("/bin/ls", options).unwrap();
 
         {
-            let mut heading = Heading::new(&mut printer, "First").unwrap();
+            let heading = Heading::new(&mut printer, "First").unwrap();
             {
-                let section = Section::new(&mut heading.printer, "PersonWrapper").unwrap();
+                let section = Section::new(heading.printer, "PersonWrapper").unwrap();
                 section
                     .printer
                     .object(
@@ -927,13 +2729,13 @@ mod tests {
                     .unwrap();
             }
 
-            let mut sub_heading = Heading::new(&mut heading.printer, "Second").unwrap();
+            let sub_heading = Heading::new(heading.printer, "Second").unwrap();
 
-            let mut sub_section = Section::new(&mut sub_heading.printer, "PersonWrapper").unwrap();
+            let sub_section = Section::new(sub_heading.printer, "PersonWrapper").unwrap();
             sub_section.printer.object("Hello", &"World").unwrap();
 
             {
-                let mut multi_progress = MultiProgress::new(&mut sub_section.printer);
+                let mut multi_progress = MultiProgress::new(sub_section.printer);
                 let mut first = multi_progress.add_progress("First", Some(10), None);
                 let mut second = multi_progress.add_progress("Second", Some(50), None);
                 let mut third = multi_progress.add_progress("Third", Some(100), None);
@@ -1000,7 +2802,6 @@ mod tests {
                     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                     progress.increment(1);
                 }
-                ()
             };
             handles.push(runtime.spawn(task1));
 
@@ -1023,7 +2824,6 @@ mod tests {
                     tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                     progress.increment(1);
                 }
-                ()
             };
             handles.push(runtime.spawn(task2));
 
@@ -1032,4 +2832,2297 @@ mod tests {
             }
         }
     }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn printer_handle() {
+        let handle = handle::PrinterHandle::new(Printer::new_null_term());
+        let mut tasks = Vec::new();
+        for index in 0..4 {
+            let handle = handle.clone();
+            tasks.push(std::thread::spawn(move || {
+                let _section = handle.begin_section(&format!("Task{index}")).unwrap();
+                let mut progress = handle.add_progress(&format!("Task{index}"), Some(10), None);
+                for _ in 0..10 {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                    progress.increment(1);
+                }
+                handle.info("finished", &index).unwrap();
+            }));
+        }
+
+        for task in tasks {
+            task.join().unwrap();
+        }
+        assert_eq!(handle.with(|printer| printer.indent()), 0);
+    }
+
+    #[test]
+    fn summary_junit_xml() {
+        let mut summary = summary::Summary::new("workflow");
+        summary.add_step(
+            "build",
+            std::time::Duration::from_millis(1500),
+            summary::StepStatus::Passed,
+            None,
+        );
+        summary.add_step(
+            "test <unit>",
+            std::time::Duration::from_millis(250),
+            summary::StepStatus::Failed,
+            Some("\x1b[31massertion\x1b[0m failed\x07\n\tat line 3".to_string()),
+        );
+
+        let xml = summary.to_junit_xml();
+        assert!(xml.contains("tests=\"2\" failures=\"1\" skipped=\"0\" time=\"1.750\""));
+        assert!(xml.contains("<testcase name=\"build\" classname=\"workflow\" time=\"1.500\"/>"));
+        assert!(xml.contains(
+            "<failure message=\"test &lt;unit&gt; failed\">assertion failed\n\tat line 3</failure>"
+        ));
+
+        let path = std::env::temp_dir().join(format!(
+            "printer_summary_junit_xml_{}.xml",
+            std::process::id()
+        ));
+        summary.write_junit_xml(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), xml);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(all(feature = "progress", feature = "toml"))]
+    #[test]
+    fn theme_from_toml() {
+        let theme = theme::Theme::from_toml(
+            r#"
+            heading = "magenta"
+            heading_style = "Boxed"
+            progress_chars = "=> "
+
+            [levels]
+            warning = { fixed = 208 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(theme.heading, theme::ThemeColor::Magenta);
+        assert_eq!(theme.levels.warning, theme::ThemeColor::Fixed(208));
+        assert_eq!(theme.levels.error, theme::ThemeColor::Red);
+        assert_eq!(theme.heading_style, HeadingStyle::Boxed);
+        assert!(theme::Theme::from_toml("heading = \"chartreuse\"").is_err());
+
+        let mut printer = builder::PrinterBuilder::new_null_term()
+            .theme(theme)
+            .build();
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let _progress = multi_progress.add_progress("themed", Some(10), None);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn progress_layout_degrades() {
+        let preset = preset::Preset::Classic;
+        let layout = |available| preset.layout(true, Some(100), available, 10);
+        assert_eq!(layout(200), preset::Layout::Full);
+        assert_eq!(layout(45), preset::Layout::NoMessage);
+        assert_eq!(layout(35), preset::Layout::NoPrefix);
+        assert_eq!(layout(12), preset::Layout::BarOnly(12));
+        assert_eq!(layout(2), preset::Layout::Spinner);
+        assert_eq!(format::fit_to_width("abcdef", 4), "abc…");
+        assert_eq!(format::fit_to_width("abcdef", 0), "");
+        assert_eq!(format::fit_to_width("50%\r100%", 6), "100%  ");
+        assert_eq!(format::sanitize("ab\x08c\tx\r\n"), "ac  x ");
+        assert_eq!(format::sanitize("\x1b[2K\x1b[1mdone\x07"), "\x1b[1mdone");
+        assert_eq!(format::visible_width("\x1b[1mdone\x1b[0m"), 4);
+
+        let mut printer = Printer::new_null_term();
+        printer.max_width = 3;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let mut progress = multi_progress.add_progress("tiny", Some(10), None);
+        progress.set_message("does not fit");
+        assert_eq!(progress.layout, preset::Layout::Spinner);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn prefix_alignment() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let short = multi_progress.add_progress("git", Some(10), None);
+        let long = multi_progress.add_progress("download", Some(10), None);
+        let prefix_width = |bar: &MultiProgressBar| {
+            format::visible_width(bar.progress.as_ref().unwrap().prefix().as_str())
+        };
+        assert_eq!(prefix_width(&short), "download:".len());
+        assert_eq!(prefix_width(&long), "download:".len());
+        // the column stops growing at the cap
+        let overflow = multi_progress.add_progress(&"x".repeat(40), Some(10), None);
+        assert_eq!(prefix_width(&short), prefix_column::PREFIX_MAX_WIDTH);
+        assert_eq!(prefix_width(&overflow), 41);
+        assert_eq!(short.prefix_label().trim(), "git:");
+
+        multi_progress.set_prefix_width(Some(12));
+        assert_eq!(prefix_width(&short), 12);
+        assert_eq!(prefix_width(&long), 12);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn terminal_capabilities() {
+        let environment = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let modern = capabilities::Capabilities::detect_with(
+            environment(&[
+                ("TERM", "xterm-256color"),
+                ("COLORTERM", "truecolor"),
+                ("LANG", "en_US.UTF-8"),
+                ("VTE_VERSION", "6003"),
+            ]),
+            true,
+        );
+        assert!(modern.is_truecolor && modern.is_hyperlinks && modern.is_cursor_control);
+        assert_eq!(modern.is_unicode, !cfg!(windows));
+
+        let forced = capabilities::Capabilities::detect_with(
+            environment(&[
+                ("TERM", "dumb"),
+                ("PRINTER_UNICODE", "1"),
+                ("PRINTER_TRUECOLOR", "off"),
+            ]),
+            true,
+        );
+        assert!(forced.is_unicode && !forced.is_truecolor && !forced.is_cursor_control);
+
+        let mut printer = Printer::new_null_term();
+        printer.set_capabilities(capabilities::Capabilities {
+            is_unicode: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            printer.hyperlink("docs", "https://example.com"),
+            "docs (https://example.com)"
+        );
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let mut progress = multi_progress.add_progress("fetch", Some(10), None);
+        progress.set_preset(preset::Preset::Fancy);
+        assert_eq!(progress.preset, preset::Preset::Ascii);
+    }
+
+    #[cfg(all(feature = "progress", feature = "toml"))]
+    #[test]
+    fn gradient_colors() {
+        use theme::{ColorDepth, ThemeColor};
+        let theme = theme::Theme::from_toml(
+            r#"
+            progress_gradient = [{ rgb = [255, 0, 0] }, { rgb = [0, 0, 255] }]
+
+            [levels]
+            warning = { rgb = [255, 175, 0] }
+            "#,
+        )
+        .unwrap();
+        let stops = theme.progress_gradient.as_slice();
+        assert_eq!(ThemeColor::blend(stops, 0.0), ThemeColor::Rgb(255, 0, 0));
+        assert_eq!(ThemeColor::blend(stops, 0.5), ThemeColor::Rgb(128, 0, 128));
+        assert_eq!(ThemeColor::blend(stops, 1.0), ThemeColor::Rgb(0, 0, 255));
+        assert_eq!(
+            ThemeColor::Rgb(255, 0, 0).downgrade(ColorDepth::Ansi256),
+            ThemeColor::Fixed(196)
+        );
+        assert_eq!(
+            ThemeColor::Fixed(208).downgrade(ColorDepth::Ansi16),
+            ThemeColor::Yellow
+        );
+        assert_eq!(
+            ThemeColor::Fixed(9).downgrade(ColorDepth::Ansi16),
+            ThemeColor::Red
+        );
+
+        let mut printer = Printer::new_null_term();
+        printer.set_theme(theme.clone());
+        assert_eq!(printer.theme.levels.warning, ThemeColor::Fixed(214));
+        printer.set_capabilities(capabilities::Capabilities {
+            is_truecolor: true,
+            ..Default::default()
+        });
+        printer.set_theme(theme);
+        assert_eq!(printer.theme.levels.warning, ThemeColor::Rgb(255, 175, 0));
+        // the gradient replaces the bar but keeps its width
+        assert_eq!(
+            preset::template_width("{gradient_bar} {prefix}", None),
+            preset::template_width("{bar} {prefix}", None)
+        );
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn format_macros() {
+        use std::fmt::Write;
+        let mut printer = Printer::new_null_term();
+        let count = 3;
+        pinfo!(printer, "built {count} targets").unwrap();
+        pwarn!(printer, "{} skipped", 1).unwrap();
+        perror!(printer, "failed").unwrap();
+        writeln!(printer, "raw {count}").unwrap();
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let mut progress = multi_progress.add_progress("macros", Some(10), None);
+        pinfo!(progress, "step {}", 1);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn file_log_level() {
+        let path =
+            std::env::temp_dir().join(format!("printer_file_log_level_{}.log", std::process::id()));
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.level = Level::Warning;
+        printer.log_to_file(&path, Level::Debug).unwrap();
+        printer.log(Level::Trace, "hidden everywhere").unwrap();
+        printer.log(Level::Info, "file only").unwrap();
+        {
+            let mut multi_progress = MultiProgress::new(&mut printer);
+            let mut progress = multi_progress.add_progress("file", None, None);
+            progress.log_with(Level::Debug, || "from a bar".to_string());
+        }
+        printer.log(Level::Error, "everywhere").unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines,
+            vec!["Info: file only", "Debug: from a bar", "Error: everywhere"]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl SharedBuffer {
+        // everything written so far with color codes removed
+        fn output(&self) -> String {
+            let output = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            format::strip_ansi_codes(output.as_str()).to_string()
+        }
+    }
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buffer);
+            Ok(buffer.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn error_context_lines() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.set_error_context_lines(2);
+        for index in 0..3 {
+            printer
+                .log(Level::Debug, format!("step {index}").as_str())
+                .unwrap();
+        }
+        printer.log(Level::Info, "visible").unwrap();
+        printer.log(Level::Error, "failed").unwrap();
+        printer.log(Level::Error, "failed again").unwrap();
+
+        let output = buffer.output();
+        let lines: Vec<&str> = output.lines().map(str::trim_end).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Info: visible",
+                "last 2 suppressed lines:",
+                "Debug: step 1",
+                "Debug: step 2",
+                "Error: failed",
+                "Error: failed again",
+            ]
+        );
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn spinner_guard() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.verbosity.is_show_progress_bars = true;
+        {
+            let spinner = printer.spinner("Parsing workspace");
+            spinner.set_message("still parsing");
+        }
+        printer.log(Level::Info, "parsed").unwrap();
+        let output = buffer.output();
+        let lines: Vec<&str> = output.lines().map(str::trim_end).collect();
+        assert_eq!(lines, vec!["Info: Parsing workspace", "Info: parsed"]);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn checklist_steps() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        {
+            use checklist::StepState;
+            let mut checklist =
+                checklist::Checklist::new(&mut printer, &["checkout", "build", "test"]);
+            checklist.set_state(0, StepState::Running).unwrap();
+            checklist.set_state(0, StepState::Done).unwrap();
+            checklist.set_state(1, StepState::Failed).unwrap();
+            checklist.set_state(2, StepState::Skipped).unwrap();
+            assert!(checklist.set_state(3, StepState::Done).is_err());
+            assert_eq!(checklist.state(1), Some(StepState::Failed));
+        }
+        let output = buffer.output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines, vec!["✓ checkout", "✗ build", "- test"]);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn gauge_display() {
+        let gauge = gauge::Gauge::new("mem");
+        gauge.set(0.62);
+        assert_eq!(gauge.to_string(), "mem ▓▓▓░░ 62%");
+        gauge.set_with_text(2.0, "3.2 MB/s");
+        assert_eq!(gauge.to_string(), "mem ▓▓▓▓▓ 3.2 MB/s");
+
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        multi_progress.add_gauge(&gauge);
+        assert!(multi_progress.jobs_header.is_some());
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn countdown_cancel() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let wait = std::time::Duration::from_millis(150);
+        assert!(printer.countdown("Retrying in", wait).unwrap());
+        printer.controls().request_cancel();
+        let start = std::time::Instant::now();
+        assert!(!printer
+            .countdown("Retrying in", std::time::Duration::from_secs(30))
+            .unwrap());
+        assert!(start.elapsed() < wait);
+    }
+
+    #[test]
+    fn fuzzy_select_matches() {
+        let candidates = ["build", "test", "format-check", "bulid-docs"];
+        assert_eq!(prompt::fuzzy_filter("bld", &candidates), vec![0, 3]);
+        assert_eq!(prompt::fuzzy_filter("tesr", &candidates), vec![1]);
+        assert_eq!(suggest::suggest("buld", &candidates), vec!["build"]);
+        assert_eq!(suggest::edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn secret_redaction() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.add_secret("hunter2");
+        printer.log(Level::Info, "token is hunter2").unwrap();
+        printer.info("token", &"hunter2").unwrap();
+        let output = buffer.output();
+        assert!(!output.contains("hunter2"));
+        assert!(output.contains("token is ********"));
+    }
+
+    #[test]
+    fn multi_select_preselected() {
+        let mut printer = Printer::new_null_term();
+        let no_items: [&str; 0] = [];
+        assert!(printer
+            .multi_select("Members", &no_items, &[])
+            .unwrap()
+            .is_empty());
+        assert!(printer.multi_select("Members", &["a", "b"], &[2]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit_text_with_editor() {
+        // `true` leaves the file as it was written
+        let mut printer = Printer::new_null_term();
+        let edited = printer
+            .edit_text_with("true", "name = \"printer\"\n", ".toml")
+            .unwrap();
+        assert_eq!(edited, "name = \"printer\"\n");
+        assert!(printer.edit_text_with("false", "", "txt").is_err());
+    }
+
+    #[test]
+    fn section_hooks() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut printer = Printer::new_null_term();
+        let started = events.clone();
+        printer.on_section_start(move |info| {
+            started
+                .lock()
+                .unwrap()
+                .push(format!("start {} {}", info.name, info.depth));
+        });
+        let ended = events.clone();
+        printer.on_section_end(move |info, _, outcome| {
+            ended
+                .lock()
+                .unwrap()
+                .push(format!("end {} {outcome:?}", info.name));
+        });
+        {
+            let heading = Heading::new(&mut printer, "Build").unwrap();
+            {
+                let section = Section::new(heading.printer, "compile").unwrap();
+                section
+                    .printer
+                    .log(Level::Warning, "unused import")
+                    .unwrap();
+            }
+            let guard = heading.printer.begin_section("link").unwrap();
+            heading.printer.end_section(guard);
+        }
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                "start Build 0",
+                "start compile 1",
+                "end compile Failed",
+                "start link 1",
+                "end link Success",
+                "end Build Failed",
+            ]
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive_printable() {
+        #[derive(Printable)]
+        struct Disk {
+            mount: String,
+        }
+
+        #[derive(Printable)]
+        struct Machine {
+            #[printable(rename = "host")]
+            name: String,
+            #[printable(skip)]
+            _token: String,
+            #[printable(nested)]
+            disk: Disk,
+        }
+
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let machine = Machine {
+            name: "builder".to_string(),
+            _token: "secret".to_string(),
+            disk: Disk {
+                mount: "/".to_string(),
+            },
+        };
+        printer.print(&machine).unwrap();
+        let output = buffer.output();
+        assert!(output.contains("host: builder"));
+        assert!(output.contains("disk:\n  mount: /"));
+        assert!(!output.contains("secret"));
+    }
+
+    #[test]
+    fn object_hints() {
+        #[derive(Serialize)]
+        struct Artifact {
+            size_bytes: u64,
+            build_ms: u64,
+            files: u64,
+            version: u64,
+        }
+
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let artifact = Artifact {
+            size_bytes: 1536,
+            build_ms: 2500,
+            files: 12_000,
+            version: 3,
+        };
+        let hints = hints::Hints::new().with("files", hints::Hint::Count);
+        printer
+            .object_with_hints("artifact", &artifact, &hints)
+            .unwrap();
+        let output = buffer.output();
+        assert!(output.contains("1.5 KiB"));
+        assert!(output.contains("2.5s"));
+        assert!(output.contains("12.0k"));
+        assert!(output.contains(" 3\n"));
+
+        let value = serde_json::to_value(&artifact).unwrap();
+        assert_eq!(value["size_bytes"], 1536);
+    }
+
+    #[test]
+    fn object_order() {
+        #[derive(Serialize)]
+        struct Config {
+            zone: String,
+            address: String,
+        }
+
+        let config = Config {
+            zone: "eu".to_string(),
+            address: "localhost".to_string(),
+        };
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.object("config", &config).unwrap();
+        printer.object_order = ObjectOrder::Alphabetical;
+        printer.object("config", &config).unwrap();
+        let output = buffer.output();
+        let (declared, sorted) = output.split_at(output.rfind("config:").unwrap());
+        assert!(declared.find("zone").unwrap() < declared.find("address").unwrap());
+        assert!(sorted.find("address").unwrap() < sorted.find("zone").unwrap());
+    }
+
+    #[test]
+    fn stream_object() {
+        #[derive(Serialize)]
+        enum Source {
+            Registry,
+            Git { url: String },
+        }
+
+        #[derive(Serialize)]
+        struct Package {
+            name: String,
+            checksum: Option<String>,
+            size_bytes: u64,
+            source: Source,
+        }
+
+        #[derive(Serialize)]
+        struct Lockfile {
+            version: u32,
+            packages: Vec<Package>,
+        }
+
+        let lockfile = Lockfile {
+            version: 4,
+            packages: vec![
+                Package {
+                    name: "serde".to_string(),
+                    checksum: None,
+                    size_bytes: 2048,
+                    source: Source::Registry,
+                },
+                Package {
+                    name: "printer".to_string(),
+                    checksum: Some("abc".to_string()),
+                    size_bytes: 10,
+                    source: Source::Git {
+                        url: "https://example.com/printer".to_string(),
+                    },
+                },
+            ],
+        };
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer
+            .stream_object("lockfile", &lockfile, &hints::Hints::default())
+            .unwrap();
+        let output = buffer.output();
+        assert!(output.starts_with("lockfile: \n  version: 4\n  packages: \n    [0]: \n"));
+        assert!(output.contains("      name: serde\n      size_bytes: "));
+        assert!(output.contains("      checksum: abc\n"));
+        assert!(output.contains("size_bytes: 2.0 KiB"));
+        assert!(output.contains("source: Registry\n"));
+        assert!(output.contains(
+            "      source: \n        Git: \n          url: https://example.com/printer\n"
+        ));
+    }
+
+    #[test]
+    fn log_padding_width() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.log(Level::Info, "日本語").unwrap();
+        printer.log(Level::Warning, "plain").unwrap();
+        let output = buffer.output();
+        for line in output.lines() {
+            assert_eq!(format::visible_width(line), 80);
+        }
+    }
+
+    #[test]
+    fn text_renderers() {
+        let mut table = text::Table::new(&["name", "size"]);
+        table.add_row(&["printer", "12 KiB"]);
+        table.add_row(&["a|b"]);
+        let mut plain = String::new();
+        table.write_plain(&mut plain, "").unwrap();
+        assert_eq!(
+            plain,
+            "name     size\n-------  ------\nprinter  12 KiB\na|b\n"
+        );
+        let mut markdown = String::new();
+        table.write_markdown(&mut markdown).unwrap();
+        assert!(markdown.contains("| name | size |\n| --- | --- |\n"));
+        assert!(markdown.contains("| a\\|b |  |\n"));
+
+        let tree = text::TreeNode::new("workspace")
+            .child(text::TreeNode::new("printer").child(text::TreeNode::new("derive")))
+            .child(text::TreeNode::new("docs"));
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.tree(&tree).unwrap();
+        let output = buffer.output();
+        assert_eq!(output, "workspace\n├── printer\n│   └── derive\n└── docs\n");
+    }
+
+    #[test]
+    fn capture_output() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let (result, captured) = printer.capture(|printer| {
+            printer.log(Level::Info, "inside").unwrap();
+            let (_, inner) = printer.capture_and_print(|printer| {
+                printer.log(Level::Warning, "nested").unwrap();
+            });
+            assert!(inner.contains("Warning: nested"));
+            42
+        });
+        printer.log(Level::Info, "outside").unwrap();
+
+        assert_eq!(result, 42);
+        let captured = format::strip_ansi_codes(captured.as_str()).into_owned();
+        assert!(captured.contains("Info: inside"));
+        assert!(captured.contains("Warning: nested"));
+        let output = buffer.output();
+        assert!(!output.contains("inside"));
+        assert!(!output.contains("nested"));
+        assert!(output.contains("outside"));
+    }
+
+    #[test]
+    fn report_formatters() {
+        let mut summary = summary::Summary::new("nightly");
+        let second = std::time::Duration::from_secs(1);
+        summary.add_step("build", second, summary::StepStatus::Passed, None);
+        summary.add_step(
+            "test|unit",
+            second,
+            summary::StepStatus::Failed,
+            Some("```\nassertion <failed>".to_string()),
+        );
+
+        let comment = report::github_comment(&summary);
+        assert!(comment.starts_with("### ❌ nightly: 1 failed, 1 passed in 2.0s"));
+        assert!(comment.contains("| test\\|unit | ❌ Failed | 1.0s |"));
+        assert!(comment.contains("<details><summary>test|unit output</summary>"));
+        assert!(comment.contains("````text\n```\nassertion <failed>\n````"));
+
+        let message = report::slack_message(&summary);
+        assert!(message.starts_with(":x: *nightly*: 1 failed, 1 passed in 2.0s\n"));
+        assert!(message.contains("assertion &lt;failed&gt;"));
+
+        assert_eq!(
+            report::markdown_to_slack(
+                "# Title\n- **bold** and *it* ~~old~~ [docs](https://x.io)\n"
+            ),
+            "*Title*\n• *bold* and _it_ ~old~ <https://x.io|docs>\n"
+        );
+
+        let (plain, html) = report::email(&summary);
+        assert!(plain.starts_with("nightly: FAILED\n1 failed, 1 passed in 2.0s\n"));
+        assert!(plain.contains("  FAILED   test|unit  1.0s\n"));
+        assert!(html.contains("assertion &lt;failed&gt;"));
+        let multipart = report::email_multipart(&summary);
+        assert!(multipart.contains("boundary=\"printer-report\""));
+        assert!(multipart.ends_with("--printer-report--\r\n"));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn progress_grid() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        multi_progress.enable_grid(2);
+        let mut bars: Vec<_> = (0..2)
+            .map(|index| {
+                multi_progress.add_progress(format!("fetch{index}").as_str(), Some(4), None)
+            })
+            .collect();
+        let is_active = |multi_progress: &MultiProgress| {
+            multi_progress
+                .grid
+                .as_ref()
+                .is_some_and(|grid| grid.is_active())
+        };
+        assert!(!is_active(&multi_progress));
+        bars.push(multi_progress.add_progress("fetch2", None, None));
+        assert!(is_active(&multi_progress));
+        bars[0].increment(2);
+        bars[1].log(Level::Info, "printed above the grid");
+        assert_eq!(multi_progress.snapshot()[0].position, 2);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn progress_groups() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        multi_progress.set_order(arrange::BarOrder::State);
+        let builds = multi_progress.add_group("builds");
+        let mut finished = multi_progress.add_progress_to_group(builds, "first", Some(2), None);
+        let mut queued = multi_progress.add_queued_progress("second", Some(2), None);
+        finished.finish_with(FinishStyle::FinishAtTotal);
+        let status = |bar: &MultiProgressBar| bar.snapshot().unwrap().status;
+        assert_eq!(status(&finished), snapshot::BarStatus::Finished);
+        assert_eq!(status(&queued), snapshot::BarStatus::Queued);
+        queued.increment(1);
+        assert_eq!(status(&queued), snapshot::BarStatus::Running);
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn audit_log() {
+        let path =
+            std::env::temp_dir().join(format!("printer_audit_log_{}.jsonl", std::process::id()));
+        let mut printer = Printer::new_null_term();
+        printer.enable_audit_log(&path).unwrap();
+        printer
+            .execute_process("/bin/ls", ExecuteOptions::default())
+            .unwrap();
+        let options = ExecuteOptions::builder().arg("-c").arg("exit 3").build();
+        assert!(printer.execute_process("/bin/sh", options).is_err());
+
+        let records = audit::read_audit_log(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].exit_code, Some(0));
+        assert_eq!(records[1].command.as_ref(), "/bin/sh");
+        assert_eq!(records[1].arguments, vec!["-c".into(), "exit 3".into()]);
+        assert_eq!(records[1].exit_code, Some(3));
+        assert_eq!(records[0].environment_hash, records[1].environment_hash);
+        assert!(records[1].started_at <= records[1].ended_at);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(all(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn compressed_logs() {
+        let mut printer = Printer::new_null_term();
+        let matches = |printer: &Printer, extension: &str| {
+            printer
+                .execution_log()
+                .grep("Cargo.toml", 0)
+                .unwrap()
+                .iter()
+                .filter(|grep_match| grep_match.log_file_path.ends_with(extension))
+                .count()
+        };
+        let log_path = |extension: &str| {
+            std::env::temp_dir().join(format!(
+                "printer_compressed_logs_{}.log.{extension}",
+                std::process::id()
+            ))
+        };
+        for extension in ["gz", "zst"] {
+            let path = log_path(extension);
+            let options = ExecuteOptions::builder().log_to(&path).build();
+            printer.execute_process("/bin/ls", options).unwrap();
+            let file = std::fs::read(&path).unwrap();
+            assert!(!String::from_utf8_lossy(&file).contains("Cargo.toml"));
+        }
+        assert!(matches(&printer, ".gz") > 0);
+        assert!(matches(&printer, ".zst") > 0);
+        for extension in ["gz", "zst"] {
+            std::fs::remove_file(log_path(extension)).unwrap();
+        }
+    }
+
+    #[test]
+    fn log_manager() {
+        let directory =
+            std::env::temp_dir().join(format!("printer_log_manager_{}", std::process::id()));
+        for name in ["20200101-000000", "20200102-000000", "notes"] {
+            std::fs::create_dir_all(directory.join(name)).unwrap();
+        }
+        let manager = logs::LogManager::new(&directory).unwrap();
+        let first = manager.allocate("build");
+        let second = manager.allocate("build");
+        assert!(first.ends_with("build.log"));
+        assert!(second.ends_with("build-2.log"));
+        assert!(manager.allocate("a/b c").ends_with("a_b_c.log"));
+
+        let removed = manager
+            .prune(logs::PruneOptions {
+                max_runs: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(!directory.join("20200101-000000").exists());
+        assert!(directory.join("20200102-000000").exists());
+        assert!(directory.join("notes").exists());
+        assert!(manager.run_directory().exists());
+        assert_eq!(
+            std::fs::canonicalize(manager.latest()).unwrap(),
+            std::fs::canonicalize(manager.run_directory()).unwrap()
+        );
+        let next = logs::LogManager::new(&directory).unwrap();
+        assert_eq!(
+            std::fs::canonicalize(next.latest()).unwrap(),
+            std::fs::canonicalize(next.run_directory()).unwrap()
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn session_record_and_replay() {
+        let path = std::env::temp_dir().join(format!(
+            "printer_session_record_and_replay_{}.jsonl",
+            std::process::id()
+        ));
+        {
+            let mut printer = Printer::new_null_term();
+            printer.verbosity.is_show_progress_bars = true;
+            printer.record_session(&path).unwrap();
+            printer.info("before", &"bars").unwrap();
+            let mut multi_progress = MultiProgress::new(&mut printer);
+            let mut progress = multi_progress.add_progress("recorded", Some(4), None);
+            progress.set_message("working");
+            progress.increment(2);
+        }
+        let events = session::read_session(&path).unwrap();
+        let kinds: Vec<_> = events.iter().map(|recorded| &recorded.event).collect();
+        assert!(matches!(kinds[0], session::SessionEvent::Output { .. }));
+        assert!(kinds.contains(&&session::SessionEvent::BarProgress {
+            id: 0,
+            position: 2,
+            total: Some(4),
+        }));
+        assert_eq!(
+            kinds.last(),
+            Some(&&session::SessionEvent::BarFinished {
+                id: 0,
+                is_failed: false
+            })
+        );
+        session::replay(&path, 0.0).unwrap();
+        let mut cast = Vec::new();
+        cast::write_cast(&events, cast::CastOptions::default(), &mut cast).unwrap();
+        let cast = String::from_utf8(cast).unwrap();
+        assert!(cast.starts_with("{\"version\":2,"));
+        assert!(cast.contains("[##########----------] 2/4 working"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn progress_snapshot() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        {
+            let mut done = multi_progress.add_progress("done", Some(3), None);
+            done.increment(3);
+        }
+        let mut running = multi_progress.add_progress("running", None, None);
+        running.set_message("waiting");
+        running.pause();
+
+        let states = multi_progress.snapshot();
+        assert_eq!(states.len(), 2);
+        assert_eq!(states[0].status, snapshot::BarStatus::Finished);
+        assert_eq!(states[0].total, Some(3));
+        assert_eq!(states[1].status, snapshot::BarStatus::Paused);
+        assert_eq!(states[1].total, None);
+        assert_eq!(states[1].prefix, "running:");
+        assert_eq!(states[1].message, "waiting");
+    }
+
+    #[cfg(feature = "dashboard")]
+    #[test]
+    fn dashboard_state() {
+        use std::io::{Read, Write};
+        let mut printer = Printer::new_null_term();
+        let dashboard = printer.serve_dashboard("127.0.0.1:0").unwrap();
+        printer.info("shown", &"in the browser").unwrap();
+
+        let mut stream = std::net::TcpStream::connect(dashboard.address()).unwrap();
+        stream.write_all(b"GET /state HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("in the browser"));
+
+        // dropping the dashboard ends open event streams and the listener
+        let address = dashboard.address();
+        let mut events = std::net::TcpStream::connect(address).unwrap();
+        events.write_all(b"GET /events HTTP/1.1\r\n\r\n").unwrap();
+        let mut first = [0; 15];
+        events.read_exact(&mut first).unwrap();
+        assert_eq!(&first, b"HTTP/1.1 200 OK");
+        drop(dashboard);
+        let mut rest = Vec::new();
+        let _ = events.read_to_end(&mut rest);
+        assert!(std::net::TcpStream::connect(address).is_err());
+    }
+
+    #[cfg(all(feature = "metrics", feature = "progress"))]
+    #[test]
+    fn metrics_from_progress() {
+        let mut printer = Printer::new_null_term();
+        let metrics = printer.enable_metrics();
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let _running = multi_progress.add_progress("running", None, None);
+        {
+            let mut failed = multi_progress.add_progress("failed", None, None);
+            failed.finish_with(FinishStyle::AbandonWithMessage("broken".into()));
+        }
+        let text = metrics.render();
+        assert!(text.contains("printer_jobs_started_total 2\n"));
+        assert!(text.contains("printer_jobs_failed_total 1\n"));
+        assert!(text.contains("printer_jobs_active 1\n"));
+        assert!(text.contains("printer_job_duration_seconds_count 1\n"));
+
+        use std::io::{BufRead, Read, Write};
+        let server = metrics.serve("127.0.0.1:0").unwrap();
+        let address = server.address();
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.contains("printer_jobs_started_total 2\n"));
+        drop(server);
+        assert!(std::net::TcpStream::connect(address).is_err());
+
+        let gateway = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let gateway_url = format!("http://{}", gateway.local_addr().unwrap());
+        let request = std::thread::spawn(move || {
+            let (mut stream, _) = gateway.accept().unwrap();
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            // reads the rest so closing doesn't reset the connection
+            let mut length = 0;
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 0 && !header.trim().is_empty() {
+                if let Some(value) = header.strip_prefix("Content-Length: ") {
+                    length = value.trim().parse().unwrap();
+                }
+                header.clear();
+            }
+            reader.read_exact(&mut vec![0; length]).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+            request_line
+        });
+        metrics
+            .push(gateway_url.as_str(), "nightly build/1")
+            .unwrap();
+        let request = request.join().unwrap();
+        assert!(request.starts_with("PUT /metrics/job/nightly%20build%2F1 HTTP/1.1"));
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn carriage_return_redraws() {
+        let (lines_tx, lines_rx) = mpsc::channel();
+        let (redraws_tx, redraws_rx) = mpsc::channel();
+        let output: &[u8] = b"10%\r50%\r100%\r\ndone\npartial";
+        read_redrawn_lines(output, &lines_tx, &redraws_tx);
+        let lines: Vec<String> = lines_rx.try_iter().collect();
+        let redraws: Vec<String> = redraws_rx.try_iter().collect();
+        assert_eq!(lines, ["100%", "done", "partial"]);
+        assert_eq!(redraws, ["10%", "50%", "100%"]);
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn process_pool_dependencies() {
+        use pool::{JobState, ProcessPool};
+        let mut pool = ProcessPool::new(2);
+        let fetch = pool.add("fetch", "true", ExecuteOptions::default());
+        let build =
+            pool.add_with_dependencies("build", "false", ExecuteOptions::default(), &[fetch]);
+        let test = pool.add_with_dependencies("test", "true", ExecuteOptions::default(), &[build]);
+        pool.add_with_dependencies("package", "true", ExecuteOptions::default(), &[test]);
+        pool.add("lint", "true", ExecuteOptions::default());
+
+        let mut printer = Printer::new_null_term();
+        let report = printer.execute_pool("pool", &pool).unwrap();
+        let states: Vec<JobState> = report.jobs.iter().map(|job| job.state).collect();
+        assert_eq!(
+            states,
+            [
+                JobState::Done,
+                JobState::Failed,
+                JobState::Skipped,
+                JobState::Skipped,
+                JobState::Done
+            ]
+        );
+        assert!(!report.is_success());
+        assert_eq!(report.failures().count(), 1);
+        // build only started after fetch finished
+        let fetch = &report.jobs[0];
+        let build = &report.jobs[1];
+        assert!(build.started.unwrap() >= fetch.started.unwrap() + fetch.duration.unwrap());
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn pool_critical_path() {
+        use pool::ProcessPool;
+        let mut pool = ProcessPool::new(4);
+        let fetch = pool.add("fetch", "true", ExecuteOptions::default());
+        let build = ExecuteOptions {
+            arguments: vec!["0.3".into()],
+            ..Default::default()
+        };
+        pool.add_with_dependencies("build", "sleep", build, &[fetch]);
+        pool.add("lint", "true", ExecuteOptions::default());
+
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let report = printer.execute_pool("pool", &pool).unwrap();
+        assert_eq!(report.critical_path(), [0, 1]);
+        let output = buffer.output();
+        assert!(output.contains("critical path"));
+        assert!(output.contains("build"));
+        assert!(!output.contains("lint  "));
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn process_cache() {
+        let directory = std::env::temp_dir().join(format!("printer_cache_{}", std::process::id()));
+        let input = directory.join("input.txt");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(&input, "one").unwrap();
+        let cache = Arc::new(cache::ProcessCache::new(directory.join("cache")));
+        let options = builder::ExecuteOptionsBuilder::default()
+            .arg("cached")
+            .capture_stdout()
+            .cache(cache.clone())
+            .cache_input(&input)
+            .build();
+
+        let mut printer = Printer::new_null_term();
+        let mut run = || printer.execute_process("echo", options.clone()).unwrap();
+        assert_eq!(run().as_deref(), Some("cached\n"));
+        assert_eq!(run().as_deref(), Some("cached\n"));
+        assert_eq!(cache.stats().hits, 1);
+        std::fs::write(&input, "two").unwrap();
+        run();
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(cache.stats().stores, 2);
+
+        cache.clear().unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn watch_reruns() {
+        let directory = std::env::temp_dir().join(format!("printer_watch_{}", std::process::id()));
+        let source = directory.join("source.txt");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(&source, "one").unwrap();
+        let watch = watch::Watch::new()
+            .path(format!("{}/*.txt", directory.display()).as_str())
+            .debounce(std::time::Duration::from_millis(50))
+            .poll_interval(std::time::Duration::from_millis(10))
+            .max_runs(2);
+        let writer = {
+            let source = source.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                std::fs::write(&source, "two, longer").unwrap();
+            })
+        };
+
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let options = builder::ExecuteOptionsBuilder::default()
+            .arg("built")
+            .build();
+        assert_eq!(printer.watch(&watch, "echo", options).unwrap(), 2);
+        writer.join().unwrap();
+
+        let output = buffer.output();
+        assert!(output.contains("run 1: echo built"));
+        assert!(output.contains("run 2: echo built"));
+        assert!(output.contains("source.txt"));
+        assert!(output.contains("built"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn watch_diff_output() {
+        let directory =
+            std::env::temp_dir().join(format!("printer_watch_diff_{}", std::process::id()));
+        let source = directory.join("source.txt");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(&source, "header\nold value\nfooter\n").unwrap();
+        let watch = watch::Watch::new()
+            .path(source.display().to_string().as_str())
+            .debounce(std::time::Duration::from_millis(50))
+            .poll_interval(std::time::Duration::from_millis(10))
+            .max_runs(2)
+            .diff_output();
+        let writer = {
+            let source = source.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(200));
+                std::fs::write(&source, "header\nnew value\nfooter\n").unwrap();
+            })
+        };
+
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let options = builder::ExecuteOptionsBuilder::default()
+            .arg(&source)
+            .build();
+        printer.watch(&watch, "cat", options).unwrap();
+        writer.join().unwrap();
+
+        let output = buffer.output();
+        assert_eq!(output.matches("header").count(), 1);
+        assert!(output.contains("- old value"));
+        assert!(output.contains("+ new value"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn global_output_lock() {
+        use output_lock::GlobalOutputLock;
+        let path = std::env::temp_dir().join(format!("printer_lock_{}.lock", std::process::id()));
+        let outer = GlobalOutputLock::try_acquire(path.as_path()).unwrap();
+        assert!(outer.is_some());
+        assert!(GlobalOutputLock::try_acquire(path.as_path())
+            .unwrap()
+            .is_none());
+        drop(outer);
+        assert!(GlobalOutputLock::try_acquire(path.as_path())
+            .unwrap()
+            .is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn nested_depth() {
+        assert_eq!(nested::depth_with(None), 0);
+        assert_eq!(nested::depth_with(Some("2".to_string())), 2);
+        assert_eq!(nested::depth_with(Some("yes".to_string())), 0);
+
+        let options = || {
+            builder::ExecuteOptionsBuilder::default()
+                .arg("-c")
+                .arg(format!("echo ${}", nested::NESTED_ENV_VAR))
+                .capture_stdout()
+        };
+        let mut printer = Printer::new_null_term();
+        let stdout = printer
+            .execute_process("sh", options().child_protocol().build())
+            .unwrap();
+        assert_eq!(stdout, Some(format!("{}\n", nested::depth() + 1)));
+        // only children that opt in are told
+        let inherited = std::env::var(nested::NESTED_ENV_VAR).unwrap_or_default();
+        let stdout = printer.execute_process("sh", options().build()).unwrap();
+        assert_eq!(stdout, Some(format!("{inherited}\n")));
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn child_progress_protocol() {
+        use child_progress::ProgressUpdate;
+        let update = ProgressUpdate::parse("@printer progress 42/100 msg=compiling core").unwrap();
+        assert_eq!(update.position, Some(42));
+        assert_eq!(update.total, Some(100));
+        assert_eq!(update.message.as_deref(), Some("compiling core"));
+        assert_eq!(
+            ProgressUpdate::parse(update.to_string().as_str()),
+            Some(update)
+        );
+        assert_eq!(
+            ProgressUpdate::parse("@printer progress msg=linking"),
+            Some(ProgressUpdate {
+                message: Some("linking".to_string()),
+                ..Default::default()
+            })
+        );
+        assert!(ProgressUpdate::parse("@printer progress x/10").is_none());
+        assert!(ProgressUpdate::parse("@printer progressive 1").is_none());
+
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let mut progress_bar = multi_progress.add_progress("child", None, None);
+        let options = builder::ExecuteOptionsBuilder::default()
+            .arg("-c")
+            .arg(format!(
+                "echo '@printer progress 3/10 msg=half' >&${}",
+                child_progress::PROGRESS_FD_ENV_VAR
+            ))
+            .child_protocol()
+            .build();
+        progress_bar.execute_process("sh", options).unwrap();
+        assert_eq!(progress_bar.total(), Some(10));
+        assert_eq!(progress_bar.position(), Some(3));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn scopes_end_out_of_order() {
+        let mut printer = Printer::new_null_term();
+        let ended = Arc::new(Mutex::new(Vec::new()));
+        let events = ended.clone();
+        printer.on_section_end(move |info, _, _| {
+            events.lock().unwrap().push(info.name.clone());
+        });
+        let handle = handle::PrinterHandle::new(printer);
+        let first = handle.begin_section("first").unwrap();
+        let second = handle.begin_section("second").unwrap();
+        drop(first);
+        assert_eq!(*ended.lock().unwrap(), vec!["first"]);
+        drop(second);
+        assert_eq!(*ended.lock().unwrap(), vec!["first", "second"]);
+
+        // sections on other threads don't add to this thread's indent
+        let _outer = handle.begin_section("outer").unwrap();
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    let _guard = handle.begin_section("worker").unwrap();
+                    handle.with(|printer| printer.indent())
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 1);
+        }
+        assert_eq!(handle.with(|printer| printer.indent()), 1);
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn script_counts_lines() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let lines = [
+            script::ScriptLine::new("sleep", &["0.3"]),
+            script::ScriptLine::new("false", &[]).allow_failure(),
+            script::ScriptLine::new("true", &[]),
+        ];
+        let options = builder::ExecuteOptionsBuilder::default()
+            .label("script")
+            .build();
+        let outputs = multi_progress.execute_script(&lines, options).unwrap();
+        assert_eq!(outputs.len(), 3);
+        let states = multi_progress.snapshot();
+        assert_eq!(states[0].prefix, "script:");
+        assert_eq!(states[0].position, 3);
+        assert_eq!(states[0].total, Some(3));
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn process_pool_cancel() {
+        use pool::{JobState, ProcessPool};
+        let mut pool = ProcessPool::new(2);
+        let first = pool.add("first", "true", ExecuteOptions::default());
+        pool.add_with_dependencies("second", "true", ExecuteOptions::default(), &[first]);
+
+        let mut printer = Printer::new_null_term();
+        printer.controls().request_cancel();
+        let report = printer.execute_pool("pool", &pool).unwrap();
+        let states: Vec<JobState> = report.jobs.iter().map(|job| job.state).collect();
+        assert_eq!(states, [JobState::Cancelled, JobState::Cancelled]);
+        assert!(report.jobs.iter().all(|job| job.started.is_none()));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn cancel_resets_after_operation() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let controls = printer.controls();
+        controls.request_cancel();
+        assert!(!printer
+            .countdown("Retrying in", std::time::Duration::from_secs(30))
+            .unwrap());
+        assert!(!controls.is_cancel_requested());
+        assert!(printer
+            .countdown("Retrying in", std::time::Duration::from_millis(50))
+            .unwrap());
+
+        // only the outermost operation clears the request
+        let outer = controls.begin_operation();
+        controls.request_cancel();
+        drop(controls.begin_operation());
+        assert!(controls.is_cancel_requested());
+        drop(outer);
+        assert!(!controls.is_cancel_requested());
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn output_lock_on_first_bar() {
+        use output_lock::GlobalOutputLock;
+        let path = std::env::temp_dir().join(format!("printer_screen_{}.lock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let interactive_printer = || {
+            let mut printer = Printer::new_null_term();
+            printer.render_mode = RenderMode::Interactive;
+            printer.output_lock_path = Some(path.clone());
+            printer
+        };
+
+        let mut quiet = interactive_printer();
+        MultiProgress::new(&mut quiet).add_progress("quiet", None, None);
+        assert!(quiet.output_lock().is_none());
+        assert!(!path.exists());
+
+        let mut first = interactive_printer();
+        first.verbosity.is_show_progress_bars = true;
+        let mut second = interactive_printer();
+        second.verbosity.is_show_progress_bars = true;
+        MultiProgress::new(&mut first).add_progress("first", None, None);
+        MultiProgress::new(&mut second).add_progress("second", None, None);
+        // the second printer of the process reuses the handle of the first
+        assert!(first.output_lock().is_some());
+        assert!(second.output_lock().is_some());
+        assert_eq!(second.render_mode, RenderMode::Interactive);
+        assert!(GlobalOutputLock::try_acquire(path.as_path())
+            .unwrap()
+            .is_none());
+
+        drop(first);
+        drop(second);
+        assert!(GlobalOutputLock::try_acquire(path.as_path())
+            .unwrap()
+            .is_some());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn process_cache_replay() {
+        let directory =
+            std::env::temp_dir().join(format!("printer_cache_replay_{}", std::process::id()));
+        let log_path = directory.join("echo.log");
+        std::fs::create_dir_all(&directory).unwrap();
+        let cache = Arc::new(cache::ProcessCache::new(directory.join("cache")));
+        let options = builder::ExecuteOptionsBuilder::default()
+            .arg("replayed")
+            .log_to(&log_path)
+            .log_level(Level::Info)
+            .cache(cache.clone())
+            .build();
+
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let logs = SharedBuffer::default();
+        printer.add_log_writer(logs.clone(), Level::Info);
+        printer.execute_process("echo", options.clone()).unwrap();
+        logs.0.lock().unwrap().clear();
+        printer.execute_process("echo", options).unwrap();
+        let output = logs.output();
+        assert!(output.contains("[stdout:echo] replayed"));
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(log.starts_with("cached: echo replayed"));
+        assert!(log.ends_with("replayed\n"));
+
+        buffer.0.lock().unwrap().clear();
+        let stats = printer.cache_report(&cache).unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.stores), (1, 1, 1));
+        let output = buffer.output();
+        assert!(output.contains("1 hits, 1 misses, 1 stored"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn pipeline_stage_errors() {
+        let mut printer = Printer::new_null_term();
+        let failing = pipeline::ExecutePipeline::new("failing")
+            .stage("true", ExecuteOptions::default())
+            .stage("false", ExecuteOptions::default());
+        let error = printer.execute_pipeline(&failing).unwrap_err();
+        assert!(format!("{error:#}").contains("Pipeline stage 2 (false) failed"));
+
+        let missing = pipeline::ExecutePipeline::new("missing")
+            .stage("yes", ExecuteOptions::default())
+            .stage("/nonexistent/printer-stage", ExecuteOptions::default());
+        let error = printer.execute_pipeline(&missing).unwrap_err();
+        assert!(format!("{error:#}")
+            .contains("Failed to spawn pipeline stage 2 (/nonexistent/printer-stage)"));
+
+        // cancelling stops every stage instead of waiting for the slowest
+        let cancelled = pipeline::ExecutePipeline::new("cancelled")
+            .stage("sleep", ExecuteOptions::builder().arg("5").build())
+            .stage("cat", ExecuteOptions::default());
+        printer.controls().request_cancel();
+        let started = std::time::Instant::now();
+        let error = printer.execute_pipeline(&cancelled).unwrap_err();
+        assert!(format!("{error:#}").contains("Cancelled: cancelled"));
+        assert!(started.elapsed() < std::time::Duration::from_secs(4));
+        assert!(!printer.controls().is_cancel_requested());
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn download_complete_partial() {
+        use std::io::Read;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let count = stream.read(&mut request).unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */5\r\n\
+                    Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+            String::from_utf8_lossy(&request[..count]).to_lowercase()
+        });
+
+        let directory =
+            std::env::temp_dir().join(format!("printer_download_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let destination = directory.join("file.txt");
+        std::fs::write(directory.join("file.txt.part"), "hello").unwrap();
+        std::fs::write(directory.join("file.txt.part.validator"), "\"v1\"").unwrap();
+
+        let mut printer = Printer::new_null_term();
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let mut progress_bar = multi_progress.add_progress("download", None, None);
+        progress_bar
+            .download(
+                format!("http://{address}/file.txt").as_str(),
+                &destination,
+                &download::DownloadOptions::default(),
+            )
+            .unwrap();
+        let request = server.join().unwrap();
+        assert!(request.contains("range: bytes=5-"));
+        assert!(request.contains("if-range: \"v1\""));
+        assert_eq!(std::fs::read_to_string(&destination).unwrap(), "hello");
+        assert!(!directory.join("file.txt.part.validator").exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn collapse_flushed_before_scope_end() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let seen = Arc::new(Mutex::new(String::new()));
+        {
+            let buffer = buffer.clone();
+            let seen = seen.clone();
+            printer.on_section_end(move |_, _, _| {
+                *seen.lock().unwrap() = buffer.output();
+            });
+        }
+        {
+            let section = Section::new_collapsible(
+                &mut printer,
+                "Collapsed",
+                std::time::Duration::from_secs(5),
+            )
+            .unwrap();
+            section.printer.object("Hidden", &"World").unwrap();
+        }
+        assert!(printer.collapse_stack.is_empty());
+        let seen = seen.lock().unwrap().clone();
+        assert!(seen.contains("Collapsed ... done"));
+    }
+
+    #[test]
+    fn collapsible_section() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        {
+            let section = Section::new_collapsible(
+                &mut printer,
+                "Collapsed",
+                std::time::Duration::from_secs(5),
+            )
+            .unwrap();
+            section.printer.object("Hidden", &"World").unwrap();
+        }
+        {
+            let mut section = Section::new_collapsible(
+                &mut printer,
+                "Failing",
+                std::time::Duration::from_secs(5),
+            )
+            .unwrap();
+            section.printer.object("Shown", &"World").unwrap();
+            section.mark_failed();
+        }
+        let output = buffer.output();
+        assert!(output.contains("Collapsed ... done"));
+        assert!(!output.contains("Hidden"));
+        assert!(output.contains("Failing"));
+        assert!(output.contains("Shown"));
+    }
+
+    #[test]
+    fn heading_section_guards() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let heading = printer.begin_heading("Guards").unwrap();
+        let base = printer.indent();
+        let first = printer.begin_section("First").unwrap();
+        assert_eq!(printer.indent(), base + 1);
+        printer.info("inside", &"first").unwrap();
+        printer.end_section(first);
+        assert_eq!(printer.indent(), base);
+        let second = printer.begin_section("Second").unwrap();
+        printer.info("inside", &"second").unwrap();
+        drop(second);
+        printer.end_heading(heading);
+        assert_eq!(printer.indent(), 0);
+
+        let output = buffer.output();
+        for name in ["Guards", "First", "Second"] {
+            assert!(output.contains(name));
+        }
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn jobs_header_counts() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        multi_progress.enable_jobs_header();
+        let header = multi_progress.jobs_header.clone().unwrap();
+        let first = multi_progress.add_progress("first", Some(1), None);
+        let mut second = multi_progress.add_progress("second", Some(1), None);
+        assert_eq!(header.counts(), (2, 0, 0));
+        drop(first);
+        second.mark_failed();
+        drop(second);
+        assert_eq!(header.counts(), (0, 1, 1));
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn grep_execution_logs() {
+        let log_path =
+            std::env::temp_dir().join(format!("printer_grep_{}.log", std::process::id()));
+        let options = ExecuteOptions::builder()
+            .label("lines")
+            // the arguments land in the log header, so they must not match
+            .arg("first\\nne%sle\\nlast")
+            .arg("ed")
+            .log_to(&log_path)
+            .build();
+        let mut printer = Printer::new_null_term();
+        printer.execute_process("printf", options).unwrap();
+
+        let execution_log = printer.execution_log();
+        assert_eq!(execution_log.entries.len(), 1);
+        assert_eq!(execution_log.entries[0].label.as_ref(), "lines");
+        let matches = execution_log.grep("needle", 1).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, "needle");
+        assert_eq!(matches[0].before, vec!["first".to_string()]);
+        assert_eq!(printer.grep_logs("missing", 0).unwrap(), 0);
+
+        std::fs::remove_file(&log_path).unwrap();
+    }
+
+    #[test]
+    fn system_report_rows() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let report = printer
+            .system_report(std::env::temp_dir().as_path(), &["printer-missing-tool"])
+            .unwrap();
+        assert!(report.cpu_count > 0);
+        assert_eq!(report.os, std::env::consts::OS);
+        assert!(report.tools[0].path.is_none());
+
+        let output = buffer.output();
+        let rows: Vec<Vec<&str>> = output
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        let cpu_count = report.cpu_count.to_string();
+        assert!(rows.contains(&vec!["cpus", cpu_count.as_str()]));
+        assert!(rows.contains(&vec!["printer-missing-tool", "not", "found"]));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn progress_presets() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        {
+            let mut multi_progress = MultiProgress::new(&mut printer);
+            let classic = multi_progress.add_progress("classic", Some(10), None);
+            assert_eq!(classic.preset, preset::Preset::Classic);
+            let fancy = multi_progress.add_progress_with_preset(
+                "fancy",
+                Some(10),
+                None,
+                preset::Preset::Fancy,
+            );
+            assert_eq!(fancy.preset, preset::Preset::Fancy);
+        }
+
+        // the block characters need unicode
+        printer.set_capabilities(capabilities::Capabilities {
+            is_unicode: false,
+            ..Default::default()
+        });
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let fancy =
+            multi_progress.add_progress_with_preset("fancy", Some(10), None, preset::Preset::Fancy);
+        assert_eq!(fancy.preset, preset::Preset::Ascii);
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn execute_options_builder() {
+        // only stored in the options, the builder never creates it
+        let log_path = std::env::temp_dir().join(format!(
+            "printer_execute_options_builder_{}.log",
+            std::process::id()
+        ));
+        let options = ExecuteOptions::builder()
+            .label("list")
+            .arg("-alt")
+            .args(["first", "second"])
+            .env("KEY", "value")
+            .clear_env()
+            .cwd("/tmp")
+            .capture_stdout()
+            .log_to(&log_path)
+            .append_log()
+            .log_level(Level::Debug)
+            .build();
+        assert_eq!(options.label.as_ref(), "list");
+        assert_eq!(options.arguments, ["-alt", "first", "second"]);
+        assert_eq!(
+            options.environment,
+            vec![(Arc::from("KEY"), Arc::from("value"))]
+        );
+        assert!(options.clear_environment);
+        assert_eq!(
+            options.working_directory,
+            Some(std::path::PathBuf::from("/tmp"))
+        );
+        assert!(options.is_return_stdout);
+        assert_eq!(
+            options.log_file_path.as_deref(),
+            Some(log_path.to_string_lossy().as_ref())
+        );
+        assert!(options.is_append_log);
+        assert_eq!(options.log_level, Some(Level::Debug));
+    }
+
+    #[test]
+    fn indented_guard() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.info("outer", &"top").unwrap();
+        {
+            let _indented = printer.indented();
+            assert_eq!(printer.indent(), 1);
+            printer.info("nested", &"no header").unwrap();
+        }
+        assert_eq!(printer.indent(), 0);
+
+        let output = buffer.output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        // no section header, only the indent
+        assert!(lines[1].find("nested").unwrap() > lines[0].find("outer").unwrap());
+    }
+
+    #[test]
+    fn quiet_scope_silences() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        {
+            let _quiet = printer.quiet_scope();
+            printer.error("muted", &"not shown").unwrap();
+            assert!(!is_verbosity_active(
+                printer.active_verbosity(),
+                Level::Error
+            ));
+        }
+        assert!(is_verbosity_active(
+            printer.active_verbosity(),
+            Level::Error
+        ));
+        printer.error("loud", &"shown").unwrap();
+
+        let output = buffer.output();
+        assert!(!output.contains("not shown"));
+        assert!(output.contains("shown"));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn level_handle_reaches_live_bars() {
+        let mut printer = Printer::new_null_term();
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let progress = multi_progress.add_progress("live", None, None);
+        let is_info_shown = |progress: &MultiProgressBar| {
+            is_verbosity_active(
+                progress.level.resolve(progress.printer_verbosity),
+                Level::Info,
+            )
+        };
+        assert!(is_info_shown(&progress));
+        multi_progress.printer.set_level(Level::Error);
+        assert!(!is_info_shown(&progress));
+        multi_progress.printer.level_handle().clear();
+        assert!(is_info_shown(&progress));
+    }
+
+    #[cfg(feature = "process")]
+    #[test]
+    fn command_template_resolve() {
+        let variables: template::TemplateVariables = [
+            ("target".to_string(), "x86_64".to_string()),
+            ("root".to_string(), "/work".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let mut template = template::CommandTemplate::new("cargo")
+            .arg("build")
+            .arg("--target={target}")
+            .arg("{{literal}}");
+        template.working_directory = Some("{root}/crate".to_string());
+        template.environment = vec![("TARGET".to_string(), "{target}".to_string())];
+        let (command, options) = template
+            .resolve(&variables, ExecuteOptions::default())
+            .unwrap();
+        assert_eq!(command, "cargo");
+        assert_eq!(options.arguments, ["build", "--target=x86_64", "{literal}"]);
+        assert_eq!(
+            options.working_directory,
+            Some(std::path::PathBuf::from("/work/crate"))
+        );
+        assert_eq!(
+            options.environment,
+            vec![(Arc::from("TARGET"), Arc::from("x86_64"))]
+        );
+
+        let error = |argument: &str| {
+            let template = template::CommandTemplate::new("echo").arg(argument);
+            format!(
+                "{:#}",
+                template
+                    .resolve(&variables, ExecuteOptions::default())
+                    .unwrap_err()
+            )
+        };
+        assert!(error("{missing}").contains("Unknown placeholder {missing}"));
+        assert!(error("{target").contains("Unclosed placeholder"));
+        assert!(error("target}").contains("Unmatched `}`"));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn progress_io_counts_bytes() {
+        use std::io::Read;
+        // takes at most 3 bytes per call so partial writes are counted right
+        struct ShortWriter(Vec<u8>);
+        impl std::io::Write for ShortWriter {
+            fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+                let count = buffer.len().min(3);
+                self.0.extend_from_slice(&buffer[..count]);
+                Ok(count)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+
+        let content = vec![7u8; 100_000];
+        let mut reading = multi_progress.add_progress("read", Some(content.len() as u64), None);
+        let mut read = Vec::new();
+        reading
+            .wrap_read(content.as_slice())
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, content);
+        assert_eq!(reading.position(), Some(content.len() as u64));
+
+        let mut writing = multi_progress.add_progress("write", Some(10), None);
+        let mut writer = writing.wrap_write(ShortWriter(Vec::new()));
+        writer.write_all(b"0123456789").unwrap();
+        assert_eq!(writer.into_inner().0, b"0123456789");
+        assert_eq!(writing.position(), Some(10));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn progress_iter_tracks_items() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+
+        let mut counted = multi_progress.add_progress("counted", None, None);
+        let items: Vec<u32> = counted.wrap_iter(vec![1, 2, 3, 4]).collect();
+        assert_eq!(items, [1, 2, 3, 4]);
+        assert_eq!(counted.total(), Some(4));
+        assert_eq!(counted.position(), Some(4));
+
+        // no exact length, the bar stays a spinner
+        let mut filtered = multi_progress.add_progress("filtered", None, None);
+        let evens = filtered
+            .wrap_iter((0..10).filter(|value| value % 2 == 0))
+            .count();
+        assert_eq!(evens, 5);
+        assert!(filtered.percent().is_none());
+        assert_eq!(filtered.position(), Some(5));
+
+        let mut partial = multi_progress.add_progress("partial", None, None);
+        {
+            let mut iter = partial.wrap_iter(0..10);
+            iter.next();
+            iter.next();
+            assert_eq!(iter.size_hint(), (8, Some(8)));
+        }
+        assert_eq!(partial.position(), Some(2));
+
+        assert_eq!(printer.progress_iter("sum", 1..=3).sum::<i32>(), 6);
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn tui_unsupported_fallback() {
+        assert!(tui::is_supported_with(Some("xterm-256color"), true));
+        assert!(tui::is_supported_with(None, true));
+        assert!(!tui::is_supported_with(Some("dumb"), true));
+        assert!(!tui::is_supported_with(Some("xterm-256color"), false));
+
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        // a TUI that did start would take over the terminal running the tests
+        if !console::Term::stdout().is_term() {
+            assert!(!multi_progress.enable_tui());
+        }
+        let mut progress = multi_progress.add_progress("job", Some(2), None);
+        assert!(progress.tui_job.is_none());
+        progress.increment(2);
+        assert_eq!(progress.position(), Some(2));
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn plain_status_line() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.verbosity.is_show_progress_bars = true;
+        printer.add_secret("hunter2");
+        {
+            let mut multi_progress = MultiProgress::new(&mut printer);
+            let mut counted = multi_progress.add_progress("building", Some(40), None);
+            counted.set_position(12);
+            counted.set_message("sdk hunter2");
+            // spinners have nothing to count
+            let mut spinner = multi_progress.add_progress("fetching", None, None);
+            spinner.set_message("index");
+        }
+        let output = buffer.output();
+        let counted = output
+            .lines()
+            .rfind(|line| line.starts_with("[12/40] building"))
+            .unwrap();
+        assert!(counted.contains(" sdk ******** ("), "{output}");
+        assert!(counted.ends_with(')'), "{output}");
+        assert!(!output.contains("hunter2"), "{output}");
+        assert!(output
+            .lines()
+            .any(|line| line.starts_with("fetching") && line.contains(" index (")));
+    }
+
+    #[test]
+    fn github_actions_commands() {
+        use ci::CiAdapter;
+        let adapter = ci::GithubActions;
+        let location = ci::SourceLocation {
+            file: "src/main.rs",
+            line: 12,
+        };
+        assert_eq!(
+            adapter
+                .annotate(Level::Warning, "unused value", Some(&location))
+                .unwrap(),
+            "::warning file=src/main.rs,line=12::unused value"
+        );
+        assert_eq!(
+            adapter
+                .annotate(Level::Error, "50% done\nthen failed", None)
+                .unwrap(),
+            "::error::50%25 done%0Athen failed"
+        );
+
+        // groups don't nest, only the outermost one is folded
+        assert_eq!(adapter.start_group("Build", 0).unwrap(), "::group::Build");
+        assert!(adapter.start_group("Compile", 1).is_none());
+        assert!(adapter.end_group("Compile", 1).is_none());
+        assert_eq!(adapter.end_group("Build", 0).unwrap(), "::endgroup::");
+    }
+
+    #[test]
+    fn teamcity_and_gitlab_markers() {
+        use ci::CiAdapter;
+        let teamcity = ci::TeamCity;
+        let location = ci::SourceLocation {
+            file: "build.rs",
+            line: 3,
+        };
+        assert_eq!(
+            teamcity
+                .annotate(Level::Error, "can't find [sdk]", Some(&location))
+                .unwrap(),
+            "##teamcity[message text='build.rs:3: can|'t find |[sdk|]' status='ERROR']"
+        );
+        // blocks nest, every level opens one
+        assert_eq!(
+            teamcity.start_group("Compile", 1).unwrap(),
+            "##teamcity[blockOpened name='Compile']"
+        );
+        assert_eq!(
+            teamcity.end_group("Compile", 1).unwrap(),
+            "##teamcity[blockClosed name='Compile']"
+        );
+
+        let gitlab = ci::GitLab;
+        assert!(gitlab.annotate(Level::Error, "failed", None).is_none());
+        let start = gitlab.start_group("Run Tests", 1).unwrap();
+        assert!(start.starts_with("\x1b[0Ksection_start:"), "{start:?}");
+        assert!(
+            start.ends_with(":run_tests_1[collapsed=true]\r\x1b[0KRun Tests"),
+            "{start:?}"
+        );
+        let end = gitlab.end_group("Run Tests", 1).unwrap();
+        assert!(end.starts_with("\x1b[0Ksection_end:"), "{end:?}");
+        assert!(end.ends_with(":run_tests_1\r\x1b[0K"), "{end:?}");
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn failure_bundle_contents() {
+        let directory = std::env::temp_dir().join(format!("printer_bundle_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let log_path = directory.join("command.log");
+        let options = ExecuteOptions::builder()
+            .label("broken")
+            .arg("-c")
+            .arg("echo compiling; echo broken >&2; exit 3")
+            .env("API_TOKEN", "hunter2")
+            .env("GREETING", "hello")
+            .log_to(&log_path)
+            .build();
+        let mut printer = Printer::new_null_term();
+        assert!(printer.execute_process("sh", options).is_err());
+        // a registered secret is masked wherever it shows up in a value
+        let path = std::env::var("PATH").unwrap();
+        printer.add_secret(path.as_str());
+
+        let bundle = directory.join("bundle");
+        printer.write_failure_bundle(&bundle).unwrap();
+        let read = |name: &str| std::fs::read_to_string(bundle.join(name)).unwrap();
+
+        let failure: execution_log::FailureRecord =
+            serde_json::from_str(read("failure.json").as_str()).unwrap();
+        assert_eq!(failure.label.as_ref(), "broken");
+        assert_eq!(failure.command.as_ref(), "sh");
+        assert_eq!(failure.exit_code, Some(3));
+        let variables: Vec<(&str, &str)> = failure
+            .environment
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_ref()))
+            .collect();
+        assert_eq!(
+            variables,
+            [("API_TOKEN", "********"), ("GREETING", "hello")]
+        );
+        let execution_log = read("execution_log.json");
+        assert!(!execution_log.contains("hunter2"), "{execution_log}");
+        let execution_log: execution_log::ExecutionLog =
+            serde_json::from_str(execution_log.as_str()).unwrap();
+        assert_eq!(execution_log.failures.len(), 1);
+        let environment: std::collections::BTreeMap<String, String> =
+            serde_json::from_str(read("environment.json").as_str()).unwrap();
+        assert_eq!(environment["PATH"], "********");
+        assert!(environment
+            .iter()
+            .filter(|(name, _)| name.contains("TOKEN") || name.contains("SECRET"))
+            .all(|(_, value)| value == "********"));
+        let system: serde_json::Value = serde_json::from_str(read("system.json").as_str()).unwrap();
+        assert_eq!(system["os"], std::env::consts::OS);
+        let output = read("output.txt");
+        assert!(output.contains("compiling"), "{output}");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn filtered_warning_fails_collapse() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.verbosity.level = Level::Error;
+        {
+            let section =
+                Section::new_collapsible(&mut printer, "Warned", std::time::Duration::from_secs(5))
+                    .unwrap();
+            section.printer.object("Shown", &"World").unwrap();
+            pwarn!(section.printer, "{} hidden", 1).unwrap();
+        }
+        let output = buffer.output();
+        assert!(output.contains("Shown"), "{output}");
+        assert!(!output.contains("1 hidden"), "{output}");
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn jobs_header_shown_automatically() {
+        let mut printer = Printer::new_null_term();
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let first = multi_progress.add_progress("first", Some(1), None);
+        let header = multi_progress.jobs_header.clone().unwrap();
+        assert!(!header.is_shown());
+        let second = multi_progress.add_progress("second", Some(1), None);
+        assert!(header.is_shown());
+        // bars added before the header showed up are counted too
+        assert_eq!(header.counts(), (2, 0, 0));
+        drop(first);
+        drop(second);
+        assert_eq!(header.counts(), (0, 2, 0));
+
+        // plain mode has no header to redraw
+        let mut printer = Printer::from_writer(SharedBuffer::default());
+        printer.verbosity.is_show_progress_bars = true;
+        let mut multi_progress = MultiProgress::new(&mut printer);
+        let _first = multi_progress.add_progress("first", Some(1), None);
+        let _second = multi_progress.add_progress("second", Some(1), None);
+        assert!(!multi_progress.jobs_header.clone().unwrap().is_shown());
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn execute_process_marks_failed_bar() {
+        let path =
+            std::env::temp_dir().join(format!("printer_failed_bar_{}.jsonl", std::process::id()));
+        {
+            let mut printer = Printer::new_null_term();
+            printer.verbosity.is_show_progress_bars = true;
+            printer.record_session(&path).unwrap();
+            assert!(printer
+                .execute_process("false", ExecuteOptions::default())
+                .is_err());
+        }
+        let events = session::read_session(&path).unwrap();
+        assert!(events.iter().any(|recorded| matches!(
+            recorded.event,
+            session::SessionEvent::BarFinished {
+                is_failed: true,
+                ..
+            }
+        )));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn draw_target_plain_println() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.add_secret("hunter2");
+        let multi_progress = MultiProgress::new(&mut printer);
+        let target = multi_progress.draw_target();
+        target.println("from a library hunter2").unwrap();
+        let output = buffer.output();
+        assert_eq!(output, "from a library ********\n");
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn watch_expand_prunes() {
+        let root = std::env::temp_dir().join(format!(
+            "printer_watch_expand_prunes_{}",
+            std::process::id()
+        ));
+        for (file, content) in [
+            ("src/main.rs", ""),
+            ("src/nested/lib.rs", ""),
+            ("src/notes.txt", ""),
+            (".git/hook.rs", ""),
+            (
+                "target/CACHEDIR.TAG",
+                "Signature: 8a477f597d28d172789f06886806bc55",
+            ),
+            ("target/build.rs", ""),
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        // a cycle that would recurse forever if symlinks were followed
+        std::os::unix::fs::symlink(&root, root.join("src/cycle")).unwrap();
+
+        let relative = |pattern: &str| {
+            let mut files: Vec<String> =
+                watch::expand(format!("{}/{pattern}", root.display()).as_str())
+                    .iter()
+                    .map(|file| file.strip_prefix(&root).unwrap().display().to_string())
+                    .collect();
+            files.sort();
+            files
+        };
+        assert_eq!(relative("**/*.rs"), ["src/main.rs", "src/nested/lib.rs"]);
+        assert_eq!(
+            relative("src/*"),
+            ["src/cycle", "src/main.rs", "src/notes.txt"]
+        );
+        assert_eq!(
+            relative("src/**"),
+            [
+                "src/cycle",
+                "src/main.rs",
+                "src/nested/lib.rs",
+                "src/notes.txt"
+            ]
+        );
+        assert_eq!(relative(".git/*.rs"), [".git/hook.rs"]);
+        assert_eq!(relative("target/**/*.rs"), ["target/build.rs"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn ascii_glyphs() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        printer.set_capabilities(capabilities::Capabilities {
+            is_unicode: false,
+            ..Default::default()
+        });
+        printer.indent_style.is_guide_visible = true;
+        {
+            let _indented = printer.indented();
+            let tree = text::TreeNode::new("workspace")
+                .child(text::TreeNode::new("printer").child(text::TreeNode::new("derive")))
+                .child(text::TreeNode::new("docs"));
+            printer.tree(&tree).unwrap();
+        }
+        {
+            use checklist::StepState;
+            let mut checklist = checklist::Checklist::new(&mut printer, &["build", "test"]);
+            checklist.set_state(0, StepState::Done).unwrap();
+            checklist.set_state(1, StepState::Failed).unwrap();
+        }
+        let output = buffer.output();
+        assert!(output.is_ascii(), "{output}");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "| workspace",
+                "| |-- printer",
+                "| |   `-- derive",
+                "| `-- docs",
+                "v build",
+                "x test"
+            ]
+        );
+
+        let gauge = gauge::Gauge::new("mem");
+        gauge.set(0.62);
+        let rendered = gauge.render(printer.theme());
+        assert_eq!(format::strip_ansi_codes(rendered.as_str()), "mem ###-- 62%");
+        assert!(printer
+            .theme()
+            .glyphs
+            .spinner
+            .iter()
+            .all(|frame| frame.is_ascii()));
+    }
+
+    #[cfg(all(unix, feature = "process"))]
+    #[test]
+    fn process_log_sources() {
+        let buffer = SharedBuffer::default();
+        let mut printer = Printer::from_writer(buffer.clone());
+        let options = ExecuteOptions::builder()
+            .arg("-c")
+            .arg("echo out; echo err >&2")
+            .log_level(Level::Info)
+            .build();
+        printer.execute_process("sh", options).unwrap();
+        let output = buffer.output();
+        assert!(output.contains("[stdout:sh] out"), "{output}");
+        assert!(output.contains("[stderr:sh] err"), "{output}");
+    }
+
+    #[cfg(feature = "progress")]
+    #[test]
+    fn printer_handle_indent_per_thread() {
+        let handle = handle::PrinterHandle::new(Printer::new_null_term());
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let tasks: Vec<_> = (0..2)
+            .map(|index| {
+                let handle = handle.clone();
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    let section = handle.begin_section(&format!("Task{index}")).unwrap();
+                    let _indented = handle.indented();
+                    // both threads are nested here at the same time
+                    barrier.wait();
+                    let depth = handle.with(|printer| printer.indent());
+                    barrier.wait();
+                    (depth, section)
+                })
+            })
+            .collect();
+        for task in tasks {
+            let (depth, _section) = task.join().unwrap();
+            assert_eq!(depth, 2);
+            // sections of other threads leave this one at the top level
+            assert_eq!(handle.with(|printer| printer.indent()), 0);
+        }
+    }
 }
@@ -0,0 +1,188 @@
+use crate::{format, Level, Printer};
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+#[cfg(feature = "process")]
+use std::io::Read;
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Mutex,
+    },
+};
+
+// Picked from the log file extension. Appending to a compressed log adds a new
+// gzip member or zstd frame, which both formats read back as one stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn from_path(path: &str) -> Self {
+        if path.ends_with(".gz") {
+            Self::Gzip
+        } else if path.ends_with(".zst") {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    fn check_enabled(self, path: &str) -> anyhow::Result<()> {
+        let (feature, is_enabled) = match self {
+            Self::None => return Ok(()),
+            Self::Gzip => ("gzip", cfg!(feature = "gzip")),
+            Self::Zstd => ("zstd", cfg!(feature = "zstd")),
+        };
+        if is_enabled {
+            Ok(())
+        } else {
+            Err(format_error!(
+                "{path} is compressed but printer was built without the `{feature}` feature"
+            ))
+        }
+    }
+}
+
+pub(crate) enum LogWriter {
+    Plain(std::fs::File),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<std::fs::File>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::AutoFinishEncoder<'static, std::fs::File>),
+}
+
+impl LogWriter {
+    // Compressed writers finish their stream when dropped
+    pub(crate) fn open(path: &str, is_append: bool) -> anyhow::Result<Self> {
+        let compression = Compression::from_path(path);
+        compression.check_enabled(path)?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(is_append)
+            .truncate(!is_append)
+            .open(path)
+            .context(format_context!("while creating {path}"))?;
+        match compression {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Self::Gzip(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let encoder = zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL)
+                    .context(format_context!("while creating {path}"))?;
+                Ok(Self::Zstd(encoder.auto_finish()))
+            }
+            _ => Ok(Self::Plain(file)),
+        }
+    }
+
+    fn inner(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Plain(file) => file,
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder,
+            #[cfg(feature = "zstd")]
+            Self::Zstd(encoder) => encoder,
+        }
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.inner().write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner().flush()
+    }
+}
+
+// Reads a log written by LogWriter, decompressing it if needed
+#[cfg(feature = "process")]
+pub(crate) fn open_reader(path: &str) -> anyhow::Result<Box<dyn Read>> {
+    let compression = Compression::from_path(path);
+    compression.check_enabled(path)?;
+    let file = std::fs::File::open(path).context(format_context!("while opening {path}"))?;
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(file))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(
+            zstd::Decoder::new(file).context(format_context!("while opening {path}"))?,
+        )),
+        _ => Ok(Box::new(file)),
+    }
+}
+
+struct LevelWriter {
+    level: Level,
+    writer: Box<dyn Write + Send>,
+}
+
+// Secondary outputs for log lines, each with its own level so a file can keep
+// Trace detail while the terminal only shows Info. Shared with progress bars.
+#[derive(Clone)]
+pub(crate) struct LogWriters {
+    writers: Arc<Mutex<Vec<LevelWriter>>>,
+    // lowest level any writer accepts, checked before formatting a line
+    min_level: Arc<AtomicU8>,
+}
+
+impl Default for LogWriters {
+    fn default() -> Self {
+        Self {
+            writers: Arc::new(Mutex::new(Vec::new())),
+            min_level: Arc::new(AtomicU8::new(u8::MAX)),
+        }
+    }
+}
+
+impl LogWriters {
+    fn add(&self, level: Level, writer: Box<dyn Write + Send>) {
+        self.writers
+            .lock()
+            .unwrap()
+            .push(LevelWriter { level, writer });
+        self.min_level.fetch_min(level as u8, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_active(&self, level: Level) -> bool {
+        level != Level::Silent && level as u8 >= self.min_level.load(Ordering::Relaxed)
+    }
+
+    // `line` is the terminal formatted line; writers get it without colors or
+    // the padding that clears the rest of a terminal row
+    pub(crate) fn write(&self, level: Level, line: &str) {
+        if !self.is_active(level) {
+            return;
+        }
+        let line = format!("{}\n", format::strip_ansi_codes(line).trim_end());
+        for level_writer in self.writers.lock().unwrap().iter_mut() {
+            if level >= level_writer.level {
+                let _ = level_writer.writer.write_all(line.as_bytes());
+            }
+        }
+    }
+}
+
+impl Printer {
+    // Logs at `level` and above go to `path` whatever the terminal shows. A
+    // `.gz` or `.zst` path is compressed.
+    pub fn log_to_file(&mut self, path: &std::path::Path, level: Level) -> anyhow::Result<()> {
+        let path = path.to_string_lossy();
+        let writer = LogWriter::open(path.as_ref(), false).context(format_context!(""))?;
+        self.log_writers.add(level, Box::new(writer));
+        Ok(())
+    }
+
+    pub fn add_log_writer<Writer: Write + Send + 'static>(&mut self, writer: Writer, level: Level) {
+        self.log_writers.add(level, Box::new(writer));
+    }
+}
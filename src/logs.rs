@@ -0,0 +1,271 @@
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// run directories are named like 20260131-235959, with -2, -3 appended when
+// several runs start within the same second
+const TIMESTAMP_LENGTH: usize = 15;
+const LATEST_LINK: &str = "latest";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PruneOptions {
+    // runs last modified longer ago than this are removed
+    pub max_age: Option<Duration>,
+    // at most this many runs are kept, the current one included
+    pub max_runs: Option<usize>,
+}
+
+// Hands out one log file per job under `<directory>/<timestamp>/<job>.log`.
+// `<directory>/latest` links to the newest run.
+pub struct LogManager {
+    directory: PathBuf,
+    run_directory: PathBuf,
+    allocated: Mutex<HashSet<String>>,
+}
+
+fn days_to_date(days: u64) -> (u64, u64, u64) {
+    // civil_from_days from Howard Hinnant's date algorithms
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+fn run_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = days_to_date(seconds / 86400);
+    let seconds_of_day = seconds % 86400;
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        seconds_of_day / 3600,
+        (seconds_of_day / 60) % 60,
+        seconds_of_day % 60
+    )
+}
+
+fn is_run_name(name: &str) -> bool {
+    let Some(timestamp) = name.get(..TIMESTAMP_LENGTH) else {
+        return false;
+    };
+    let rest = &name[TIMESTAMP_LENGTH..];
+    timestamp
+        .char_indices()
+        .all(|(index, character)| match index {
+            8 => character == '-',
+            _ => character.is_ascii_digit(),
+        })
+        && (rest.is_empty()
+            || rest
+                .strip_prefix('-')
+                .is_some_and(|suffix| suffix.chars().all(|character| character.is_ascii_digit())))
+}
+
+fn sanitize_job_name(job: &str) -> String {
+    let name: String = job
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || "-_.".contains(character) {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match name.trim_matches('.') {
+        "" => "job".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+impl LogManager {
+    // Creates a new run directory under `directory`, which is usually relative
+    // to the workspace root
+    pub fn new<Directory: Into<PathBuf>>(directory: Directory) -> anyhow::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)
+            .context(format_context!("while creating {}", directory.display()))?;
+
+        let timestamp = run_timestamp(SystemTime::now());
+        let mut run_directory = directory.join(timestamp.as_str());
+        let mut attempt = 1;
+        // create_dir fails if another run got there first, so this is race free
+        loop {
+            match std::fs::create_dir(&run_directory) {
+                Ok(()) => break,
+                Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                    attempt += 1;
+                    run_directory = directory.join(format!("{timestamp}-{attempt}"));
+                }
+                Err(error) => {
+                    return Err(error).context(format_context!(
+                        "while creating {}",
+                        run_directory.display()
+                    ))
+                }
+            }
+        }
+
+        let manager = Self {
+            directory,
+            run_directory,
+            allocated: Mutex::new(HashSet::new()),
+        };
+        manager.update_latest().context(format_context!(
+            "while linking {}",
+            manager.latest().display()
+        ))?;
+        Ok(manager)
+    }
+
+    // Always points at the most recent run
+    pub fn latest(&self) -> PathBuf {
+        self.directory.join(LATEST_LINK)
+    }
+
+    #[cfg(unix)]
+    fn update_latest(&self) -> anyhow::Result<()> {
+        let target = self
+            .run_directory
+            .file_name()
+            .ok_or(format_error!("run directory has no name"))?;
+        // renaming over the old link swaps it atomically so readers never
+        // see a missing `latest`
+        let staging = self
+            .directory
+            .join(format!("{LATEST_LINK}.{}", std::process::id()));
+        let _ = std::fs::remove_file(&staging);
+        std::os::unix::fs::symlink(target, &staging)
+            .context(format_context!("while creating {}", staging.display()))?;
+        std::fs::rename(&staging, self.latest())
+            .context(format_context!("while renaming {}", staging.display()))?;
+        Ok(())
+    }
+
+    // Symbolic links need developer mode or elevation on Windows, junctions
+    // don't. Neither can be replaced atomically.
+    #[cfg(windows)]
+    fn update_latest(&self) -> anyhow::Result<()> {
+        let latest = self.latest();
+        if std::fs::symlink_metadata(&latest).is_ok() {
+            std::fs::remove_dir(&latest)
+                .context(format_context!("while removing {}", latest.display()))?;
+        }
+        if std::os::windows::fs::symlink_dir(&self.run_directory, &latest).is_ok() {
+            return Ok(());
+        }
+        let target = std::path::absolute(&self.run_directory).context(format_context!(
+            "while resolving {}",
+            self.run_directory.display()
+        ))?;
+        let status = std::process::Command::new("cmd")
+            .arg("/C")
+            .arg("mklink")
+            .arg("/J")
+            .arg(&latest)
+            .arg(&target)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .context(format_context!("while running mklink"))?;
+        if !status.success() {
+            return Err(format_error!(
+                "mklink /J {} {} failed",
+                latest.display(),
+                target.display()
+            ));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn update_latest(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn run_directory(&self) -> &Path {
+        &self.run_directory
+    }
+
+    // A log path for `job` to set on ExecuteOptions::log_file_path. Jobs with
+    // the same name get `-2`, `-3` and so on appended.
+    pub fn allocate(&self, job: &str) -> Arc<str> {
+        let name = sanitize_job_name(job);
+        let mut allocated = self.allocated.lock().unwrap();
+        let mut candidate = name.clone();
+        let mut attempt = 1;
+        while !allocated.insert(candidate.clone()) {
+            attempt += 1;
+            candidate = format!("{name}-{attempt}");
+        }
+        self.run_directory
+            .join(format!("{candidate}.log"))
+            .to_string_lossy()
+            .into()
+    }
+
+    // Removes earlier runs that are too old or beyond the count to keep. The
+    // current run is never removed. Returns how many runs were removed.
+    pub fn prune(&self, options: PruneOptions) -> anyhow::Result<usize> {
+        let entries = std::fs::read_dir(&self.directory).context(format_context!(
+            "while reading {}",
+            self.directory.display()
+        ))?;
+        let mut runs = Vec::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            let is_directory = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            if is_directory && is_run_name(name.as_str()) && path != self.run_directory {
+                runs.push((name, path));
+            }
+        }
+        // names sort oldest first
+        runs.sort();
+
+        let now = SystemTime::now();
+        let keep_earlier = options
+            .max_runs
+            .map(|max_runs| max_runs.saturating_sub(1))
+            .unwrap_or(usize::MAX);
+        let excess = runs.len().saturating_sub(keep_earlier);
+
+        let mut removed = 0;
+        for (index, (_, path)) in runs.iter().enumerate() {
+            let is_too_old = options.max_age.is_some_and(|max_age| {
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .is_some_and(|age| age > max_age)
+            });
+            if index < excess || is_too_old {
+                std::fs::remove_dir_all(path)
+                    .context(format_context!("while removing {}", path.display()))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,33 @@
+// Logging with format arguments. The message is only formatted when the level
+// is active, so filtered calls don't build a String.
+//
+// pinfo!(printer, "built {} targets", count)?;
+// pwarn!(progress_bar, "retrying {url}");
+
+#[macro_export]
+macro_rules! plog {
+    ($printer:expr, $level:expr, $($argument:tt)+) => {
+        $printer.log_fmt($level, format_args!($($argument)+))
+    };
+}
+
+#[macro_export]
+macro_rules! pinfo {
+    ($printer:expr, $($argument:tt)+) => {
+        $crate::plog!($printer, $crate::Level::Info, $($argument)+)
+    };
+}
+
+#[macro_export]
+macro_rules! pwarn {
+    ($printer:expr, $($argument:tt)+) => {
+        $crate::plog!($printer, $crate::Level::Warning, $($argument)+)
+    };
+}
+
+#[macro_export]
+macro_rules! perror {
+    ($printer:expr, $($argument:tt)+) => {
+        $crate::plog!($printer, $crate::Level::Error, $($argument)+)
+    };
+}
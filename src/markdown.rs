@@ -1,4 +1,63 @@
-use crate::Printer;
+use crate::{
+    text::{MarkdownText, Table},
+    Printer,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadingRecord {
+    pub text: String,
+    pub slug: String,
+    pub level: usize,
+}
+
+// GitHub-style anchor: lowercase, spaces become `-`, punctuation is dropped
+pub fn slug(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|character| match character {
+            ' ' => Some('-'),
+            '-' | '_' => Some(character),
+            _ if character.is_alphanumeric() => Some(character),
+            _ => None,
+        })
+        .collect()
+}
+
+impl Printer {
+    pub fn enable_heading_records(&mut self) {
+        if self.heading_records.is_none() {
+            self.heading_records = Some(Vec::new());
+        }
+    }
+
+    pub fn heading_records(&self) -> &[HeadingRecord] {
+        self.heading_records.as_deref().unwrap_or_default()
+    }
+
+    pub(crate) fn record_heading(&mut self, text: &str, level: usize) {
+        let Some(records) = self.heading_records.as_mut() else {
+            return;
+        };
+        let base = slug(text);
+        let duplicates = records
+            .iter()
+            .filter(|record| slug(record.text.as_str()) == base)
+            .count();
+        let slug = if duplicates == 0 {
+            base
+        } else {
+            format!("{base}-{duplicates}")
+        };
+        records.push(HeadingRecord {
+            text: text.to_string(),
+            slug,
+            level,
+        });
+    }
+}
 
 pub struct Markdown<'a> {
     pub printer: &'a mut Printer,
@@ -9,55 +68,53 @@ impl<'a> Markdown<'a> {
         Markdown { printer }
     }
 
+    fn write_with<Function>(&mut self, function: Function) -> anyhow::Result<()>
+    where
+        Function: FnOnce(&mut MarkdownText<&mut String>) -> std::fmt::Result,
+    {
+        let mut buffer = String::new();
+        function(&mut MarkdownText::new(&mut buffer))?;
+        self.printer.write(buffer.as_str())
+    }
+
     pub fn heading(&mut self, level: u8, content: &str) -> anyhow::Result<()> {
-        self.printer
-            .write(&format!("{} {}\n\n", "#".repeat(level as usize), content))?;
-        Ok(())
+        self.printer.record_heading(content, level as usize);
+        self.write_with(|text| text.heading(level, content))
     }
 
     pub fn list(&mut self, items: Vec<&str>) -> anyhow::Result<()> {
-        for item in items {
-            self.printer.write(&format!("- {}\n", item))?;
-        }
-        self.printer.write("\n")?;
-        Ok(())
+        self.write_with(|text| text.list(&items))
     }
 
     pub fn list_item(&mut self, level: u8, item: &str) -> anyhow::Result<()> {
-        let level = if level == 0 { 1_usize } else { level as usize };
-        self.printer
-            .write(&format!("{}- {}\n", " ".repeat(((level) - 1) * 2), item))?;
-        Ok(())
+        self.write_with(|text| text.list_item(level, item))
     }
 
     pub fn bold(&mut self, content: &str) -> anyhow::Result<()> {
-        self.printer.write(&format!("**{}**", content))?;
-        Ok(())
+        self.write_with(|text| text.bold(content))
     }
 
     pub fn italic(&mut self, content: &str) -> anyhow::Result<()> {
-        self.printer.write(&format!("*{}*", content))?;
-        Ok(())
+        self.write_with(|text| text.italic(content))
     }
 
     pub fn strikethrough(&mut self, content: &str) -> anyhow::Result<()> {
-        self.printer.write(&format!("~~{}~~", content))?;
-        Ok(())
+        self.write_with(|text| text.strikethrough(content))
     }
 
     pub fn code(&mut self, content: &str) -> anyhow::Result<()> {
-        self.printer.write(&format!("`{}`", content))?;
-        Ok(())
+        self.write_with(|text| text.code(content))
     }
 
     pub fn code_block(&mut self, code_type: &str, content: &str) -> anyhow::Result<()> {
-        self.printer
-            .write(&format!("```{code_type}\n{}\n```", content))?;
-        Ok(())
+        self.write_with(|text| text.code_block(code_type, content))
     }
 
     pub fn paragraph(&mut self, content: &str) -> anyhow::Result<()> {
-        self.printer.write(&format!("{}\n\n", content))?;
-        Ok(())
+        self.write_with(|text| text.paragraph(content))
+    }
+
+    pub fn table(&mut self, table: &Table) -> anyhow::Result<()> {
+        self.write_with(|text| text.table(table))
     }
 }
@@ -0,0 +1,209 @@
+use crate::{
+    server::{self, ServerGuard},
+    session::{EventSink, RecordedEvent, SessionEvent},
+    Printer,
+};
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{BufRead, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+};
+
+// upper bounds in seconds, from quick checks to full workspace syncs
+const DURATION_BUCKETS: [f64; 10] = [0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 600.0, 1800.0];
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    started: u64,
+    succeeded: u64,
+    failed: u64,
+    // start time in milliseconds of each running job
+    active: HashMap<usize, u64>,
+    bucket_counts: [u64; DURATION_BUCKETS.len()],
+    duration_count: u64,
+    duration_sum: f64,
+}
+
+impl MetricsState {
+    fn apply(&mut self, recorded: &RecordedEvent) {
+        match &recorded.event {
+            SessionEvent::BarStarted { id, .. } => {
+                self.started += 1;
+                self.active.insert(*id, recorded.at);
+            }
+            SessionEvent::BarFinished { id, is_failed } => {
+                if *is_failed {
+                    self.failed += 1;
+                } else {
+                    self.succeeded += 1;
+                }
+                if let Some(started_at) = self.active.remove(id) {
+                    let seconds = recorded.at.saturating_sub(started_at) as f64 / 1000.0;
+                    for (bucket, bound) in DURATION_BUCKETS.iter().enumerate() {
+                        if seconds <= *bound {
+                            self.bucket_counts[bucket] += 1;
+                        }
+                    }
+                    self.duration_count += 1;
+                    self.duration_sum += seconds;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut text = String::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            let _ = write!(
+                text,
+                "# HELP {name} {help}\n# TYPE {name} counter\n{name} {value}\n"
+            );
+        };
+        counter("printer_jobs_started_total", "Jobs started.", self.started);
+        counter(
+            "printer_jobs_succeeded_total",
+            "Jobs that finished successfully.",
+            self.succeeded,
+        );
+        counter(
+            "printer_jobs_failed_total",
+            "Jobs that failed.",
+            self.failed,
+        );
+
+        let _ = write!(
+            text,
+            "# HELP printer_jobs_active Jobs currently running.\n# TYPE printer_jobs_active gauge\nprinter_jobs_active {}\n",
+            self.active.len()
+        );
+
+        let name = "printer_job_duration_seconds";
+        let _ = write!(
+            text,
+            "# HELP {name} Job durations.\n# TYPE {name} histogram\n"
+        );
+        for (bound, count) in DURATION_BUCKETS.iter().zip(self.bucket_counts.iter()) {
+            let _ = writeln!(text, "{name}_bucket{{le=\"{bound}\"}} {count}");
+        }
+        let _ = write!(
+            text,
+            "{name}_bucket{{le=\"+Inf\"}} {count}\n{name}_sum {}\n{name}_count {count}\n",
+            self.duration_sum,
+            count = self.duration_count
+        );
+        text
+    }
+}
+
+struct MetricsSink {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl EventSink for MetricsSink {
+    fn event(&mut self, event: &RecordedEvent) {
+        self.state.lock().unwrap().apply(event);
+    }
+}
+
+// Job counters, active jobs and a duration histogram in the Prometheus text
+// format, derived from the printer's progress bars
+#[derive(Clone)]
+pub struct Metrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl Metrics {
+    pub fn render(&self) -> String {
+        self.state.lock().unwrap().render()
+    }
+
+    // Serves the metrics at `/metrics` for Prometheus to scrape until the
+    // returned guard is dropped
+    pub fn serve<Address: ToSocketAddrs>(&self, address: Address) -> anyhow::Result<ServerGuard> {
+        let metrics = self.clone();
+        server::serve(address, "metrics", move |stream| metrics.respond(stream))
+            .context(format_context!(""))
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        let mut reader = std::io::BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+            header.clear();
+        }
+        let (status, body) = match request_line.split_whitespace().nth(1) {
+            Some("/metrics") => ("200 OK", self.render()),
+            _ => ("404 Not Found", "not found".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        stream.flush()
+    }
+
+    // Pushes the current values to a Prometheus pushgateway such as
+    // `http://gateway:9091`, grouped under `job`
+    pub fn push(&self, gateway: &str, job: &str) -> anyhow::Result<()> {
+        let host = gateway
+            .strip_prefix("http://")
+            .ok_or(format_error!(
+                "Only http:// pushgateway URLs are supported: {gateway}"
+            ))?
+            .trim_end_matches('/');
+        let body = self.render();
+        let mut stream =
+            TcpStream::connect(host).context(format_context!("while connecting to {gateway}"))?;
+        write!(
+            stream,
+            "PUT /metrics/job/{} HTTP/1.1\r\nHost: {host}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            percent_encode(job),
+            body.len()
+        )
+        .context(format_context!("while pushing to {gateway}"))?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .context(format_context!("while reading the response from {gateway}"))?;
+        let status = response.split_whitespace().nth(1).unwrap_or("");
+        if !status.starts_with('2') {
+            return Err(format_error!(
+                "Pushgateway {gateway} rejected metrics: {}",
+                response.lines().next().unwrap_or("")
+            ));
+        }
+        Ok(())
+    }
+}
+
+// `job` is a path segment, so everything but unreserved characters is escaped
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+impl Printer {
+    pub fn enable_metrics(&mut self) -> Metrics {
+        let state = Arc::new(Mutex::new(MetricsState::default()));
+        self.add_event_sink(Box::new(MetricsSink {
+            state: state.clone(),
+        }));
+        Metrics { state }
+    }
+}
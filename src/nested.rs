@@ -0,0 +1,27 @@
+// Set to the depth of nesting, 1 for a direct child, for processes started
+// with `ExecuteOptionsBuilder::child_protocol()`. Other children inherit this
+// process's value unchanged. A child that also uses the crate reads it and
+// reports in plain mode, whose one line per update the parent already shows
+// as the message of the bar running it.
+pub const NESTED_ENV_VAR: &str = "PRINTER_NESTED";
+
+// 0 when this process was not started by another printer
+pub fn depth() -> usize {
+    depth_with(std::env::var(NESTED_ENV_VAR).ok())
+}
+
+pub fn is_nested() -> bool {
+    depth() > 0
+}
+
+pub(crate) fn depth_with(value: Option<String>) -> usize {
+    value
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+// the value handed to children of this process
+#[cfg(feature = "process")]
+pub(crate) fn child_depth() -> String {
+    (depth() + 1).to_string()
+}
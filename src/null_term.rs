@@ -1,5 +1,6 @@
 use std::fmt::Debug;
 use std::io::{Result as IoResult, Write};
+#[cfg(feature = "progress")]
 use indicatif::TermLike;
 
 #[derive(Default)]
@@ -22,6 +23,7 @@ impl Write for NullTerm {
 }
 
 // Implement TermLike for NullTerm
+#[cfg(feature = "progress")]
 impl TermLike for NullTerm {
     fn write_line(&self, _: &str) -> IoResult<()> {
         Ok(()) // Discard the line
@@ -0,0 +1,111 @@
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+};
+
+// The lock belongs to an open file, so printers of one process share the
+// handle instead of finding the lock taken by each other.
+static SHARED_LOCK: Mutex<Option<Weak<GlobalOutputLock>>> = Mutex::new(None);
+
+// An advisory lock on a file named after the terminal, held by the printer
+// that owns the screen. A second printer in the same terminal, like a tool
+// that runs itself recursively, finds the lock taken and reports progress as
+// plain prefixed lines instead of redrawing over the first one's bars. The
+// lock is released when the holder exits, even when it crashes.
+#[derive(Debug)]
+pub struct GlobalOutputLock {
+    path: PathBuf,
+    // held open for as long as the lock is
+    _file: File,
+}
+
+impl GlobalOutputLock {
+    // None when stdout is not a terminal, there are no redraws to serialize
+    pub fn terminal_path() -> Option<PathBuf> {
+        let name = terminal_name()?;
+        let name: String = name
+            .trim_start_matches('/')
+            .chars()
+            .map(|character| {
+                if character.is_ascii_alphanumeric() {
+                    character
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        Some(std::env::temp_dir().join(format!("printer-output-{name}.lock")))
+    }
+
+    // Ok(None) when another process holds the lock
+    pub fn try_acquire(path: &Path) -> anyhow::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .context(format_context!("while opening {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self {
+                path: path.to_path_buf(),
+                _file: file,
+            })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(error)) => {
+                Err(error).context(format_context!("while locking {}", path.display()))
+            }
+        }
+    }
+
+    // Like try_acquire, but returns the handle another printer of this
+    // process already holds for the same path
+    pub fn acquire_shared(path: &Path) -> anyhow::Result<Option<Arc<Self>>> {
+        let mut shared = SHARED_LOCK.lock().unwrap();
+        if let Some(lock) = shared.as_ref().and_then(Weak::upgrade) {
+            if lock.path == path {
+                return Ok(Some(lock));
+            }
+        }
+        let lock = Self::try_acquire(path)?.map(Arc::new);
+        if let Some(lock) = lock.as_ref() {
+            *shared = Some(Arc::downgrade(lock));
+        }
+        Ok(lock)
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+}
+
+#[cfg(unix)]
+fn terminal_name() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    unsafe {
+        if libc::isatty(libc::STDOUT_FILENO) == 0 {
+            return None;
+        }
+        if libc::ttyname_r(
+            libc::STDOUT_FILENO,
+            buffer.as_mut_ptr() as *mut libc::c_char,
+            buffer.len(),
+        ) != 0
+        {
+            return None;
+        }
+    }
+    let name = std::ffi::CStr::from_bytes_until_nul(&buffer).ok()?;
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(unix))]
+fn terminal_name() -> Option<String> {
+    use std::io::IsTerminal;
+    // one console per process tree is the common case
+    std::io::stdout()
+        .is_terminal()
+        .then(|| "console".to_string())
+}
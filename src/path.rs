@@ -0,0 +1,79 @@
+use crate::Printer;
+use std::path::{Path, PathBuf};
+
+fn home_directory() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .filter(|home| !home.as_os_str().is_empty())
+}
+
+pub struct DisplayPath<'a> {
+    path: &'a Path,
+    root: Option<&'a Path>,
+}
+
+impl<'a> DisplayPath<'a> {
+    pub fn new(path: &'a Path, root: Option<&'a Path>) -> Self {
+        Self { path, root }
+    }
+}
+
+impl std::fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(relative) = self.root.and_then(|root| self.path.strip_prefix(root).ok()) {
+            if relative.as_os_str().is_empty() {
+                return write!(formatter, ".");
+            }
+            return write!(formatter, "{}", relative.display());
+        }
+        if let Some(home) = home_directory() {
+            if let Ok(relative) = self.path.strip_prefix(&home) {
+                if relative.as_os_str().is_empty() {
+                    return write!(formatter, "~");
+                }
+                return write!(
+                    formatter,
+                    "~{}{}",
+                    std::path::MAIN_SEPARATOR,
+                    relative.display()
+                );
+            }
+        }
+        write!(formatter, "{}", self.path.display())
+    }
+}
+
+// rewrites root and home prefixes found anywhere in free-form text
+pub(crate) fn shorten_paths(root: Option<&Path>, text: &str) -> String {
+    let mut result = text.to_string();
+    if let Some(root) = root.map(|root| root.display().to_string()) {
+        if !root.is_empty() {
+            result = result
+                .replace(format!("{root}{}", std::path::MAIN_SEPARATOR).as_str(), "")
+                .replace(root.as_str(), ".");
+        }
+    }
+    if let Some(home) = home_directory().map(|home| home.display().to_string()) {
+        result = result.replace(home.as_str(), "~");
+    }
+    result
+}
+
+impl Printer {
+    pub fn set_path_root<Root: Into<PathBuf>>(&mut self, root: Root) {
+        self.path_root = Some(root.into());
+    }
+
+    pub fn path_root(&self) -> Option<&Path> {
+        self.path_root.as_deref()
+    }
+
+    pub fn display_path<'a>(&'a self, path: &'a Path) -> DisplayPath<'a> {
+        DisplayPath::new(path, self.path_root())
+    }
+
+    pub(crate) fn shorten_paths(&self, text: &str) -> String {
+        shorten_paths(self.path_root(), text)
+    }
+}
@@ -0,0 +1,96 @@
+use crate::MultiProgressBar;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phase {
+    pub name: Arc<str>,
+    pub weight: u64,
+}
+
+impl Phase {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            weight: 1,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u64) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+impl From<&str> for Phase {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<(&str, u64)> for Phase {
+    fn from((name, weight): (&str, u64)) -> Self {
+        Self::new(name).with_weight(weight)
+    }
+}
+
+pub struct Phases<'a> {
+    progress_bar: &'a mut MultiProgressBar,
+    phases: Vec<Phase>,
+    // number of next_phase calls, one past the phase count once all are done
+    started: usize,
+    prefix: String,
+}
+
+impl Phases<'_> {
+    pub fn current(&self) -> Option<&Phase> {
+        self.started
+            .checked_sub(1)
+            .and_then(|index| self.phases.get(index))
+    }
+
+    // Completes the current phase and starts the next one. Returns None once
+    // every phase has run.
+    pub fn next_phase(&mut self) -> Option<&Phase> {
+        let next = self.started.min(self.phases.len());
+        let completed: u64 = self.phases[..next].iter().map(|phase| phase.weight).sum();
+        self.progress_bar.set_position(completed);
+        self.started = next + 1;
+        if next == self.phases.len() {
+            return None;
+        }
+
+        let phase = &self.phases[next];
+        self.progress_bar
+            .set_prefix(format!("{} [{}/{}]", self.prefix, next + 1, self.phases.len()).as_str());
+        self.progress_bar.set_message(&phase.name);
+        Some(phase)
+    }
+
+    pub fn finish(self) {
+        let total = self.phases.iter().map(|phase| phase.weight).sum();
+        self.progress_bar.set_position(total);
+        self.progress_bar.set_prefix(self.prefix.as_str());
+    }
+}
+
+impl MultiProgressBar {
+    // Drives the bar through named phases, each filling a share of the bar
+    // proportional to its weight.
+    pub fn phases<Items, Item>(&mut self, phases: Items) -> Phases<'_>
+    where
+        Items: IntoIterator<Item = Item>,
+        Item: Into<Phase>,
+    {
+        let phases: Vec<Phase> = phases.into_iter().map(Into::into).collect();
+        let total = phases.iter().map(|phase| phase.weight).sum();
+        self.set_total(total);
+        self.set_position(0);
+        let prefix = self.prefix_label();
+        Phases {
+            progress_bar: self,
+            phases,
+            started: 0,
+            prefix,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{audit, ExecuteOptions, MultiProgress, MultiProgressBar, Printer, Section};
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use std::process::Stdio;
+use std::sync::{mpsc, Arc};
+
+#[derive(Debug, Clone)]
+pub struct PipelineStage {
+    pub command: Arc<str>,
+    pub options: ExecuteOptions,
+}
+
+// Stages are connected stdout to stdin, like `tar | gzip | split`. The
+// stdout of the last stage is returned when its options ask for it.
+#[derive(Debug, Clone)]
+pub struct ExecutePipeline {
+    pub label: Arc<str>,
+    pub stages: Vec<PipelineStage>,
+}
+
+impl ExecutePipeline {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.into(),
+            stages: Vec::new(),
+        }
+    }
+
+    pub fn stage(mut self, command: &str, options: ExecuteOptions) -> Self {
+        self.stages.push(PipelineStage {
+            command: command.into(),
+            options,
+        });
+        self
+    }
+
+    pub fn get_full_command(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| stage.options.get_full_command(stage.command.as_ref()))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+impl MultiProgressBar {
+    pub fn execute_pipeline(
+        &mut self,
+        pipeline: &ExecutePipeline,
+    ) -> anyhow::Result<Option<String>> {
+        let _operation = self.controls.begin_operation();
+        let last_stage = pipeline
+            .stages
+            .last()
+            .ok_or(format_error!("Pipeline {} has no stages", pipeline.label))?;
+        self.set_message(&pipeline.get_full_command());
+
+        let mut children: Vec<std::process::Child> = Vec::with_capacity(pipeline.stages.len());
+        let mut threads = Vec::with_capacity(pipeline.stages.len() + 1);
+        let mut stderr_receivers = Vec::with_capacity(pipeline.stages.len());
+        let started_at = std::time::SystemTime::now();
+        let stdout_rx =
+            match spawn_stages(pipeline, &mut children, &mut threads, &mut stderr_receivers) {
+                Ok(stdout_rx) => stdout_rx,
+                Err(error) => {
+                    // the stages already running would wait on a pipe nobody reads
+                    stop_stages(&mut children, threads);
+                    self.mark_failed();
+                    return Err(error);
+                }
+            };
+
+        let mut stdout_content = String::new();
+        let mut stderr_contents = vec![String::new(); children.len()];
+        let mut statuses = vec![None; children.len()];
+        let mut drain = |progress: &mut MultiProgressBar, stderr_contents: &mut Vec<String>| {
+            while let Ok(line) = stdout_rx.try_recv() {
+                progress.set_message(line.as_str());
+                if last_stage.options.is_return_stdout {
+                    stdout_content.push_str(line.as_str());
+                    stdout_content.push('\n');
+                }
+            }
+            for (index, stderr_rx) in stderr_receivers.iter().enumerate() {
+                while let Ok(line) = stderr_rx.try_recv() {
+                    progress.set_message(line.as_str());
+                    stderr_contents[index].push_str(line.as_str());
+                    stderr_contents[index].push('\n');
+                }
+            }
+        };
+
+        while statuses.iter().any(Option::is_none) {
+            if self.controls.is_cancel_requested() {
+                stop_stages(&mut children, threads);
+                for (stage, status) in pipeline.stages.iter().zip(statuses.iter()) {
+                    if status.is_none() {
+                        audit::record(
+                            self.audit.as_ref(),
+                            stage.command.as_ref(),
+                            &stage.options,
+                            started_at,
+                            None,
+                        );
+                    }
+                }
+                self.mark_failed();
+                return Err(format_error!("Cancelled: {}", pipeline.label));
+            }
+            for (index, child) in children.iter_mut().enumerate() {
+                if statuses[index].is_none() {
+                    if let Ok(Some(status)) = child.try_wait() {
+                        let stage = &pipeline.stages[index];
+                        audit::record(
+                            self.audit.as_ref(),
+                            stage.command.as_ref(),
+                            &stage.options,
+                            started_at,
+                            status.code(),
+                        );
+                        statuses[index] = Some(status);
+                    }
+                }
+            }
+            drain(self, &mut stderr_contents);
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            self.increment_with_overflow(1);
+        }
+
+        for thread in threads {
+            let _ = thread.join();
+        }
+        drain(self, &mut stderr_contents);
+
+        for (index, (stage, status)) in pipeline.stages.iter().zip(statuses.iter()).enumerate() {
+            let status = status.as_ref().expect("all stages have exited");
+            if !status.success() {
+                self.mark_failed();
+                self.execution_log.lock().unwrap().add_failure(
+                    stage.command.as_ref(),
+                    &stage.options,
+                    status.code(),
+                    stderr_contents[index].as_str(),
+                );
+                return Err(format_error!(
+                    "Pipeline stage {} ({}) failed with exit code {} : {}",
+                    index + 1,
+                    stage.command,
+                    status
+                        .code()
+                        .map(|code| code.to_string())
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    stderr_contents[index]
+                ));
+            }
+        }
+
+        Ok(last_stage
+            .options
+            .is_return_stdout
+            .then_some(stdout_content))
+    }
+}
+
+// Starts every stage with its stdout piped into the next one. Whatever was
+// started is left in `children` and `threads` when a stage fails to start.
+fn spawn_stages(
+    pipeline: &ExecutePipeline,
+    children: &mut Vec<std::process::Child>,
+    threads: &mut Vec<std::thread::JoinHandle<()>>,
+    stderr_receivers: &mut Vec<mpsc::Receiver<String>>,
+) -> anyhow::Result<mpsc::Receiver<String>> {
+    let mut previous_stdout: Option<std::process::ChildStdout> = None;
+    for (index, stage) in pipeline.stages.iter().enumerate() {
+        let stdin = previous_stdout
+            .take()
+            .map(Stdio::from)
+            .unwrap_or_else(Stdio::null);
+        let mut child = stage
+            .options
+            .create_command(stage.command.as_ref())
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context(format_context!(
+                "Failed to spawn pipeline stage {} ({})",
+                index + 1,
+                stage.command
+            ))?;
+        stage.options.notify_started(child.id());
+        let stderr = child.stderr.take();
+        previous_stdout = child.stdout.take();
+        children.push(child);
+
+        let stderr = stderr.ok_or(format_error!("Internal Error: Child has no stderr"))?;
+        let (stderr_thread, stderr_rx) = ExecuteOptions::process_child_output(stderr, None)?;
+        threads.push(stderr_thread);
+        stderr_receivers.push(stderr_rx);
+    }
+
+    let stdout = previous_stdout.ok_or(format_error!("Internal Error: Child has no stdout"))?;
+    let (stdout_thread, stdout_rx) = ExecuteOptions::process_child_output(stdout, None)?;
+    threads.push(stdout_thread);
+    Ok(stdout_rx)
+}
+
+// the readers finish once the stages holding their pipes are gone
+fn stop_stages(children: &mut [std::process::Child], threads: Vec<std::thread::JoinHandle<()>>) {
+    for child in children.iter_mut() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    for thread in threads {
+        let _ = thread.join();
+    }
+}
+
+impl Printer {
+    pub fn execute_pipeline(
+        &mut self,
+        pipeline: &ExecutePipeline,
+    ) -> anyhow::Result<Option<String>> {
+        let section = Section::new(self, pipeline.label.as_ref()).context(format_context!(""))?;
+        let mut multi_progress = MultiProgress::new(section.printer);
+        let mut progress_bar = multi_progress.add_progress("progress", None, None);
+        progress_bar
+            .execute_pipeline(pipeline)
+            .context(format_context!(
+                "while running {}",
+                pipeline.get_full_command()
+            ))
+    }
+}
@@ -0,0 +1,376 @@
+use crate::{
+    format, text::Table, ExecuteOptions, FinishStyle, MultiProgress, MultiProgressBar, Printer,
+    Section,
+};
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use serde::Serialize;
+use std::panic::AssertUnwindSafe;
+use std::{
+    sync::{mpsc, Arc},
+    time::{Duration, Instant},
+};
+
+// Returned by ProcessPool::add and used to declare dependencies. Ids only
+// exist for jobs already in the pool, so the jobs always form a DAG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    // waiting for a dependency to finish
+    Blocked,
+    // ready and waiting for a free worker
+    Queued,
+    Running,
+    Done,
+    Failed,
+    // not run because a dependency failed
+    Skipped,
+    // not started because a cancel was requested
+    Cancelled,
+}
+
+impl JobState {
+    fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Done | JobState::Failed | JobState::Skipped | JobState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PoolJob {
+    name: Arc<str>,
+    command: Arc<str>,
+    options: ExecuteOptions,
+    dependencies: Vec<JobId>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobOutcome {
+    pub name: Arc<str>,
+    pub command: Arc<str>,
+    pub state: JobState,
+    pub dependencies: Vec<usize>,
+    // from the start of the pool
+    pub started: Option<Duration>,
+    pub duration: Option<Duration>,
+    pub stdout: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolReport {
+    pub jobs: Vec<JobOutcome>,
+    pub elapsed: Duration,
+}
+
+impl JobOutcome {
+    fn finished(&self) -> Option<Duration> {
+        Some(self.started? + self.duration?)
+    }
+}
+
+impl PoolReport {
+    pub fn is_success(&self) -> bool {
+        self.jobs.iter().all(|job| job.state == JobState::Done)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &JobOutcome> {
+        self.jobs.iter().filter(|job| job.state == JobState::Failed)
+    }
+
+    // Indexes of the chain of jobs that determined the wall time, first job
+    // first. It ends with the job that finished last and steps back through
+    // whichever dependency finished last, since that one held up the start.
+    pub fn critical_path(&self) -> Vec<usize> {
+        let last = self
+            .jobs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, job)| Some((index, job.finished()?)))
+            .max_by_key(|(_, finished)| *finished)
+            .map(|(index, _)| index);
+        let mut path: Vec<usize> = last.into_iter().collect();
+        while let Some(previous) = path.last().and_then(|index| {
+            self.jobs[*index]
+                .dependencies
+                .iter()
+                .filter_map(|dependency| Some((*dependency, self.jobs[*dependency].finished()?)))
+                .max_by_key(|(_, finished)| *finished)
+                .map(|(dependency, _)| dependency)
+        }) {
+            path.push(previous);
+        }
+        path.reverse();
+        path
+    }
+}
+
+// Runs processes on up to `max_parallel` worker threads. A job starts once
+// every job it depends on is done; when one fails, the jobs that depend on
+// it are skipped and the rest of the pool keeps going.
+#[derive(Debug, Clone)]
+pub struct ProcessPool {
+    jobs: Vec<PoolJob>,
+    max_parallel: usize,
+}
+
+impl ProcessPool {
+    pub fn new(max_parallel: usize) -> Self {
+        Self {
+            jobs: Vec::new(),
+            max_parallel: max_parallel.max(1),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, command: &str, options: ExecuteOptions) -> JobId {
+        self.add_with_dependencies(name, command, options, &[])
+    }
+
+    pub fn add_with_dependencies(
+        &mut self,
+        name: &str,
+        command: &str,
+        options: ExecuteOptions,
+        dependencies: &[JobId],
+    ) -> JobId {
+        self.jobs.push(PoolJob {
+            name: name.into(),
+            command: command.into(),
+            options,
+            dependencies: dependencies.to_vec(),
+        });
+        JobId(self.jobs.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    fn blocking_names(&self, job: &PoolJob, states: &[JobState]) -> String {
+        job.dependencies
+            .iter()
+            .filter(|dependency| states[dependency.0] != JobState::Done)
+            .map(|dependency| self.jobs[dependency.0].name.as_ref())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn execute(&self, multi_progress: &mut MultiProgress) -> anyhow::Result<PoolReport> {
+        let pool_started = Instant::now();
+        let controls = multi_progress.printer.controls();
+        let _operation = controls.begin_operation();
+        multi_progress.enable_jobs_header();
+        let mut states: Vec<JobState> = self
+            .jobs
+            .iter()
+            .map(|job| {
+                if job.dependencies.is_empty() {
+                    JobState::Queued
+                } else {
+                    JobState::Blocked
+                }
+            })
+            .collect();
+        let mut bars: Vec<Option<MultiProgressBar>> = Vec::with_capacity(self.jobs.len());
+        for (job, state) in self.jobs.iter().zip(states.iter()) {
+            let mut bar = multi_progress.add_queued_progress(job.name.as_ref(), None, None);
+            if *state == JobState::Blocked {
+                let reason = format!("waiting for {}", self.blocking_names(job, &states));
+                bar.enter_queue_with(reason.as_str());
+            }
+            bars.push(Some(bar));
+        }
+        let mut outcomes: Vec<JobOutcome> = self
+            .jobs
+            .iter()
+            .map(|job| JobOutcome {
+                name: job.name.clone(),
+                command: job.command.clone(),
+                state: JobState::Blocked,
+                dependencies: job
+                    .dependencies
+                    .iter()
+                    .map(|dependency| dependency.0)
+                    .collect(),
+                started: None,
+                duration: None,
+                stdout: None,
+                error: None,
+            })
+            .collect();
+
+        type Finished = (usize, MultiProgressBar, anyhow::Result<Option<String>>);
+        let (sender, receiver) = mpsc::channel::<Finished>();
+        let mut running = 0;
+        loop {
+            for index in 0..self.jobs.len() {
+                if running >= self.max_parallel {
+                    break;
+                }
+                if states[index] != JobState::Queued {
+                    continue;
+                }
+                // pausing holds back new jobs, cancelling stops starting them
+                controls.wait_while_paused();
+                if controls.is_cancel_requested() {
+                    break;
+                }
+                let mut bar = bars[index]
+                    .take()
+                    .ok_or(format_error!("Internal Error: job {index} has no bar"))?;
+                let job = self.jobs[index].clone();
+                let sender = sender.clone();
+                states[index] = JobState::Running;
+                outcomes[index].started = Some(pool_started.elapsed());
+                running += 1;
+                std::thread::spawn(move || {
+                    bar.start();
+                    // a panicking job still reports, otherwise recv waits forever
+                    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                        bar.execute_process(job.command.as_ref(), job.options)
+                    }))
+                    .unwrap_or_else(|_| Err(format_error!("{} panicked", job.name)));
+                    let _ = sender.send((index, bar, result));
+                });
+            }
+            if running == 0 {
+                break;
+            }
+
+            let (index, mut bar, result) = receiver
+                .recv()
+                .context(format_context!("Internal Error: a pool worker exited"))?;
+            running -= 1;
+            let outcome = &mut outcomes[index];
+            outcome.duration = outcome
+                .started
+                .map(|started| pool_started.elapsed() - started);
+            match result {
+                Ok(stdout) => {
+                    states[index] = JobState::Done;
+                    outcome.stdout = stdout;
+                    bar.finish_with(FinishStyle::FinishAtTotal);
+                }
+                Err(error) => {
+                    states[index] = JobState::Failed;
+                    outcome.error = Some(format!("{error:#}"));
+                }
+            }
+            bars[index] = Some(bar);
+
+            // unblocks or skips the jobs that were waiting on this one. Skips
+            // cascade, so this repeats until nothing changes.
+            let mut is_changed = true;
+            while is_changed {
+                is_changed = false;
+                for (waiting, job) in self.jobs.iter().enumerate() {
+                    if states[waiting] != JobState::Blocked {
+                        continue;
+                    }
+                    let failed = job.dependencies.iter().find(|dependency| {
+                        matches!(states[dependency.0], JobState::Failed | JobState::Skipped)
+                    });
+                    let is_ready = job
+                        .dependencies
+                        .iter()
+                        .all(|dependency| states[dependency.0] == JobState::Done);
+                    let Some(bar) = bars[waiting].as_mut() else {
+                        continue;
+                    };
+                    if let Some(failed) = failed {
+                        states[waiting] = JobState::Skipped;
+                        is_changed = true;
+                        let message = format!("skipped, {} failed", self.jobs[failed.0].name);
+                        bar.enter_queue_with(message.as_str());
+                        bar.finish_with(FinishStyle::Keep);
+                    } else if is_ready {
+                        states[waiting] = JobState::Queued;
+                        is_changed = true;
+                        bar.enter_queue();
+                    } else {
+                        let reason = format!("waiting for {}", self.blocking_names(job, &states));
+                        bar.enter_queue_with(reason.as_str());
+                    }
+                }
+            }
+        }
+
+        for (state, bar) in states.iter_mut().zip(bars.iter_mut()) {
+            if matches!(state, JobState::Queued | JobState::Blocked) {
+                *state = JobState::Cancelled;
+                if let Some(bar) = bar.as_mut() {
+                    bar.enter_queue_with("cancelled");
+                    bar.finish_with(FinishStyle::Keep);
+                }
+            }
+        }
+        for (outcome, state) in outcomes.iter_mut().zip(states.iter()) {
+            // a job can only stay unfinished if the loops above have a bug
+            debug_assert!(state.is_finished());
+            outcome.state = *state;
+        }
+        Ok(PoolReport {
+            jobs: outcomes,
+            elapsed: pool_started.elapsed(),
+        })
+    }
+}
+
+impl Printer {
+    pub fn execute_pool(&mut self, name: &str, pool: &ProcessPool) -> anyhow::Result<PoolReport> {
+        let section = Section::new(self, name).context(format_context!(""))?;
+        let mut multi_progress = MultiProgress::new(section.printer);
+        let report = pool
+            .execute(&mut multi_progress)
+            .context(format_context!("while running {name}"))?;
+        drop(multi_progress);
+        section
+            .printer
+            .critical_path(&report)
+            .context(format_context!(""))?;
+        Ok(report)
+    }
+
+    // Lists the critical path of a pool run with when each job started and
+    // how long it took. Speeding up any other job would not finish sooner.
+    pub fn critical_path(&mut self, report: &PoolReport) -> anyhow::Result<()> {
+        let path = report.critical_path();
+        if path.len() < 2 {
+            return Ok(());
+        }
+        let mut table = Table::new(&["job", "started", "duration"]);
+        let mut busy = Duration::ZERO;
+        for index in path.iter() {
+            let job = &report.jobs[*index];
+            let duration = job.duration.unwrap_or_default();
+            busy += duration;
+            table.add_row(&[
+                job.name.to_string(),
+                format!(
+                    "+{}",
+                    format::humanize_duration(job.started.unwrap_or_default())
+                ),
+                format::humanize_duration(duration),
+            ]);
+        }
+        self.info(
+            "critical path",
+            &format!(
+                "{} of {} wall time",
+                format::humanize_duration(busy),
+                format::humanize_duration(report.elapsed)
+            ),
+        )
+        .context(format_context!(""))?;
+        self.table(&table).context(format_context!(""))
+    }
+}
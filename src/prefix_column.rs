@@ -0,0 +1,106 @@
+use crate::format;
+use indicatif::{ProgressBar, WeakProgressBar};
+use std::sync::{Arc, Mutex};
+
+// prefixes longer than this overflow rather than pushing every bar right
+pub(crate) const PREFIX_MAX_WIDTH: usize = 24;
+
+// The prefix column shared by the bars of a MultiProgress. Each prefix is
+// padded to the widest one registered so far, capped at PREFIX_MAX_WIDTH, so
+// the bars line up. The column only grows to keep bars from jumping around.
+#[derive(Default)]
+pub(crate) struct PrefixColumn {
+    state: Mutex<ColumnState>,
+}
+
+#[derive(Default)]
+struct ColumnState {
+    width: usize,
+    fixed_width: Option<usize>,
+    labels: Vec<(WeakProgressBar, String)>,
+}
+
+impl ColumnState {
+    fn width(&self) -> usize {
+        self.fixed_width.unwrap_or(self.width)
+    }
+
+    fn widen(&mut self, label: &str) -> bool {
+        let width = format::visible_width(label).min(PREFIX_MAX_WIDTH);
+        if width > self.width {
+            self.width = width;
+            self.fixed_width.is_none()
+        } else {
+            false
+        }
+    }
+
+    fn apply(&self, progress: &ProgressBar, label: &str) {
+        let padding = self.width().saturating_sub(format::visible_width(label));
+        progress.set_prefix(format!("{label}{:padding$}", ""));
+    }
+
+    // finished bars keep their entry so slot indexes stay valid
+    fn apply_all(&self) {
+        for (progress, label) in self.labels.iter() {
+            if let Some(progress) = progress.upgrade() {
+                self.apply(&progress, label);
+            }
+        }
+    }
+}
+
+impl PrefixColumn {
+    // `None` goes back to sizing from the registered prefixes
+    pub(crate) fn set_width(&self, width: Option<usize>) {
+        let mut state = self.state.lock().unwrap();
+        state.fixed_width = width;
+        state.apply_all();
+    }
+
+    pub(crate) fn register(self: &Arc<Self>, progress: &ProgressBar, label: &str) -> PrefixSlot {
+        let mut state = self.state.lock().unwrap();
+        let index = state.labels.len();
+        state.labels.push((progress.downgrade(), label.to_string()));
+        if state.widen(label) {
+            state.apply_all();
+        } else {
+            state.apply(progress, label);
+        }
+        PrefixSlot {
+            column: self.clone(),
+            progress: progress.downgrade(),
+            index,
+        }
+    }
+}
+
+// A bar's place in the column, used to change its prefix later
+pub(crate) struct PrefixSlot {
+    column: Arc<PrefixColumn>,
+    progress: WeakProgressBar,
+    index: usize,
+}
+
+impl PrefixSlot {
+    pub(crate) fn label(&self) -> String {
+        let state = self.column.state.lock().unwrap();
+        state
+            .labels
+            .get(self.index)
+            .map(|(_, label)| label.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn relabel(&self, label: &str) {
+        let mut state = self.column.state.lock().unwrap();
+        if let Some(entry) = state.labels.get_mut(self.index) {
+            entry.1 = label.to_string();
+        }
+        if state.widen(label) {
+            state.apply_all();
+        } else if let Some(progress) = self.progress.upgrade() {
+            state.apply(&progress, label);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use crate::{
+    capabilities::{Capabilities, Glyphs},
+    format,
+    theme::{ColorDepth, Theme, ThemeColor},
+    Level,
+};
+use indicatif::{ProgressState, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Display, Serialize, Deserialize)]
+pub enum Preset {
+    Minimal,
+    #[default]
+    Classic,
+    Fancy,
+    Ascii,
+}
+
+impl Preset {
+    fn template(&self, is_determinate: bool) -> &'static str {
+        match (self, is_determinate) {
+            (Preset::Minimal, true) => "{prefix} {pos}/{len} {msg}",
+            (Preset::Minimal, false) => "{prefix} {msg}",
+            (Preset::Classic, _) => "{elapsed_precise}|{bar}|{prefix} {msg}",
+            (Preset::Fancy, _) => "{elapsed_precise} {bar} {prefix} {msg}",
+            (Preset::Ascii, _) => "{elapsed_precise} [{bar}] {prefix} {msg}",
+        }
+    }
+
+    fn progress_chars(&self, is_determinate: bool) -> &'static str {
+        match (self, is_determinate) {
+            (Preset::Minimal, _) => "#>-",
+            (Preset::Classic, true) => "#>-",
+            (Preset::Classic, false) => "*>-",
+            (Preset::Fancy, true) => "█▉▊▋▌▍▎▏ ",
+            (Preset::Fancy, false) => "█▓▒░ ",
+            (Preset::Ascii, true) => "=> ",
+            (Preset::Ascii, false) => "*  ",
+        }
+    }
+
+    // the block characters of the fancy bar need a unicode font
+    pub fn supported(&self, capabilities: &Capabilities) -> Preset {
+        match self {
+            Preset::Fancy if !capabilities.is_unicode => Preset::Ascii,
+            preset => *preset,
+        }
+    }
+
+    pub fn style(&self, is_determinate: bool) -> ProgressStyle {
+        self.themed_style(is_determinate, &Theme::default())
+    }
+
+    pub fn themed_style(&self, is_determinate: bool, theme: &Theme) -> ProgressStyle {
+        self.layout_style(is_determinate, theme, Layout::Full, ColorDepth::default())
+    }
+
+    pub(crate) fn layout_style(
+        &self,
+        is_determinate: bool,
+        theme: &Theme,
+        layout: Layout,
+        depth: ColorDepth,
+    ) -> ProgressStyle {
+        let is_gradient = theme.is_gradient();
+        let template = match self {
+            Preset::Classic | Preset::Fancy if is_gradient => self
+                .template(is_determinate)
+                .replace("{bar}", GRADIENT_BAR_PLACEHOLDER),
+            Preset::Classic | Preset::Fancy => self
+                .template(is_determinate)
+                .replace("{bar}", theme.bar_placeholder().as_str()),
+            Preset::Minimal | Preset::Ascii => self.template(is_determinate).to_string(),
+        };
+        let template = layout.apply(template.as_str());
+        let progress_chars = theme
+            .progress_chars
+            .as_deref()
+            .filter(|chars| chars.chars().count() >= 2)
+            .unwrap_or(self.progress_chars(is_determinate));
+        let style = ProgressStyle::with_template(template.as_str())
+            .or_else(|_| ProgressStyle::with_template(self.template(is_determinate)))
+            .unwrap()
+            .progress_chars(progress_chars);
+        if !is_gradient {
+            return style;
+        }
+        let width = match layout {
+            Layout::BarOnly(width) => width,
+            _ => DEFAULT_BAR_WIDTH,
+        };
+        let bar = GradientBar {
+            stops: theme.progress_gradient.clone(),
+            background: theme.progress_background,
+            chars: progress_chars.chars().collect(),
+            width,
+            depth,
+        };
+        style.with_key(
+            "gradient_bar",
+            move |state: &ProgressState, output: &mut dyn std::fmt::Write| {
+                let _ = bar.write(state.fraction(), output);
+            },
+        )
+    }
+
+    // keeps the preset layout but paints the bar and message with the error color
+    pub(crate) fn failed_style(&self, is_determinate: bool, theme: &Theme) -> ProgressStyle {
+        let mark = if *self == Preset::Ascii {
+            Glyphs::ASCII.failed
+        } else {
+            theme.glyphs.failed
+        };
+        let (bar, message) = match theme.level_color(Level::Error).template_name() {
+            Some(color) => (
+                format!("{{bar:.{color}}}"),
+                format!("{mark} {{msg:.{color}}}"),
+            ),
+            None => ("{bar}".to_string(), format!("{mark} {{msg}}")),
+        };
+        let template = self
+            .template(is_determinate)
+            .replace("{bar}", bar.as_str())
+            .replace("{msg}", message.as_str());
+        ProgressStyle::with_template(template.as_str())
+            .unwrap_or_else(|_| self.themed_style(is_determinate, theme))
+            .progress_chars(self.progress_chars(is_determinate))
+    }
+
+    // columns taken by everything except the prefix and the message
+    pub(crate) fn width(&self, is_determinate: bool, length: Option<u64>) -> usize {
+        template_width(self.template(is_determinate), length)
+    }
+
+    pub(crate) fn layout(
+        &self,
+        is_determinate: bool,
+        length: Option<u64>,
+        available: usize,
+        prefix_width: usize,
+    ) -> Layout {
+        let width = self.width(is_determinate, length);
+        if available >= width + prefix_width + MIN_MESSAGE_WIDTH {
+            Layout::Full
+        } else if available >= width + prefix_width {
+            Layout::NoMessage
+        } else if available >= width {
+            Layout::NoPrefix
+        } else if available >= MIN_BAR_WIDTH {
+            Layout::BarOnly(available)
+        } else {
+            Layout::Spinner
+        }
+    }
+}
+
+const MIN_MESSAGE_WIDTH: usize = 8;
+const MIN_BAR_WIDTH: usize = 4;
+
+// Degradation ladder for lines too narrow for the full template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Layout {
+    #[default]
+    Full,
+    NoMessage,
+    NoPrefix,
+    BarOnly(usize),
+    Spinner,
+}
+
+impl Layout {
+    fn apply(&self, template: &str) -> String {
+        let without_message = || template.replace(" {msg}", "").replace("{msg}", "");
+        match self {
+            Layout::Full => template.to_string(),
+            Layout::NoMessage => without_message(),
+            Layout::NoPrefix => without_message()
+                .replace("{prefix} ", "")
+                .replace(" {prefix}", "")
+                .replace("{prefix}", ""),
+            // the gradient key is drawn at the layout width directly
+            Layout::BarOnly(_) if template.contains(GRADIENT_BAR_PLACEHOLDER) => {
+                GRADIENT_BAR_PLACEHOLDER.to_string()
+            }
+            Layout::BarOnly(width) => {
+                let bar = template.find("{bar").and_then(|start| {
+                    let end = start + template[start..].find('}')?;
+                    Some(&template[start + 4..end])
+                });
+                match bar {
+                    // keeps the theme colors: ":.red/blue" becomes ":12.red/blue"
+                    Some(format) => match format.strip_prefix(':') {
+                        Some(format) => format!("{{bar:{width}{format}}}"),
+                        None => format!("{{bar:{width}}}"),
+                    },
+                    None => "{spinner}".to_string(),
+                }
+            }
+            Layout::Spinner => "{spinner}".to_string(),
+        }
+    }
+}
+
+// indicatif draws bars this wide unless the template requests a width
+const DEFAULT_BAR_WIDTH: usize = 20;
+
+// indicatif colors a bar with a single style, so gradients are drawn by a
+// custom template key
+const GRADIENT_BAR_PLACEHOLDER: &str = "{gradient_bar}";
+
+// Each filled cell takes the gradient color at its position along the whole
+// bar, so the colors stay put as the bar grows.
+#[derive(Clone)]
+struct GradientBar {
+    stops: Vec<ThemeColor>,
+    background: ThemeColor,
+    chars: Vec<char>,
+    width: usize,
+    depth: ColorDepth,
+}
+
+impl GradientBar {
+    fn write(&self, fraction: f32, output: &mut dyn std::fmt::Write) -> std::fmt::Result {
+        let (Some(fill), Some(empty)) = (self.chars.first(), self.chars.last()) else {
+            return Ok(());
+        };
+        let filled = ((fraction.clamp(0.0, 1.0) * self.width as f32) as usize).min(self.width);
+        let last = self.width.saturating_sub(1).max(1) as f32;
+        let fill = fill.to_string();
+        for cell in 0..filled {
+            let color = ThemeColor::blend(&self.stops, cell as f32 / last).downgrade(self.depth);
+            write!(output, "{}", color.paint(fill.as_str()))?;
+        }
+        let rest = empty.to_string().repeat(self.width - filled);
+        write!(output, "{}", self.background.paint(rest.as_str()))
+    }
+}
+
+// Measures the columns a template renders, leaving out the prefix and message
+// which are sized to fit whatever remains of the line.
+pub(crate) fn template_width(template: &str, length: Option<u64>) -> usize {
+    let count_width = length.map(|length| length.to_string().len()).unwrap_or(3);
+    let mut width = 0;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        width += format::visible_width(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            rest = &rest[start..];
+            break;
+        };
+        let placeholder = &rest[start + 1..start + end];
+        let (key, format) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+        let requested = format
+            .split('.')
+            .next()
+            .map(|alignment| alignment.trim_start_matches(['<', '^', '>', '!']))
+            .and_then(|requested| requested.parse::<usize>().ok());
+        width += match key {
+            "prefix" | "msg" | "wide_msg" => 0,
+            "bar" | "wide_bar" | "gradient_bar" => requested.unwrap_or(DEFAULT_BAR_WIDTH),
+            "elapsed_precise" | "eta_precise" | "duration_precise" => 8,
+            "pos" | "len" => count_width,
+            "spinner" => 1,
+            "bytes" | "total_bytes" | "binary_bytes" | "binary_total_bytes" => 10,
+            "bytes_per_sec" | "binary_bytes_per_sec" => 12,
+            _ => requested.unwrap_or(0),
+        };
+        rest = &rest[start + end + 1..];
+    }
+    width + format::visible_width(rest)
+}
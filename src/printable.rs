@@ -0,0 +1,47 @@
+use crate::Printer;
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use serde::Serialize;
+
+pub type Result = anyhow::Result<()>;
+
+// Types that render themselves, with tables, colors or layout that flattening
+// through serde_json can't express. With the `derive` feature
+// #[derive(Printable)] prints each field as `name: value`.
+pub trait Printable {
+    fn print(&self, printer: &mut Printer) -> Result;
+}
+
+impl<Type: Printable + ?Sized> Printable for &Type {
+    fn print(&self, printer: &mut Printer) -> Result {
+        (**self).print(printer)
+    }
+}
+
+impl Printer {
+    pub fn print<Type: Printable + ?Sized>(&mut self, value: &Type) -> Result {
+        value.print(self)
+    }
+
+    // `name: value` as objects are printed, whatever the level
+    pub fn print_field<Type: Serialize>(&mut self, name: &str, value: &Type) -> Result {
+        self.object(name, value)
+    }
+
+    // A Printable value indented under `name`
+    pub fn print_nested<Type: Printable + ?Sized>(&mut self, name: &str, value: &Type) -> Result {
+        self.write(
+            format!(
+                "{}{}:\n",
+                self.indentation(),
+                name.if_supports_color(Stdout, |text| text.bold())
+            )
+            .as_str(),
+        )?;
+        let _indent = self.indented();
+        value
+            .print(self)
+            .context(format_context!("while printing {name}"))
+    }
+}
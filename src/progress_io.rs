@@ -0,0 +1,149 @@
+use crate::{MultiProgress, MultiProgressBar, Printer};
+use std::io::{Read, Write};
+
+pub struct ProgressReader<'a, Inner: Read> {
+    progress_bar: &'a mut MultiProgressBar,
+    inner: Inner,
+}
+
+impl<Inner: Read> ProgressReader<'_, Inner> {
+    pub fn into_inner(self) -> Inner {
+        self.inner
+    }
+}
+
+impl<Inner: Read> Read for ProgressReader<'_, Inner> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buffer)?;
+        self.progress_bar.increment(count as u64);
+        Ok(count)
+    }
+}
+
+pub struct ProgressWriter<'a, Inner: Write> {
+    progress_bar: &'a mut MultiProgressBar,
+    inner: Inner,
+}
+
+impl<Inner: Write> ProgressWriter<'_, Inner> {
+    pub fn into_inner(self) -> Inner {
+        self.inner
+    }
+}
+
+impl<Inner: Write> Write for ProgressWriter<'_, Inner> {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let count = self.inner.write(buffer)?;
+        self.progress_bar.increment(count as u64);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl MultiProgressBar {
+    // Advances the bar by the number of bytes read. Set the total to the
+    // expected size (e.g. the archive length) for an accurate bar.
+    pub fn wrap_read<Inner: Read>(&mut self, inner: Inner) -> ProgressReader<'_, Inner> {
+        ProgressReader {
+            progress_bar: self,
+            inner,
+        }
+    }
+
+    pub fn wrap_write<Inner: Write>(&mut self, inner: Inner) -> ProgressWriter<'_, Inner> {
+        ProgressWriter {
+            progress_bar: self,
+            inner,
+        }
+    }
+}
+
+pub struct ProgressIter<'a, Inner: Iterator> {
+    progress_bar: &'a mut MultiProgressBar,
+    inner: Inner,
+}
+
+impl<Inner: Iterator> Iterator for ProgressIter<'_, Inner> {
+    type Item = Inner::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next();
+        if item.is_some() {
+            self.progress_bar.increment(1);
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+// Owns the MultiProgress so `printer.progress_iter()` can be used directly
+// in a for loop. The bar is declared first so it finishes before the
+// MultiProgress is dropped.
+pub struct PrinterProgressIter<'a, Inner: Iterator> {
+    progress_bar: MultiProgressBar,
+    _multi_progress: MultiProgress<'a>,
+    inner: Inner,
+}
+
+impl<Inner: Iterator> Iterator for PrinterProgressIter<'_, Inner> {
+    type Item = Inner::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next();
+        if item.is_some() {
+            self.progress_bar.increment(1);
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+fn exact_length<Inner: Iterator>(inner: &Inner) -> Option<u64> {
+    match inner.size_hint() {
+        (lower, Some(upper)) if lower == upper => Some(upper as u64),
+        _ => None,
+    }
+}
+
+impl MultiProgressBar {
+    pub fn wrap_iter<Inner: IntoIterator>(
+        &mut self,
+        inner: Inner,
+    ) -> ProgressIter<'_, Inner::IntoIter> {
+        let inner = inner.into_iter();
+        if let Some(length) = exact_length(&inner) {
+            self.set_total(length);
+        }
+        ProgressIter {
+            progress_bar: self,
+            inner,
+        }
+    }
+}
+
+impl Printer {
+    pub fn progress_iter<Inner: IntoIterator>(
+        &mut self,
+        label: &str,
+        inner: Inner,
+    ) -> PrinterProgressIter<'_, Inner::IntoIter> {
+        let inner = inner.into_iter();
+        let length = exact_length(&inner);
+        let mut multi_progress = MultiProgress::new(self);
+        let progress_bar = multi_progress.add_progress(label, length, Some("done"));
+        PrinterProgressIter {
+            progress_bar,
+            _multi_progress: multi_progress,
+            inner,
+        }
+    }
+}
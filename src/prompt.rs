@@ -0,0 +1,436 @@
+#[cfg(feature = "progress")]
+use crate::RenderMode;
+use crate::{suggest, Printer};
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+#[cfg(feature = "progress")]
+use owo_colors::{OwoColorize, Stream::Stderr};
+#[cfg(feature = "progress")]
+use std::io::IsTerminal;
+
+// matches listed under the query while typing
+const VISIBLE_MATCHES: usize = 8;
+
+// shortest password answer registered as a secret
+const MIN_SECRET_LENGTH: usize = 6;
+
+// Lower is better: where the match starts plus the gaps between matched
+// characters. None unless every query character appears in order.
+fn subsequence_score(query: &str, candidate: &str) -> Option<usize> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    for query_char in query.to_lowercase().chars() {
+        let offset = candidate[next..]
+            .iter()
+            .position(|candidate_char| *candidate_char == query_char)?;
+        score += offset;
+        next += offset + 1;
+    }
+    Some(score)
+}
+
+// Indices of the candidates matching `query`, best first. Type-ahead matches
+// come before misspellings found by the suggest module.
+pub(crate) fn fuzzy_filter(query: &str, candidates: &[&str]) -> Vec<usize> {
+    if query.is_empty() {
+        return (0..candidates.len()).collect();
+    }
+    let mut typed: Vec<(usize, usize)> = Vec::new();
+    let mut misspelled: Vec<(usize, usize)> = Vec::new();
+    for (index, candidate) in candidates.iter().enumerate() {
+        if let Some(score) = subsequence_score(query, candidate) {
+            typed.push((score, index));
+        } else if let Some(distance) = suggest::close_distance(query, candidate) {
+            misspelled.push((distance, index));
+        }
+    }
+    typed.sort();
+    misspelled.sort();
+    typed
+        .into_iter()
+        .chain(misspelled)
+        .map(|(_, index)| index)
+        .collect()
+}
+
+// Prompts need a person at a terminal; otherwise the answer is read as a line
+// from stdin so scripts can pipe it in. Reading keys needs the progress
+// feature, without it answers are always read as lines.
+#[cfg(feature = "progress")]
+pub(crate) fn is_interactive(printer: &Printer) -> bool {
+    printer.render_mode == RenderMode::Interactive
+        && std::io::stdin().is_terminal()
+        && std::io::stderr().is_terminal()
+}
+
+pub(crate) fn read_answer_line(prompt: &str) -> anyhow::Result<String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .context(format_context!("while reading the answer to {prompt}"))?;
+    Ok(line.trim_end_matches(['\n', '\r']).to_string())
+}
+
+#[cfg(feature = "progress")]
+fn render_matches(
+    prompt: &str,
+    query: &str,
+    candidates: &[&str],
+    matches: &[usize],
+    cursor: usize,
+    marker: &str,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{} {prompt}: {query}",
+        "?".if_supports_color(Stderr, |text| text.bold())
+    )];
+    let start = cursor.saturating_sub(VISIBLE_MATCHES - 1);
+    for (position, index) in matches.iter().enumerate().skip(start).take(VISIBLE_MATCHES) {
+        let candidate = candidates[*index];
+        if position == cursor {
+            lines.push(format!(
+                "{marker} {}",
+                candidate.if_supports_color(Stderr, |text| text.bold())
+            ));
+        } else {
+            lines.push(format!("  {candidate}"));
+        }
+    }
+    if matches.is_empty() {
+        lines.push(format!(
+            "  {}",
+            "no matches".if_supports_color(Stderr, |text| text.dimmed())
+        ));
+    } else if matches.len() > VISIBLE_MATCHES {
+        lines.push(format!(
+            "  {}",
+            format!("{} matches", matches.len()).if_supports_color(Stderr, |text| text.dimmed())
+        ));
+    }
+    lines
+}
+
+#[cfg(feature = "progress")]
+fn render_checkboxes(
+    prompt: &str,
+    items: &[&str],
+    selected: &[bool],
+    cursor: usize,
+    marker: &str,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{} {prompt}: {}",
+        "?".if_supports_color(Stderr, |text| text.bold()),
+        "space toggles, a toggles all, enter confirms"
+            .if_supports_color(Stderr, |text| text.dimmed())
+    )];
+    let start = cursor.saturating_sub(VISIBLE_MATCHES - 1);
+    for (index, item) in items.iter().enumerate().skip(start).take(VISIBLE_MATCHES) {
+        let checkbox = if selected[index] { "[x]" } else { "[ ]" };
+        if index == cursor {
+            lines.push(format!(
+                "{marker} {checkbox} {}",
+                item.if_supports_color(Stderr, |text| text.bold())
+            ));
+        } else {
+            lines.push(format!("  {checkbox} {item}"));
+        }
+    }
+    if items.len() > VISIBLE_MATCHES {
+        lines.push(format!(
+            "  {}",
+            format!("{}/{}", cursor + 1, items.len())
+                .if_supports_color(Stderr, |text| text.dimmed())
+        ));
+    }
+    lines
+}
+
+// Piped answers list item names separated by commas. An empty line keeps the
+// preselected items.
+fn multi_select_line(prompt: &str, items: &[&str], selected: &mut [bool]) -> anyhow::Result<()> {
+    let answer = read_answer_line(prompt)?;
+    if answer.trim().is_empty() {
+        return Ok(());
+    }
+    selected.fill(false);
+    for name in answer
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        match items
+            .iter()
+            .position(|item| item.eq_ignore_ascii_case(name))
+        {
+            Some(index) => selected[index] = true,
+            None => {
+                let suggestions = suggest::suggest(name, items);
+                return Err(match suggestions.first() {
+                    Some(suggestion) => format_error!(
+                        "{prompt}: no item named `{name}`, did you mean `{suggestion}`?"
+                    ),
+                    None => format_error!("{prompt}: no item named `{name}`"),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "progress")]
+impl Printer {
+    fn multi_select_interactive(
+        &mut self,
+        prompt: &str,
+        items: &[&str],
+        selected: &mut [bool],
+    ) -> anyhow::Result<bool> {
+        let term = console::Term::stderr();
+        let marker = self.theme.progress_bar.paint(">");
+        let mut cursor = 0;
+        let mut drawn = 0;
+        loop {
+            let lines = render_checkboxes(prompt, items, selected, cursor, marker.as_str());
+            term.clear_last_lines(drawn).context(format_context!(""))?;
+            for line in lines.iter() {
+                term.write_line(line).context(format_context!(""))?;
+            }
+            drawn = lines.len();
+
+            match term.read_key().context(format_context!(""))? {
+                console::Key::ArrowUp => cursor = cursor.saturating_sub(1),
+                console::Key::ArrowDown if cursor + 1 < items.len() => cursor += 1,
+                console::Key::Char(' ') => selected[cursor] = !selected[cursor],
+                console::Key::Char('a') => {
+                    let is_all = selected.iter().all(|is_selected| *is_selected);
+                    selected.fill(!is_all);
+                }
+                console::Key::Enter => {
+                    term.clear_last_lines(drawn).context(format_context!(""))?;
+                    return Ok(true);
+                }
+                console::Key::Escape | console::Key::CtrlC => {
+                    term.clear_last_lines(drawn).context(format_context!(""))?;
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn select_fuzzy_interactive(
+        &mut self,
+        prompt: &str,
+        candidates: &[&str],
+    ) -> anyhow::Result<Option<usize>> {
+        let term = console::Term::stderr();
+        let marker = self.theme.progress_bar.paint(">");
+        let mut query = String::new();
+        let mut cursor = 0;
+        let mut drawn = 0;
+        loop {
+            let matches = fuzzy_filter(query.as_str(), candidates);
+            cursor = cursor.min(matches.len().saturating_sub(1));
+            let lines = render_matches(
+                prompt,
+                query.as_str(),
+                candidates,
+                &matches,
+                cursor,
+                marker.as_str(),
+            );
+            term.clear_last_lines(drawn).context(format_context!(""))?;
+            for line in lines.iter() {
+                term.write_line(line).context(format_context!(""))?;
+            }
+            drawn = lines.len();
+
+            let key = term.read_key().context(format_context!(""))?;
+            match key {
+                console::Key::Char(character) if !character.is_control() => {
+                    query.push(character);
+                    cursor = 0;
+                }
+                console::Key::Backspace => {
+                    query.pop();
+                    cursor = 0;
+                }
+                console::Key::ArrowUp => cursor = cursor.saturating_sub(1),
+                console::Key::ArrowDown if cursor + 1 < matches.len() => cursor += 1,
+                console::Key::Tab => {
+                    if let Some(index) = matches.get(cursor) {
+                        query = candidates[*index].to_string();
+                    }
+                }
+                console::Key::Enter if !matches.is_empty() => {
+                    term.clear_last_lines(drawn).context(format_context!(""))?;
+                    return Ok(Some(matches[cursor]));
+                }
+                console::Key::Escape | console::Key::CtrlC => {
+                    term.clear_last_lines(drawn).context(format_context!(""))?;
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn password_interactive(&mut self, prompt: &str) -> anyhow::Result<String> {
+        let term = console::Term::stderr();
+        term.write_str(
+            format!(
+                "{} {prompt}: ",
+                "?".if_supports_color(Stderr, |text| text.bold())
+            )
+            .as_str(),
+        )
+        .context(format_context!(""))?;
+        let value = term
+            .read_secure_line()
+            .context(format_context!("while reading the answer to {prompt}"))?;
+        term.clear_last_lines(1).context(format_context!(""))?;
+        Ok(value)
+    }
+}
+
+impl Printer {
+    // Lets the user tick any number of `items`, starting with `preselected`.
+    // Returns the chosen indices in item order.
+    pub fn multi_select<Item: AsRef<str>>(
+        &mut self,
+        prompt: &str,
+        items: &[Item],
+        preselected: &[usize],
+    ) -> anyhow::Result<Vec<usize>> {
+        let items: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+        let mut selected = vec![false; items.len()];
+        for index in preselected {
+            let is_selected = selected.get_mut(*index).ok_or_else(|| {
+                format_error!("{prompt}: preselected item {index} is out of range")
+            })?;
+            *is_selected = true;
+        }
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+        #[cfg(feature = "progress")]
+        let is_confirmed = if is_interactive(self) {
+            self.multi_select_interactive(prompt, &items, &mut selected)
+                .context(format_context!(""))?
+        } else {
+            multi_select_line(prompt, &items, &mut selected).context(format_context!(""))?;
+            true
+        };
+        #[cfg(not(feature = "progress"))]
+        let is_confirmed = {
+            multi_select_line(prompt, &items, &mut selected).context(format_context!(""))?;
+            true
+        };
+        if !is_confirmed {
+            return Err(format_error!("{prompt}: cancelled"));
+        }
+        let chosen: Vec<usize> = (0..items.len()).filter(|index| selected[*index]).collect();
+        let names: Vec<&str> = chosen.iter().map(|index| items[*index]).collect();
+        self.write(
+            format!(
+                "{}{} {prompt}: {}\n",
+                self.indentation(),
+                self.theme.success.paint(self.theme.glyphs.done),
+                names.join(", ")
+            )
+            .as_str(),
+        )?;
+        Ok(chosen)
+    }
+}
+
+// Piped answers must name one candidate: exactly, or as the only match
+fn select_fuzzy_line(prompt: &str, candidates: &[&str]) -> anyhow::Result<usize> {
+    let answer = read_answer_line(prompt)?;
+    let answer = answer.trim();
+    if let Some(index) = candidates
+        .iter()
+        .position(|candidate| candidate.eq_ignore_ascii_case(answer))
+    {
+        return Ok(index);
+    }
+    let matches = fuzzy_filter(answer, candidates);
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(format_error!("{prompt}: `{answer}` matches nothing")),
+        _ => {
+            let names: Vec<&str> = matches
+                .iter()
+                .take(VISIBLE_MATCHES)
+                .map(|index| candidates[*index])
+                .collect();
+            Err(format_error!(
+                "{prompt}: `{answer}` is ambiguous, did you mean one of {}?",
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+impl Printer {
+    // Picks one of `candidates` by typing part of it. Typos still match close
+    // names. Returns the index of the chosen candidate.
+    pub fn select_fuzzy<Candidate: AsRef<str>>(
+        &mut self,
+        prompt: &str,
+        candidates: &[Candidate],
+    ) -> anyhow::Result<usize> {
+        let candidates: Vec<&str> = candidates.iter().map(AsRef::as_ref).collect();
+        if candidates.is_empty() {
+            return Err(format_error!("{prompt}: nothing to select from"));
+        }
+        #[cfg(feature = "progress")]
+        let index = if is_interactive(self) {
+            self.select_fuzzy_interactive(prompt, &candidates)
+                .context(format_context!(""))?
+                .ok_or_else(|| format_error!("{prompt}: cancelled"))?
+        } else {
+            select_fuzzy_line(prompt, &candidates).context(format_context!(""))?
+        };
+        #[cfg(not(feature = "progress"))]
+        let index = select_fuzzy_line(prompt, &candidates).context(format_context!(""))?;
+        self.write(
+            format!(
+                "{}{} {prompt}: {}\n",
+                self.indentation(),
+                self.theme.success.paint(self.theme.glyphs.done),
+                candidates[index]
+            )
+            .as_str(),
+        )?;
+        Ok(index)
+    }
+
+    // Reads a value without echoing it, for tokens and passwords. Values of
+    // at least MIN_SECRET_LENGTH characters are masked in all later output.
+    pub fn password(&mut self, prompt: &str) -> anyhow::Result<String> {
+        #[cfg(feature = "progress")]
+        let value = if is_interactive(self) {
+            self.password_interactive(prompt)?
+        } else {
+            read_answer_line(prompt)?
+        };
+        #[cfg(not(feature = "progress"))]
+        let value = read_answer_line(prompt)?;
+        // a short value would mask unrelated text wherever it occurs
+        if value.chars().count() >= MIN_SECRET_LENGTH {
+            self.add_secret(value.as_str());
+        }
+        self.write(
+            format!(
+                "{}{} {prompt}: ********\n",
+                self.indentation(),
+                self.theme.success.paint(self.theme.glyphs.done),
+            )
+            .as_str(),
+        )?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,80 @@
+use crate::Printer;
+#[cfg(feature = "progress")]
+use crate::{MultiProgress, MultiProgressBar};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+#[cfg(feature = "progress")]
+use std::io::Write;
+
+fn as_block(text: &str) -> std::borrow::Cow<'_, str> {
+    if text.is_empty() || text.ends_with('\n') {
+        text.into()
+    } else {
+        format!("{text}\n").into()
+    }
+}
+
+impl Printer {
+    // Writes already styled text exactly as given: no indent, wrapping or level
+    // filtering. Use for output from other formatters such as a diff tool.
+    pub fn raw(&mut self, text: &str) -> anyhow::Result<()> {
+        self.write(text).context(format_context!(""))
+    }
+
+    // Like raw but guarantees the block ends on a new line
+    pub fn raw_block(&mut self, text: &str) -> anyhow::Result<()> {
+        self.raw(as_block(text).as_ref())
+    }
+}
+
+// Lets write!/writeln! target the printer directly; text is written as with raw
+impl std::fmt::Write for Printer {
+    fn write_str(&mut self, text: &str) -> std::fmt::Result {
+        self.raw(text).map_err(|_| std::fmt::Error)
+    }
+}
+
+#[cfg(feature = "progress")]
+impl MultiProgress<'_> {
+    // Hides the bars while the text is written so a redraw can't split it
+    pub fn raw(&mut self, text: &str) -> anyhow::Result<()> {
+        let printer = &mut *self.printer;
+        self.multi_progress
+            .suspend(|| printer.raw(text))
+            .context(format_context!(""))
+    }
+
+    pub fn raw_block(&mut self, text: &str) -> anyhow::Result<()> {
+        self.raw(as_block(text).as_ref())
+    }
+}
+
+#[cfg(feature = "progress")]
+impl MultiProgressBar {
+    pub fn raw(&self, text: &str) {
+        let _lock = self.lock.lock().unwrap();
+        if let Some(session) = self.session.as_ref() {
+            session.output(text);
+        }
+        #[cfg(feature = "tui")]
+        if let Some(tui_job) = self.tui_job.as_ref() {
+            for line in text.lines() {
+                tui_job.println(line);
+            }
+            return;
+        }
+        let write = || {
+            let mut stdout = std::io::stdout().lock();
+            let _ = stdout.write_all(text.as_bytes());
+            let _ = stdout.flush();
+        };
+        match (self.progress.as_ref(), self.plain.as_ref()) {
+            (Some(progress), None) => progress.suspend(write),
+            _ => write(),
+        }
+    }
+
+    pub fn raw_block(&self, text: &str) {
+        self.raw(as_block(text).as_ref());
+    }
+}
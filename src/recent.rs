@@ -0,0 +1,82 @@
+use crate::{format_log, theme, transform, Level, Printer};
+use owo_colors::{OwoColorize, Stream::Stdout};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+// The last lines filtered out by the level, shared by a printer and its bars
+// and shown before the next Error so failures come with their debug context
+#[derive(Default)]
+pub(crate) struct SuppressedLines {
+    capacity: AtomicUsize,
+    lines: Mutex<VecDeque<(Level, String)>>,
+}
+
+impl SuppressedLines {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity.load(Ordering::Relaxed) > 0
+    }
+
+    fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut lines = self.lines.lock().unwrap();
+        while lines.len() > capacity {
+            lines.pop_front();
+        }
+    }
+
+    pub(crate) fn push(&self, level: Level, message: &str) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= capacity {
+            lines.pop_front();
+        }
+        lines.push_back((level, message.to_string()));
+    }
+
+    // Empties the buffer into formatted lines, or nothing if it was empty
+    pub(crate) fn take_formatted(
+        &self,
+        indent: &str,
+        max_width: usize,
+        theme: &theme::Theme,
+        transformers: &transform::LevelTransformers,
+    ) -> String {
+        let lines = std::mem::take(&mut *self.lines.lock().unwrap());
+        if lines.is_empty() {
+            return String::new();
+        }
+        let mut output = format!(
+            "{indent}{}\n",
+            format!("last {} suppressed lines:", lines.len())
+                .if_supports_color(Stdout, |text| text.dimmed())
+        );
+        for (level, message) in lines {
+            format_log(
+                &mut output,
+                indent,
+                max_width,
+                theme,
+                transformers,
+                level,
+                message.as_str(),
+            );
+        }
+        output
+    }
+}
+
+impl Printer {
+    // Keeps the last `count` log lines hidden by the level and prints them
+    // ahead of the next Error. Zero turns it off.
+    pub fn set_error_context_lines(&mut self, count: usize) {
+        self.suppressed.set_capacity(count);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::Printer;
+use std::{
+    borrow::Cow,
+    sync::{Arc, RwLock},
+};
+
+pub(crate) const REDACTED: &str = "********";
+
+// Values that must never reach the output, such as tokens entered at a
+// password prompt. Shared by a printer and its progress bars.
+#[derive(Clone, Default)]
+pub(crate) struct Secrets {
+    values: Arc<RwLock<Vec<String>>>,
+}
+
+impl Secrets {
+    fn add(&self, secret: &str) {
+        if secret.is_empty() {
+            return;
+        }
+        let mut values = self.values.write().unwrap();
+        if !values.iter().any(|value| value == secret) {
+            values.push(secret.to_string());
+            // a secret containing another one is replaced first
+            values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+        }
+    }
+
+    pub(crate) fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let values = self.values.read().unwrap();
+        let mut result = Cow::Borrowed(text);
+        for value in values.iter() {
+            if result.contains(value.as_str()) {
+                result = Cow::Owned(result.replace(value.as_str(), REDACTED));
+            }
+        }
+        result
+    }
+}
+
+impl Printer {
+    // Masks `secret` in everything printed or logged from now on, including
+    // progress bars and log files
+    pub fn add_secret(&mut self, secret: &str) {
+        self.secrets.add(secret);
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{
+    summary::{escape_xml, StepStatus, Summary, SummaryStep},
+    text::{humanize_duration, Table},
+};
+
+// step output is cut to its end, where the errors usually are, to stay well
+// inside PR comment and Slack message size limits
+const OUTPUT_TAIL_LINES: usize = 40;
+
+fn output_tail(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    let start = lines.len().saturating_sub(OUTPUT_TAIL_LINES);
+    let mut tail = String::new();
+    if start > 0 {
+        tail.push_str(format!("... {start} earlier lines omitted\n").as_str());
+    }
+    tail.push_str(lines[start..].join("\n").as_str());
+    tail
+}
+
+// A fence longer than any backtick run in the content so it can't close early
+fn code_fence(content: &str) -> String {
+    let longest = content
+        .split(|character| character != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+// "1 failed, 3 passed, 1 skipped in 1m 02s"
+fn headline(summary: &Summary) -> String {
+    let counts: Vec<String> = [StepStatus::Failed, StepStatus::Passed, StepStatus::Skipped]
+        .into_iter()
+        .map(|status| (status, summary.count(status)))
+        .filter(|(_, count)| *count > 0)
+        .map(|(status, count)| format!("{count} {}", status.to_string().to_lowercase()))
+        .collect();
+    let counts = if counts.is_empty() {
+        "no steps".to_string()
+    } else {
+        counts.join(", ")
+    };
+    format!("{counts} in {}", humanize_duration(summary.duration()))
+}
+
+fn is_failed(summary: &Summary) -> bool {
+    summary.count(StepStatus::Failed) > 0
+}
+
+fn failed_steps(summary: &Summary) -> impl Iterator<Item = (&SummaryStep, &str)> {
+    summary.steps.iter().filter_map(|step| match step.status {
+        StepStatus::Failed => step.output.as_deref().map(|output| (step, output)),
+        _ => None,
+    })
+}
+
+fn github_status(status: StepStatus) -> &'static str {
+    match status {
+        StepStatus::Passed => "✅",
+        StepStatus::Failed => "❌",
+        StepStatus::Skipped => "⏭️",
+    }
+}
+
+// A GitHub PR comment: a table of steps with the output of each failed step
+// in a collapsed <details> block
+pub fn github_comment(summary: &Summary) -> String {
+    let status = if is_failed(summary) {
+        github_status(StepStatus::Failed)
+    } else {
+        github_status(StepStatus::Passed)
+    };
+    let mut comment = format!("### {status} {}: {}\n\n", summary.name, headline(summary));
+    let mut table = Table::new(&["Step", "Status", "Duration"]);
+    for step in summary.steps.iter() {
+        table.add_row(&[
+            step.name.to_string(),
+            format!("{} {}", github_status(step.status), step.status),
+            humanize_duration(step.duration),
+        ]);
+    }
+    let _ = table.write_markdown(&mut comment);
+    for (step, output) in failed_steps(summary) {
+        let output = output_tail(output);
+        let fence = code_fence(output.as_str());
+        comment.push_str(
+            format!(
+                "\n<details><summary>{} output</summary>\n\n{fence}text\n{output}\n{fence}\n\n</details>\n",
+                escape_xml(&step.name)
+            )
+            .as_str(),
+        );
+    }
+    comment
+}
+
+fn slack_status(status: StepStatus) -> &'static str {
+    match status {
+        StepStatus::Passed => ":white_check_mark:",
+        StepStatus::Failed => ":x:",
+        StepStatus::Skipped => ":fast_forward:",
+    }
+}
+
+// Slack treats these three as markup everywhere, even in code blocks
+fn escape_slack(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// A Slack mrkdwn message. Slack has no collapsible blocks so failed output is
+// shown inline, shortened.
+pub fn slack_message(summary: &Summary) -> String {
+    let status = if is_failed(summary) {
+        slack_status(StepStatus::Failed)
+    } else {
+        slack_status(StepStatus::Passed)
+    };
+    let mut message = format!(
+        "{status} *{}*: {}\n",
+        escape_slack(&summary.name),
+        headline(summary)
+    );
+    for step in summary.steps.iter() {
+        message.push_str(
+            format!(
+                "{} {} ({})\n",
+                slack_status(step.status),
+                escape_slack(&step.name),
+                humanize_duration(step.duration)
+            )
+            .as_str(),
+        );
+    }
+    for (step, output) in failed_steps(summary) {
+        message.push_str(
+            format!(
+                "\n*{} output*\n```\n{}\n```\n",
+                escape_slack(&step.name),
+                escape_slack(output_tail(output).as_str())
+            )
+            .as_str(),
+        );
+    }
+    message
+}
+
+// `[text](url)` becomes `<url|text>`
+fn convert_links(line: &str) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find("](").map(|index| open + index) else {
+            break;
+        };
+        let Some(end) = rest[close..].find(')').map(|index| close + index) else {
+            break;
+        };
+        result.push_str(&rest[..open]);
+        let text = &rest[open + 1..close];
+        let url = &rest[close + 2..end];
+        result.push_str(format!("<{url}|{text}>").as_str());
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+// Swaps `from` delimiters for `to`, leaving unpaired ones alone
+fn convert_emphasis(line: &str, from: &str, to: &str) -> String {
+    let parts: Vec<&str> = line.split(from).collect();
+    if parts.len() < 3 {
+        return line.to_string();
+    }
+    let pairs = (parts.len() - 1) / 2;
+    let mut result = String::new();
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            result.push_str(if index <= pairs * 2 { to } else { from });
+        }
+        result.push_str(part);
+    }
+    result
+}
+
+// Rewrites markdown, for example from Printer::capture around a Markdown
+// writer, as Slack mrkdwn. Code blocks are kept as they are.
+pub fn markdown_to_slack(markdown: &str) -> String {
+    let mut result = String::new();
+    let mut is_in_code_block = false;
+    for line in markdown.lines() {
+        let escaped = escape_slack(line);
+        if line.trim_start().starts_with("```") {
+            is_in_code_block = !is_in_code_block;
+            result.push_str(escaped.trim_start());
+        } else if is_in_code_block {
+            result.push_str(escaped.as_str());
+        } else {
+            let trimmed = escaped.trim_start();
+            let heading = trimmed.trim_start_matches('#');
+            let converted = if heading.len() < trimmed.len() && heading.starts_with(' ') {
+                // headings have no mrkdwn equivalent, bold is the closest
+                format!("*{}*", heading.trim().trim_matches('*'))
+            } else {
+                let indent = &escaped[..escaped.len() - trimmed.len()];
+                let item = match trimmed.strip_prefix("- ") {
+                    Some(item) => format!("{indent}• {item}"),
+                    None => escaped.clone(),
+                };
+                // bold goes through a placeholder so its `*` isn't read as italics
+                let item = convert_emphasis(item.as_str(), "**", "\u{0}");
+                let item = convert_emphasis(item.as_str(), "*", "_");
+                let item = item.replace('\u{0}', "*");
+                let item = convert_emphasis(item.as_str(), "~~", "~");
+                convert_links(item.as_str())
+            };
+            result.push_str(converted.as_str());
+        }
+        result.push('\n');
+    }
+    result
+}
+
+fn html_status_color(status: StepStatus) -> &'static str {
+    match status {
+        StepStatus::Passed => "#1a7f37",
+        StepStatus::Failed => "#cf222e",
+        StepStatus::Skipped => "#6e7781",
+    }
+}
+
+// Matching plain text and HTML bodies for a notification mail. Mail clients
+// drop <style> blocks, so the HTML is styled inline.
+pub fn email(summary: &Summary) -> (String, String) {
+    let result = if is_failed(summary) {
+        StepStatus::Failed
+    } else {
+        StepStatus::Passed
+    };
+    let name_width = summary
+        .steps
+        .iter()
+        .map(|step| step.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut plain = format!(
+        "{}: {}\n{}\n\n",
+        summary.name,
+        result.to_string().to_uppercase(),
+        headline(summary)
+    );
+    let mut html = format!(
+        "<html><body style=\"font-family: sans-serif\">\n<h2 style=\"color: {}\">{}: {}</h2>\n<p>{}</p>\n<table style=\"border-collapse: collapse\">\n",
+        html_status_color(result),
+        escape_xml(&summary.name),
+        result,
+        headline(summary)
+    );
+    for step in summary.steps.iter() {
+        let duration = humanize_duration(step.duration);
+        plain.push_str(
+            format!(
+                "  {:<8} {:<name_width$}  {duration}\n",
+                step.status.to_string().to_uppercase(),
+                step.name
+            )
+            .as_str(),
+        );
+        html.push_str(
+            format!(
+                "<tr><td style=\"padding: 2px 12px 2px 0; color: {}\"><b>{}</b></td><td style=\"padding: 2px 12px 2px 0\">{}</td><td>{duration}</td></tr>\n",
+                html_status_color(step.status),
+                step.status,
+                escape_xml(&step.name)
+            )
+            .as_str(),
+        );
+    }
+    html.push_str("</table>\n");
+
+    for (step, output) in failed_steps(summary) {
+        let output = output_tail(output);
+        let title = format!("{} output", step.name);
+        plain.push_str(
+            format!(
+                "\n{title}\n{}\n{output}\n",
+                "-".repeat(title.chars().count())
+            )
+            .as_str(),
+        );
+        html.push_str(
+            format!(
+                "<h3>{}</h3>\n<pre style=\"background: #f6f8fa; padding: 8px; white-space: pre-wrap\">{}</pre>\n",
+                escape_xml(title.as_str()),
+                escape_xml(output.as_str())
+            )
+            .as_str(),
+        );
+    }
+    html.push_str("</body></html>\n");
+    (plain, html)
+}
+
+// The email bodies as a multipart/alternative MIME entity with its headers,
+// ready to follow the From, To and Subject headers of a message
+pub fn email_multipart(summary: &Summary) -> String {
+    let (plain, html) = email(summary);
+    let mut boundary = "printer-report".to_string();
+    while plain.contains(boundary.as_str()) || html.contains(boundary.as_str()) {
+        boundary.push('-');
+    }
+    let part = |content_type: &str, body: &str| {
+        format!(
+            "--{boundary}\r\nContent-Type: {content_type}; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}\r\n",
+            body.replace('\n', "\r\n")
+        )
+    };
+    format!(
+        "MIME-Version: 1.0\r\nContent-Type: multipart/alternative; boundary=\"{boundary}\"\r\n\r\n{}{}--{boundary}--\r\n",
+        part("text/plain", plain.as_str()),
+        part("text/html", html.as_str())
+    )
+}
@@ -0,0 +1,147 @@
+use crate::{ci::Ci, Printer};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Heading,
+    Section,
+}
+
+// A scope fails when a Warning or Error is logged inside it, it is marked
+// failed, or it ends while panicking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeOutcome {
+    Success,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeInfo {
+    pub name: String,
+    pub kind: ScopeKind,
+    // scopes already open when this one started
+    pub depth: usize,
+}
+
+type StartHook = Arc<dyn Fn(&ScopeInfo) + Send + Sync>;
+type EndHook = Arc<dyn Fn(&ScopeInfo, Duration, ScopeOutcome) + Send + Sync>;
+
+struct OpenScope {
+    id: u64,
+    info: ScopeInfo,
+    start_time: Instant,
+    is_failed: bool,
+}
+
+// The open headings and sections of a printer, shared with the guards that
+// close them, and the hooks told about each one
+#[derive(Default)]
+pub(crate) struct Scopes {
+    start_hooks: Mutex<Vec<StartHook>>,
+    end_hooks: Mutex<Vec<EndHook>>,
+    open: Mutex<Vec<OpenScope>>,
+    next_id: AtomicU64,
+}
+
+// Closes the scope and its CI group when dropped. Every guard owns the token
+// of the scope it opened, so guards can drop in any order, e.g. on different
+// threads, and still report their own scope.
+pub(crate) struct ScopeToken {
+    scopes: Arc<Scopes>,
+    id: u64,
+    ci_group: Option<(Arc<Ci>, u64)>,
+}
+
+impl Drop for ScopeToken {
+    fn drop(&mut self) {
+        if let Some((ci, group)) = self.ci_group.as_ref() {
+            ci.end_group(*group);
+        }
+        self.scopes.end(self.id);
+    }
+}
+
+impl Scopes {
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        name: &str,
+        kind: ScopeKind,
+        ci: Option<&Arc<Ci>>,
+    ) -> ScopeToken {
+        let ci_group = ci.map(|ci| (ci.clone(), ci.start_group(name)));
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let info = {
+            let mut open = self.open.lock().unwrap();
+            let info = ScopeInfo {
+                name: name.to_string(),
+                kind,
+                depth: open.len(),
+            };
+            open.push(OpenScope {
+                id,
+                info: info.clone(),
+                start_time: Instant::now(),
+                is_failed: false,
+            });
+            info
+        };
+        // cloned so a hook can't deadlock by touching the printer's scopes
+        let hooks = self.start_hooks.lock().unwrap().clone();
+        for hook in hooks {
+            hook(&info);
+        }
+        ScopeToken {
+            scopes: self.clone(),
+            id,
+            ci_group,
+        }
+    }
+
+    fn end(&self, id: u64) {
+        let scope = {
+            let mut open = self.open.lock().unwrap();
+            let Some(index) = open.iter().position(|scope| scope.id == id) else {
+                return;
+            };
+            open.remove(index)
+        };
+        let outcome = if scope.is_failed || std::thread::panicking() {
+            ScopeOutcome::Failed
+        } else {
+            ScopeOutcome::Success
+        };
+        let duration = scope.start_time.elapsed();
+        let hooks = self.end_hooks.lock().unwrap().clone();
+        for hook in hooks {
+            hook(&scope.info, duration, outcome);
+        }
+    }
+
+    pub(crate) fn mark_failed(&self) {
+        for scope in self.open.lock().unwrap().iter_mut() {
+            scope.is_failed = true;
+        }
+    }
+}
+
+impl Printer {
+    // Called as every heading or section starts, e.g. to emit telemetry or
+    // CI markers without wrapping each call site
+    pub fn on_section_start<Function>(&mut self, hook: Function)
+    where
+        Function: Fn(&ScopeInfo) + Send + Sync + 'static,
+    {
+        self.scopes.start_hooks.lock().unwrap().push(Arc::new(hook));
+    }
+
+    pub fn on_section_end<Function>(&mut self, hook: Function)
+    where
+        Function: Fn(&ScopeInfo, Duration, ScopeOutcome) + Send + Sync + 'static,
+    {
+        self.scopes.end_hooks.lock().unwrap().push(Arc::new(hook));
+    }
+}
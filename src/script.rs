@@ -0,0 +1,126 @@
+use crate::{log_file, ExecuteOptions, FinishStyle, Level, MultiProgress, Printer, Section};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScriptLine {
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
+    pub allow_failure: bool,
+}
+
+impl ScriptLine {
+    pub fn new(command: &str, arguments: &[&str]) -> Self {
+        Self {
+            command: command.to_string(),
+            arguments: arguments
+                .iter()
+                .map(|argument| argument.to_string())
+                .collect(),
+            allow_failure: false,
+        }
+    }
+
+    pub fn allow_failure(mut self) -> Self {
+        self.allow_failure = true;
+        self
+    }
+
+    fn full_command(&self) -> String {
+        format!("{} {}", self.command, self.arguments.join(" "))
+    }
+}
+
+fn write_separator(
+    log_file_path: &str,
+    index: usize,
+    count: usize,
+    line: &ScriptLine,
+) -> anyhow::Result<()> {
+    let mut file = log_file::LogWriter::open(log_file_path, true).context(format_context!(""))?;
+    writeln!(
+        file,
+        "\n===== [{}/{count}] {} =====",
+        index + 1,
+        line.full_command()
+    )
+    .context(format_context!("while writing {log_file_path}"))?;
+    Ok(())
+}
+
+impl Printer {
+    // Runs the lines as one job. A failing line stops the script unless it
+    // allows failure. Returns the captured stdout of each line.
+    pub fn execute_script(
+        &mut self,
+        lines: &[ScriptLine],
+        options: ExecuteOptions,
+    ) -> anyhow::Result<Vec<Option<String>>> {
+        let label = options.label.clone();
+        let section = Section::new(self, label.as_ref()).context(format_context!(""))?;
+        let mut multi_progress = MultiProgress::new(section.printer);
+        multi_progress.execute_script(lines, options)
+    }
+}
+
+impl MultiProgress<'_> {
+    // A bar labelled with the options' label counts the finished lines, each
+    // line runs on a spinner of its own below it
+    pub fn execute_script(
+        &mut self,
+        lines: &[ScriptLine],
+        options: ExecuteOptions,
+    ) -> anyhow::Result<Vec<Option<String>>> {
+        if let Some(log_file_path) = options.log_file_path.as_ref() {
+            log_file::LogWriter::open(log_file_path.as_ref(), false)
+                .context(format_context!(""))?;
+        }
+
+        let mut progress_bar = self.add_progress(
+            options.label.as_ref(),
+            Some(lines.len() as u64),
+            Some("done"),
+        );
+
+        let mut outputs = Vec::with_capacity(lines.len());
+        for (index, line) in lines.iter().enumerate() {
+            let mut line_options = options.clone();
+            line_options.is_append_log = true;
+            for argument in line.arguments.iter() {
+                line_options.add_argument(argument);
+            }
+            if let Some(log_file_path) = options.log_file_path.as_ref() {
+                write_separator(log_file_path, index, lines.len(), line)
+                    .context(format_context!(""))?;
+            }
+
+            let mut line_bar = self.add_progress(line.command.as_str(), None, None);
+            match line_bar.execute_process(line.command.as_str(), line_options) {
+                Ok(output) => {
+                    line_bar.finish_with(FinishStyle::Clear);
+                    outputs.push(output);
+                }
+                Err(error) if line.allow_failure => {
+                    progress_bar.log(
+                        Level::Warning,
+                        format!("{} failed (allowed): {error}", line.full_command()).as_str(),
+                    );
+                    outputs.push(None);
+                }
+                Err(error) => {
+                    return Err(error).context(format_context!(
+                        "script line {} failed: {}",
+                        index + 1,
+                        line.full_command()
+                    ));
+                }
+            }
+            progress_bar.increment(1);
+        }
+        Ok(outputs)
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+// open connections by id, shut down when the server stops
+type Connections = Arc<Mutex<HashMap<usize, TcpStream>>>;
+
+// Serves until dropped. Dropping stops accepting and shuts down the open
+// connections, which ends streams a browser would otherwise keep open.
+pub struct ServerGuard {
+    address: SocketAddr,
+    is_stopped: Arc<AtomicBool>,
+    connections: Connections,
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl ServerGuard {
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for ServerGuard {
+    fn drop(&mut self) {
+        {
+            // under the lock so no connection is added after the shutdown
+            let connections = self.connections.lock().unwrap();
+            self.is_stopped.store(true, Ordering::Relaxed);
+            for stream in connections.values() {
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+        }
+        // wakes the blocking accept so the thread sees the flag
+        let mut wake_address = self.address;
+        if wake_address.ip().is_unspecified() {
+            wake_address.set_ip(if wake_address.is_ipv4() {
+                Ipv4Addr::LOCALHOST.into()
+            } else {
+                Ipv6Addr::LOCALHOST.into()
+            });
+        }
+        let is_woken = TcpStream::connect(wake_address).is_ok();
+        if let Some(accept_thread) = self.accept_thread.take() {
+            if is_woken {
+                let _ = accept_thread.join();
+            }
+        }
+    }
+}
+
+// Binds `address` and handles each connection on its own thread
+pub(crate) fn serve<Address, Handler>(
+    address: Address,
+    name: &str,
+    handler: Handler,
+) -> anyhow::Result<ServerGuard>
+where
+    Address: ToSocketAddrs,
+    Handler: Fn(TcpStream) -> std::io::Result<()> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address).context(format_context!("while binding {name}"))?;
+    let address = listener
+        .local_addr()
+        .context(format_context!("while binding {name}"))?;
+    let is_stopped = Arc::new(AtomicBool::new(false));
+    let connections = Connections::default();
+    let handler = Arc::new(handler);
+    let accept_thread = {
+        let is_stopped = is_stopped.clone();
+        let connections = connections.clone();
+        std::thread::spawn(move || {
+            let mut next_id = 0;
+            for stream in listener.incoming().flatten() {
+                let Ok(tracked) = stream.try_clone() else {
+                    continue;
+                };
+                let id = next_id;
+                next_id += 1;
+                {
+                    let mut open = connections.lock().unwrap();
+                    if is_stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    open.insert(id, tracked);
+                }
+                let connections = connections.clone();
+                let handler = handler.clone();
+                std::thread::spawn(move || {
+                    let _ = handler(stream);
+                    connections.lock().unwrap().remove(&id);
+                });
+            }
+        })
+    };
+    Ok(ServerGuard {
+        address,
+        is_stopped,
+        connections,
+        accept_thread: Some(accept_thread),
+    })
+}
@@ -0,0 +1,290 @@
+#[cfg(feature = "progress")]
+use crate::preset::Preset;
+use crate::Printer;
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "progress")]
+use std::{collections::HashMap, time::Duration};
+use std::{
+    io::{BufRead, Write},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionEvent {
+    Output {
+        text: String,
+    },
+    BarStarted {
+        id: usize,
+        prefix: String,
+        total: Option<u64>,
+    },
+    BarMessage {
+        id: usize,
+        message: String,
+    },
+    BarProgress {
+        id: usize,
+        position: u64,
+        total: Option<u64>,
+    },
+    BarFinished {
+        id: usize,
+        #[serde(default)]
+        is_failed: bool,
+    },
+}
+
+// One line of a session file: milliseconds since recording started plus the event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: SessionEvent,
+}
+
+// Receives every printer event as it happens. The session file is one sink;
+// dashboards and exporters can add their own with Printer::add_event_sink.
+pub trait EventSink: Send {
+    fn event(&mut self, event: &RecordedEvent);
+
+    fn flush(&mut self) {}
+}
+
+struct FileSink {
+    file: std::io::BufWriter<std::fs::File>,
+}
+
+impl EventSink for FileSink {
+    fn event(&mut self, event: &RecordedEvent) {
+        if let Ok(line) = serde_json::to_string(event) {
+            let _ = writeln!(self.file, "{line}");
+        }
+    }
+
+    fn flush(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+pub(crate) struct SessionRecorder {
+    start: Instant,
+    sinks: Vec<Box<dyn EventSink>>,
+    #[cfg(feature = "progress")]
+    next_bar_id: usize,
+}
+
+impl SessionRecorder {
+    fn record(&mut self, event: SessionEvent) {
+        let recorded = RecordedEvent {
+            at: self.start.elapsed().as_millis() as u64,
+            event,
+        };
+        for sink in self.sinks.iter_mut() {
+            sink.event(&recorded);
+        }
+    }
+}
+
+impl Drop for SessionRecorder {
+    fn drop(&mut self) {
+        for sink in self.sinks.iter_mut() {
+            sink.flush();
+        }
+    }
+}
+
+pub(crate) type SharedRecorder = Arc<Mutex<SessionRecorder>>;
+
+pub(crate) fn record(recorder: Option<&SharedRecorder>, event: SessionEvent) {
+    if let Some(recorder) = recorder {
+        recorder.lock().unwrap().record(event);
+    }
+}
+
+// A progress bar's link to the recorder. Progress is only written when it
+// changes because the monitor loop ticks bars every 100ms.
+#[cfg(feature = "progress")]
+pub(crate) struct BarSession {
+    recorder: SharedRecorder,
+    id: usize,
+    last_progress: Option<(u64, Option<u64>)>,
+    pub(crate) is_failed: bool,
+}
+
+#[cfg(feature = "progress")]
+impl BarSession {
+    pub(crate) fn start(recorder: &SharedRecorder, prefix: &str, total: Option<u64>) -> Self {
+        let mut locked = recorder.lock().unwrap();
+        let id = locked.next_bar_id;
+        locked.next_bar_id += 1;
+        locked.record(SessionEvent::BarStarted {
+            id,
+            prefix: prefix.to_string(),
+            total,
+        });
+        Self {
+            recorder: recorder.clone(),
+            id,
+            last_progress: None,
+            is_failed: false,
+        }
+    }
+
+    pub(crate) fn message(&self, message: &str) {
+        self.recorder
+            .lock()
+            .unwrap()
+            .record(SessionEvent::BarMessage {
+                id: self.id,
+                message: message.to_string(),
+            });
+    }
+
+    pub(crate) fn progress(&mut self, position: u64, total: Option<u64>) {
+        if self.last_progress == Some((position, total)) {
+            return;
+        }
+        self.last_progress = Some((position, total));
+        self.recorder
+            .lock()
+            .unwrap()
+            .record(SessionEvent::BarProgress {
+                id: self.id,
+                position,
+                total,
+            });
+    }
+
+    pub(crate) fn output(&self, text: &str) {
+        self.recorder.lock().unwrap().record(SessionEvent::Output {
+            text: text.to_string(),
+        });
+    }
+}
+
+#[cfg(feature = "progress")]
+impl Drop for BarSession {
+    fn drop(&mut self) {
+        self.recorder
+            .lock()
+            .unwrap()
+            .record(SessionEvent::BarFinished {
+                id: self.id,
+                is_failed: self.is_failed,
+            });
+    }
+}
+
+pub fn read_session(path: &std::path::Path) -> anyhow::Result<Vec<RecordedEvent>> {
+    let file =
+        std::fs::File::open(path).context(format_context!("while opening {}", path.display()))?;
+    let mut events = Vec::new();
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line.context(format_context!("while reading {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str(line.as_str()).context(format_context!(
+            "while parsing {}:{}",
+            path.display(),
+            index + 1
+        ))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+// Re-renders a recorded session on stdout. A speed of 2.0 plays twice as fast;
+// zero or less skips the delays entirely.
+#[cfg(feature = "progress")]
+pub fn replay(path: &std::path::Path, speed: f64) -> anyhow::Result<()> {
+    let events = read_session(path).context(format_context!(""))?;
+    let multi_progress = indicatif::MultiProgress::new();
+    let mut bars: HashMap<usize, indicatif::ProgressBar> = HashMap::new();
+    let start = Instant::now();
+    for recorded in events {
+        if speed > 0.0 {
+            let due = Duration::from_millis(recorded.at).div_f64(speed);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        match recorded.event {
+            SessionEvent::Output { text } => {
+                if bars.is_empty() {
+                    print!("{text}");
+                    let _ = std::io::stdout().flush();
+                } else {
+                    let _ = multi_progress.println(text.trim_end_matches('\n'));
+                }
+            }
+            SessionEvent::BarStarted { id, prefix, total } => {
+                let progress =
+                    multi_progress.add(indicatif::ProgressBar::new(total.unwrap_or(200)));
+                progress.set_style(Preset::default().style(total.is_some()));
+                progress.set_prefix(prefix);
+                bars.insert(id, progress);
+            }
+            SessionEvent::BarMessage { id, message } => {
+                if let Some(progress) = bars.get(&id) {
+                    progress.set_message(message);
+                }
+            }
+            SessionEvent::BarProgress {
+                id,
+                position,
+                total,
+            } => {
+                if let Some(progress) = bars.get(&id) {
+                    if let Some(total) = total {
+                        progress.set_length(total);
+                    }
+                    progress.set_position(position);
+                }
+            }
+            SessionEvent::BarFinished { id, .. } => {
+                if let Some(progress) = bars.remove(&id) {
+                    progress.finish();
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Printer {
+    // Records everything printed from here on, with timings, as JSON lines that
+    // `replay` can play back
+    pub fn record_session(&mut self, path: &std::path::Path) -> anyhow::Result<()> {
+        let file = std::fs::File::create(path)
+            .context(format_context!("while creating {}", path.display()))?;
+        self.add_event_sink(Box::new(FileSink {
+            file: std::io::BufWriter::new(file),
+        }));
+        Ok(())
+    }
+
+    pub fn add_event_sink(&mut self, sink: Box<dyn EventSink>) {
+        match self.recorder.as_ref() {
+            Some(recorder) => recorder.lock().unwrap().sinks.push(sink),
+            None => {
+                self.recorder = Some(Arc::new(Mutex::new(SessionRecorder {
+                    start: Instant::now(),
+                    sinks: vec![sink],
+                    #[cfg(feature = "progress")]
+                    next_bar_id: 0,
+                })))
+            }
+        }
+    }
+
+    // Detaches every sink; files are complete once live progress bars are dropped
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{keyboard::Controls, Level, LevelHandle, Printer};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use signal_hook::consts::{SIGUSR1, SIGUSR2};
+use std::sync::Arc;
+
+fn more_verbose(level: Level) -> Level {
+    match level {
+        Level::Silent => Level::Error,
+        Level::Error => Level::Warning,
+        Level::Warning => Level::App,
+        Level::App => Level::Info,
+        Level::Info => Level::Message,
+        Level::Message => Level::Debug,
+        Level::Debug | Level::Trace => Level::Trace,
+    }
+}
+
+fn less_verbose(level: Level) -> Level {
+    match level {
+        Level::Trace => Level::Debug,
+        Level::Debug => Level::Message,
+        Level::Message => Level::Info,
+        Level::Info => Level::App,
+        Level::App => Level::Warning,
+        Level::Warning => Level::Error,
+        Level::Error | Level::Silent => Level::Silent,
+    }
+}
+
+// SIGUSR1 makes output one level more verbose and SIGUSR2 one level less.
+// Process output passthrough is on while the level is Debug or lower.
+pub struct SignalListener {
+    handle: signal_hook::iterator::Handle,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl SignalListener {
+    fn start(
+        level: LevelHandle,
+        initial_level: Level,
+        controls: Arc<Controls>,
+    ) -> anyhow::Result<Self> {
+        let mut signals = signal_hook::iterator::Signals::new([SIGUSR1, SIGUSR2])
+            .context(format_context!("while registering SIGUSR1/SIGUSR2"))?;
+        let handle = signals.handle();
+        let thread = std::thread::spawn(move || {
+            for signal in signals.forever() {
+                let current = level.get().unwrap_or(initial_level);
+                let next = match signal {
+                    SIGUSR1 => more_verbose(current),
+                    SIGUSR2 => less_verbose(current),
+                    _ => continue,
+                };
+                level.set(next);
+                controls.set_passthrough(next <= Level::Debug);
+            }
+        });
+        Ok(Self {
+            handle,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for SignalListener {
+    fn drop(&mut self) {
+        self.handle.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Printer {
+    // Opt-in. Keep the listener alive for as long as the toggles should work.
+    pub fn enable_signal_controls(&self) -> anyhow::Result<SignalListener> {
+        SignalListener::start(
+            self.level_handle(),
+            self.verbosity.level,
+            self.controls.clone(),
+        )
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{clock::PauseClock, format, handle::PrinterHandle, MultiProgress, MultiProgressBar};
+use serde::Serialize;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BarStatus {
+    Running,
+    // added with add_queued_progress and not started yet
+    Queued,
+    Paused,
+    Finished,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BarState {
+    pub prefix: String,
+    pub message: String,
+    pub position: u64,
+    // None for spinners
+    pub total: Option<u64>,
+    pub status: BarStatus,
+    pub elapsed: Duration,
+}
+
+// What a snapshot needs from a bar without borrowing it. The indicatif bar is
+// held weakly so registering doesn't keep it on screen past its owner.
+pub(crate) struct SnapshotSlot {
+    progress: indicatif::WeakProgressBar,
+    clock: Arc<PauseClock>,
+    pub(crate) is_determinate: AtomicBool,
+    pub(crate) is_failed: AtomicBool,
+    pub(crate) is_queued: AtomicBool,
+    final_state: Mutex<Option<BarState>>,
+}
+
+impl SnapshotSlot {
+    fn live_state(&self, progress: &indicatif::ProgressBar) -> BarState {
+        let status = if self.is_failed.load(Ordering::Relaxed) {
+            BarStatus::Failed
+        } else if progress.is_finished() {
+            BarStatus::Finished
+        } else if self.is_queued.load(Ordering::Relaxed) {
+            BarStatus::Queued
+        } else if self.clock.is_paused() {
+            BarStatus::Paused
+        } else {
+            BarStatus::Running
+        };
+        BarState {
+            // plain text, embedders do their own styling
+            prefix: format::strip_ansi_codes(progress.prefix().trim_end()).into_owned(),
+            message: format::strip_ansi_codes(progress.message().trim_end()).into_owned(),
+            position: progress.position(),
+            total: self
+                .is_determinate
+                .load(Ordering::Relaxed)
+                .then(|| progress.length())
+                .flatten(),
+            status,
+            elapsed: self.clock.working_time(progress.elapsed()),
+        }
+    }
+
+    pub(crate) fn state(&self) -> Option<BarState> {
+        if let Some(state) = self.final_state.lock().unwrap().as_ref() {
+            return Some(state.clone());
+        }
+        self.progress
+            .upgrade()
+            .map(|progress| self.live_state(&progress))
+    }
+
+    // keeps the last view of a dropped bar so finished jobs stay listed
+    pub(crate) fn finish(&self, progress: &indicatif::ProgressBar) {
+        let mut state = self.live_state(progress);
+        if matches!(
+            state.status,
+            BarStatus::Running | BarStatus::Queued | BarStatus::Paused
+        ) {
+            state.status = BarStatus::Finished;
+        }
+        *self.final_state.lock().unwrap() = Some(state);
+    }
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct Registry {
+    slots: Arc<Mutex<Vec<Arc<SnapshotSlot>>>>,
+}
+
+impl Registry {
+    pub(crate) fn register(&self, progress_bar: &mut MultiProgressBar) {
+        let Some(progress) = progress_bar.progress.as_ref() else {
+            return;
+        };
+        let slot = Arc::new(SnapshotSlot {
+            progress: progress.downgrade(),
+            clock: progress_bar.clock.clone(),
+            is_determinate: AtomicBool::new(progress_bar.is_determinate),
+            is_failed: AtomicBool::new(progress_bar.is_failed),
+            is_queued: AtomicBool::new(progress_bar.is_queued),
+            final_state: Mutex::new(None),
+        });
+        self.slots.lock().unwrap().push(slot.clone());
+        progress_bar.snapshot_slot = Some(slot);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.slots.lock().unwrap().len()
+    }
+
+    pub(crate) fn live_bars(&self) -> Vec<indicatif::ProgressBar> {
+        self.slots
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|slot| slot.progress.upgrade())
+            .collect()
+    }
+
+    pub(crate) fn snapshot(&self) -> Vec<BarState> {
+        self.slots
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|slot| slot.state())
+            .collect()
+    }
+}
+
+impl MultiProgress<'_> {
+    // Current state of every bar added so far, in creation order, for embedders
+    // that render their own view
+    pub fn snapshot(&self) -> Vec<BarState> {
+        self.registry.snapshot()
+    }
+}
+
+impl PrinterHandle {
+    pub fn snapshot(&self) -> Vec<BarState> {
+        self.registry.snapshot()
+    }
+}
+
+impl MultiProgressBar {
+    pub fn snapshot(&self) -> Option<BarState> {
+        self.snapshot_slot.as_ref().and_then(|slot| slot.state())
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{Level, Printer, RenderMode};
+use std::sync::{Arc, Mutex};
+
+const SPINNER_TICK: std::time::Duration = std::time::Duration::from_millis(100);
+
+// The spinner currently showing for a printer. Output from the printer clears
+// it for the write and draws it again below.
+pub(crate) type ActiveSpinner = Arc<Mutex<Option<indicatif::ProgressBar>>>;
+
+// Spins until dropped, then clears its line
+pub struct SpinnerGuard {
+    progress: Option<indicatif::ProgressBar>,
+    active: ActiveSpinner,
+}
+
+impl SpinnerGuard {
+    pub fn set_message(&self, message: &str) {
+        if let Some(progress) = self.progress.as_ref() {
+            progress.set_message(message.to_string());
+        }
+    }
+}
+
+impl Drop for SpinnerGuard {
+    fn drop(&mut self) {
+        if let Some(progress) = self.progress.take() {
+            progress.finish_and_clear();
+            let mut active = self.active.lock().unwrap();
+            // a nested spinner has already replaced this one
+            if active.as_ref().is_some_and(|current| current.is_finished()) {
+                *active = None;
+            }
+        }
+    }
+}
+
+impl Printer {
+    // Feedback for a one-off operation of unknown length without setting up a
+    // MultiProgress. Plain output gets the label as a log line instead.
+    pub fn spinner(&mut self, label: &str) -> SpinnerGuard {
+        let progress = if !self.verbosity.is_show_progress_bars {
+            None
+        } else if self.render_mode == RenderMode::Plain {
+            let _ = self.log(Level::Info, label);
+            None
+        } else {
+            let frames: Vec<String> = self
+                .theme
+                .glyphs
+                .spinner
+                .iter()
+                .map(|frame| self.theme.progress_bar.paint(frame))
+                .chain(std::iter::once(" ".to_string()))
+                .collect();
+            let frames: Vec<&str> = frames.iter().map(String::as_str).collect();
+            let style =
+                indicatif::ProgressStyle::with_template("{prefix}{spinner} {msg} {elapsed}")
+                    .unwrap()
+                    .tick_strings(frames.as_slice());
+            let progress = indicatif::ProgressBar::new_spinner();
+            progress.set_style(style);
+            progress.set_prefix(self.indentation());
+            progress.set_message(label.to_string());
+            progress.enable_steady_tick(SPINNER_TICK);
+            *self.spinner.lock().unwrap() = Some(progress.clone());
+            Some(progress)
+        };
+        SpinnerGuard {
+            progress,
+            active: self.spinner.clone(),
+        }
+    }
+}
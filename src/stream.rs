@@ -0,0 +1,613 @@
+use crate::{
+    hints::{Hint, Hints},
+    Level, Printer,
+};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use serde::ser::{self, Error as _, Impossible, Serialize, SerializeSeq};
+
+type Error = serde_json::Error;
+
+fn to_error(error: anyhow::Error) -> Error {
+    Error::custom(format!("{error:?}"))
+}
+
+// True when serde_json would produce null for the value. Anything compound
+// bails out early so large values aren't walked twice.
+fn is_null<Type: Serialize + ?Sized>(value: &Type) -> bool {
+    value.serialize(NullProbe).unwrap_or(false)
+}
+
+// Keys are small, so they go through serde_json to get its map key rules
+fn map_key<Key: Serialize + ?Sized>(key: &Key) -> Result<String, Error> {
+    match serde_json::to_value(key)? {
+        serde_json::Value::String(key) => Ok(key),
+        key @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_)) => Ok(key.to_string()),
+        _ => Err(Error::custom("map key must be a string")),
+    }
+}
+
+impl Printer {
+    // Prints `value` the way objects are printed, writing each entry as serde
+    // produces it instead of building a serde_json::Value first. Keys aren't
+    // aligned and always follow declaration order.
+    pub fn stream_object<Type: Serialize + ?Sized>(
+        &mut self,
+        name: &str,
+        value: &Type,
+        hints: &Hints,
+    ) -> anyhow::Result<()> {
+        if self.active_verbosity().level <= Level::Message && is_null(value) {
+            return Ok(());
+        }
+        self.write_key(name)?;
+        value
+            .serialize(ValueSerializer {
+                printer: self,
+                hint: hints.resolve(name),
+                hints,
+            })
+            .context(format_context!("while printing {name}"))
+    }
+
+    fn write_key(&mut self, key: &str) -> anyhow::Result<()> {
+        self.write(
+            format!(
+                "{}{}: ",
+                self.indentation(),
+                key.if_supports_color(Stdout, |text| text.bold())
+            )
+            .as_str(),
+        )
+    }
+
+    fn is_null_skipped<Type: Serialize + ?Sized>(&self, value: &Type) -> bool {
+        self.active_verbosity().level > Level::Message && is_null(value)
+    }
+}
+
+struct ValueSerializer<'a> {
+    printer: &'a mut Printer,
+    hint: Hint,
+    hints: &'a Hints,
+}
+
+impl<'a> ValueSerializer<'a> {
+    fn scalar(self, text: &str) -> Result<(), Error> {
+        self.printer
+            .write(format!("{text}\n").as_str())
+            .map_err(to_error)
+    }
+
+    fn number(self, number: serde_json::Number) -> Result<(), Error> {
+        let text = self
+            .hint
+            .humanize(&number)
+            .unwrap_or_else(|| number.to_string());
+        self.scalar(text.as_str())
+    }
+
+    fn string(self, value: &str) -> Result<(), Error> {
+        self.printer.print_string_value(value).map_err(to_error)
+    }
+
+    fn open(self, depth: usize) -> Result<Compound<'a>, Error> {
+        self.printer.write("\n").map_err(to_error)?;
+        self.printer.shift_right();
+        Ok(Compound {
+            printer: self.printer,
+            hint: self.hint,
+            hints: self.hints,
+            index: 0,
+            key: None,
+            depth,
+        })
+    }
+
+    // serde_json writes variants with data as `{ "Variant": ... }`
+    fn open_variant(self, variant: &str) -> Result<ValueSerializer<'a>, Error> {
+        self.printer.write("\n").map_err(to_error)?;
+        self.printer.shift_right();
+        self.printer.write_key(variant).map_err(to_error)?;
+        Ok(ValueSerializer {
+            printer: self.printer,
+            hint: self.hints.resolve(variant),
+            hints: self.hints,
+        })
+    }
+}
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, value: bool) -> Result<(), Error> {
+        self.scalar(value.to_string().as_str())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<(), Error> {
+        self.scalar(value.to_string().as_str())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<(), Error> {
+        self.number(value.into())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<(), Error> {
+        self.scalar(value.to_string().as_str())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), Error> {
+        match serde_json::Number::from_f64(value) {
+            Some(number) => self.number(number),
+            None => self.scalar("null"),
+        }
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), Error> {
+        self.string(value.to_string().as_str())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.string(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), Error> {
+        let mut sequence = self.serialize_seq(Some(value.len()))?;
+        for byte in value {
+            sequence.serialize_element(byte)?;
+        }
+        sequence.end()
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.scalar("null")
+    }
+
+    fn serialize_some<Type: Serialize + ?Sized>(self, value: &Type) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.scalar("null")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.scalar("null")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.string(variant)
+    }
+
+    fn serialize_newtype_struct<Type: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &Type,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<Type: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &Type,
+    ) -> Result<(), Error> {
+        let serializer = self.open_variant(variant)?;
+        let printer = serializer.printer;
+        let result = value.serialize(ValueSerializer {
+            printer: &mut *printer,
+            hint: serializer.hint,
+            hints: serializer.hints,
+        });
+        printer.shift_left();
+        result
+    }
+
+    fn serialize_seq(self, _length: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.open(1)
+    }
+
+    fn serialize_tuple(self, _length: usize) -> Result<Compound<'a>, Error> {
+        self.open(1)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _length: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.open(1)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _length: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.open_variant(variant)?.open(2)
+    }
+
+    fn serialize_map(self, _length: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.open(1)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _length: usize) -> Result<Compound<'a>, Error> {
+        self.open(1)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _length: usize,
+    ) -> Result<Compound<'a>, Error> {
+        self.open_variant(variant)?.open(2)
+    }
+}
+
+struct Compound<'a> {
+    printer: &'a mut Printer,
+    hint: Hint,
+    hints: &'a Hints,
+    index: usize,
+    key: Option<String>,
+    // indentation levels to undo when the compound ends
+    depth: usize,
+}
+
+impl Compound<'_> {
+    fn element<Type: Serialize + ?Sized>(&mut self, value: &Type) -> Result<(), Error> {
+        self.printer
+            .write(format!("{}[{}]: ", self.printer.indentation(), self.index).as_str())
+            .map_err(to_error)?;
+        self.index += 1;
+        value.serialize(ValueSerializer {
+            printer: &mut *self.printer,
+            hint: self.hint,
+            hints: self.hints,
+        })
+    }
+
+    fn entry<Type: Serialize + ?Sized>(&mut self, key: &str, value: &Type) -> Result<(), Error> {
+        if self.printer.is_null_skipped(value) {
+            return Ok(());
+        }
+        self.printer.write_key(key).map_err(to_error)?;
+        value.serialize(ValueSerializer {
+            printer: &mut *self.printer,
+            hint: self.hints.resolve(key),
+            hints: self.hints,
+        })
+    }
+
+    fn close(self) -> Result<(), Error> {
+        for _ in 0..self.depth {
+            self.printer.shift_left();
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<Type: Serialize + ?Sized>(&mut self, value: &Type) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<Type: Serialize + ?Sized>(&mut self, value: &Type) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<Type: Serialize + ?Sized>(&mut self, value: &Type) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<Type: Serialize + ?Sized>(&mut self, value: &Type) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<Key: Serialize + ?Sized>(&mut self, key: &Key) -> Result<(), Error> {
+        self.key = Some(map_key(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<Type: Serialize + ?Sized>(&mut self, value: &Type) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::custom("map value without a key"))?;
+        self.entry(key.as_str(), value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<Type: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &Type,
+    ) -> Result<(), Error> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<Type: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &Type,
+    ) -> Result<(), Error> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.close()
+    }
+}
+
+struct NullProbe;
+
+fn not_null<Ok>() -> Result<Ok, Error> {
+    Err(Error::custom("not null"))
+}
+
+impl ser::Serializer for NullProbe {
+    type Ok = bool;
+    type Error = Error;
+    type SerializeSeq = Impossible<bool, Error>;
+    type SerializeTuple = Impossible<bool, Error>;
+    type SerializeTupleStruct = Impossible<bool, Error>;
+    type SerializeTupleVariant = Impossible<bool, Error>;
+    type SerializeMap = Impossible<bool, Error>;
+    type SerializeStruct = Impossible<bool, Error>;
+    type SerializeStructVariant = Impossible<bool, Error>;
+
+    fn serialize_bool(self, _value: bool) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_i8(self, _value: i8) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_i16(self, _value: i16) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_i32(self, _value: i32) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_i64(self, _value: i64) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_u8(self, _value: u8) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_u16(self, _value: u16) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_u32(self, _value: u32) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_u64(self, _value: u64) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<bool, Error> {
+        Ok(!value.is_finite())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<bool, Error> {
+        Ok(!value.is_finite())
+    }
+
+    fn serialize_char(self, _value: char) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_str(self, _value: &str) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_none(self) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn serialize_some<Type: Serialize + ?Sized>(self, value: &Type) -> Result<bool, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<bool, Error> {
+        Ok(true)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_newtype_struct<Type: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &Type,
+    ) -> Result<bool, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<Type: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &Type,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn serialize_seq(self, _length: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        not_null()
+    }
+
+    fn serialize_tuple(self, _length: usize) -> Result<Self::SerializeTuple, Error> {
+        not_null()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _length: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        not_null()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _length: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        not_null()
+    }
+
+    fn serialize_map(self, _length: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        not_null()
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _length: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        not_null()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _length: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        not_null()
+    }
+}
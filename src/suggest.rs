@@ -0,0 +1,45 @@
+// "Did you mean" matching for names typed by a user, such as workflow or
+// branch names, where a typo should still find the intended entry
+
+// Levenshtein distance over characters
+pub fn edit_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    let mut previous: Vec<usize> = (0..=second.len()).collect();
+    let mut current = vec![0; second.len() + 1];
+    for (index, first_char) in first.chars().enumerate() {
+        current[0] = index + 1;
+        for (other_index, second_char) in second.iter().enumerate() {
+            let substitution = previous[other_index] + usize::from(first_char != *second_char);
+            current[other_index + 1] = substitution
+                .min(previous[other_index + 1] + 1)
+                .min(current[other_index] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[second.len()]
+}
+
+// Typos allowed before a candidate no longer counts as a suggestion
+fn max_distance(input: &str) -> usize {
+    (input.chars().count() / 3).max(1)
+}
+
+// The distance between `input` and `candidate` ignoring case, if it is close
+// enough to suggest
+pub(crate) fn close_distance(input: &str, candidate: &str) -> Option<usize> {
+    let input = input.to_lowercase();
+    let distance = edit_distance(input.as_str(), candidate.to_lowercase().as_str());
+    (distance <= max_distance(input.as_str())).then_some(distance)
+}
+
+// Candidates close to `input`, closest first. Comparison ignores case.
+pub fn suggest<'a>(input: &str, candidates: &[&'a str]) -> Vec<&'a str> {
+    let mut close: Vec<(usize, &'a str)> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            close_distance(input, candidate).map(|distance| (distance, *candidate))
+        })
+        .collect();
+    close.sort_by_key(|(distance, _)| *distance);
+    close.into_iter().map(|(_, candidate)| candidate).collect()
+}
@@ -0,0 +1,127 @@
+use crate::format;
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use serde::{Deserialize, Serialize};
+use std::{path::Path, sync::Arc};
+use strum::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryStep {
+    pub name: Arc<str>,
+    pub duration: std::time::Duration,
+    pub status: StepStatus,
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Summary {
+    pub name: Arc<str>,
+    pub steps: Vec<SummaryStep>,
+}
+
+// Captured output carries color codes, and XML 1.0 allows no control
+// characters other than tab and line breaks
+pub(crate) fn escape_xml(value: &str) -> String {
+    let value = format::strip_ansi_codes(value);
+    let mut result = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '\t' | '\n' | '\r' => result.push(character),
+            _ if character.is_control() => {}
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(character),
+        }
+    }
+    result
+}
+
+impl Summary {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn add_step(
+        &mut self,
+        name: &str,
+        duration: std::time::Duration,
+        status: StepStatus,
+        output: Option<String>,
+    ) {
+        self.steps.push(SummaryStep {
+            name: name.into(),
+            duration,
+            status,
+            output,
+        });
+    }
+
+    pub fn count(&self, status: StepStatus) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| step.status == status)
+            .count()
+    }
+
+    pub fn duration(&self) -> std::time::Duration {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+
+    pub fn to_junit_xml(&self) -> String {
+        let name = escape_xml(&self.name);
+        let totals = format!(
+            "tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+            self.steps.len(),
+            self.count(StepStatus::Failed),
+            self.count(StepStatus::Skipped),
+            self.duration().as_secs_f64()
+        );
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!("<testsuites name=\"{name}\" {totals}>\n"));
+        xml.push_str(&format!("  <testsuite name=\"{name}\" {totals}>\n"));
+        for step in self.steps.iter() {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{name}\" time=\"{:.3}\"",
+                escape_xml(&step.name),
+                step.duration.as_secs_f64()
+            ));
+            let output = step.output.as_deref().map(escape_xml);
+            match (step.status, output) {
+                (StepStatus::Passed, None) => xml.push_str("/>\n"),
+                (StepStatus::Passed, Some(output)) => xml.push_str(&format!(
+                    ">\n      <system-out>{output}</system-out>\n    </testcase>\n"
+                )),
+                (StepStatus::Failed, output) => xml.push_str(&format!(
+                    ">\n      <failure message=\"{} failed\">{}</failure>\n    </testcase>\n",
+                    escape_xml(&step.name),
+                    output.unwrap_or_default()
+                )),
+                (StepStatus::Skipped, _) => xml.push_str(">\n      <skipped/>\n    </testcase>\n"),
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+
+    pub fn write_junit_xml(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_junit_xml()).context(format_context!(
+            "while writing JUnit report {}",
+            path.display()
+        ))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{format, Printer};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolVersion {
+    pub name: String,
+    pub path: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemReport {
+    pub os: String,
+    pub arch: String,
+    pub cpu_count: usize,
+    pub total_memory_bytes: Option<u64>,
+    pub available_memory_bytes: Option<u64>,
+    pub disk_free_bytes: Option<u64>,
+    pub tools: Vec<ToolVersion>,
+}
+
+pub fn find_in_path(name: &str) -> Option<std::path::PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .flat_map(|directory| {
+            let candidate = directory.join(name);
+            let exe = directory.join(format!("{name}{}", std::env::consts::EXE_SUFFIX));
+            [candidate, exe]
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+fn tool_version(name: &str) -> ToolVersion {
+    let path = find_in_path(name);
+    let version = path.as_ref().and_then(|path| {
+        let output = std::process::Command::new(path)
+            .arg("--version")
+            .stdin(std::process::Stdio::null())
+            .output()
+            .ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        stdout
+            .lines()
+            .chain(stderr.lines())
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+    });
+    ToolVersion {
+        name: name.to_string(),
+        path: path.map(|path| path.display().to_string()),
+        version,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn memory_bytes() -> (Option<u64>, Option<u64>) {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    let read_field = |field: &str| {
+        meminfo
+            .lines()
+            .find(|line| line.starts_with(field))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|kilobytes| kilobytes.parse::<u64>().ok())
+            .map(|kilobytes| kilobytes * 1024)
+    };
+    (read_field("MemTotal:"), read_field("MemAvailable:"))
+}
+
+#[cfg(target_os = "macos")]
+fn memory_bytes() -> (Option<u64>, Option<u64>) {
+    let total = std::process::Command::new("sysctl")
+        .args(["-n", "hw.memsize"])
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok());
+    (total, None)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn memory_bytes() -> (Option<u64>, Option<u64>) {
+    (None, None)
+}
+
+#[cfg(unix)]
+fn disk_free_bytes(path: &std::path::Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        #[allow(clippy::unnecessary_cast)]
+        Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
+
+#[cfg(not(unix))]
+fn disk_free_bytes(_path: &std::path::Path) -> Option<u64> {
+    None
+}
+
+impl SystemReport {
+    pub fn collect(workspace: &std::path::Path, tools: &[&str]) -> Self {
+        let (total_memory_bytes, available_memory_bytes) = memory_bytes();
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpu_count: std::thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(1),
+            total_memory_bytes,
+            available_memory_bytes,
+            disk_free_bytes: disk_free_bytes(workspace),
+            tools: tools.iter().map(|name| tool_version(name)).collect(),
+        }
+    }
+
+    fn rows(&self) -> Vec<(String, String)> {
+        let humanized = |bytes: Option<u64>| {
+            bytes
+                .map(format::humanize_bytes)
+                .unwrap_or_else(|| "unknown".to_string())
+        };
+        let mut rows = vec![
+            ("os".to_string(), self.os.clone()),
+            ("arch".to_string(), self.arch.clone()),
+            ("cpus".to_string(), self.cpu_count.to_string()),
+            ("memory".to_string(), humanized(self.total_memory_bytes)),
+            (
+                "memory available".to_string(),
+                humanized(self.available_memory_bytes),
+            ),
+            ("disk free".to_string(), humanized(self.disk_free_bytes)),
+        ];
+        for tool in self.tools.iter() {
+            let value = match (tool.version.as_ref(), tool.path.as_ref()) {
+                (Some(version), _) => version.clone(),
+                (None, Some(path)) => path.clone(),
+                (None, None) => "not found".to_string(),
+            };
+            rows.push((tool.name.clone(), value));
+        }
+        rows
+    }
+}
+
+impl Printer {
+    pub fn system_report(
+        &mut self,
+        workspace: &std::path::Path,
+        tools: &[&str],
+    ) -> anyhow::Result<SystemReport> {
+        let report = SystemReport::collect(workspace, tools);
+        let rows = report.rows();
+        let key_width = rows.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+        let indent = self.indentation();
+        let mut table = String::new();
+        for (key, value) in rows.iter() {
+            table.push_str(&format!(
+                "{indent}{}  {value}\n",
+                format!("{key:key_width$}").if_supports_color(Stdout, |text| text.bold())
+            ));
+        }
+        self.write(table.as_str()).context(format_context!(""))?;
+        Ok(report)
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{ExecuteOptions, MultiProgressBar, Printer};
+use anyhow::Context;
+use anyhow_source_location::{format_context, format_error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub type TemplateVariables = HashMap<String, String>;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommandTemplate {
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    #[serde(default)]
+    pub environment: Vec<(String, String)>,
+}
+
+// Replaces `{name}` with its value. `{{` and `}}` produce literal braces.
+pub fn substitute(text: &str, variables: &TemplateVariables) -> anyhow::Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                result.push('{');
+            }
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut is_closed = false;
+                for character in characters.by_ref() {
+                    if character == '}' {
+                        is_closed = true;
+                        break;
+                    }
+                    name.push(character);
+                }
+                if !is_closed {
+                    return Err(format_error!("Unclosed placeholder in `{text}`"));
+                }
+                let value = variables
+                    .get(name.as_str())
+                    .ok_or(format_error!("Unknown placeholder {{{name}}} in `{text}`"))?;
+                result.push_str(value);
+            }
+            '}' => return Err(format_error!("Unmatched `}}` in `{text}`")),
+            _ => result.push(character),
+        }
+    }
+    Ok(result)
+}
+
+impl CommandTemplate {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn arg(mut self, argument: &str) -> Self {
+        self.arguments.push(argument.to_string());
+        self
+    }
+
+    pub fn resolve(
+        &self,
+        variables: &TemplateVariables,
+        mut options: ExecuteOptions,
+    ) -> anyhow::Result<(String, ExecuteOptions)> {
+        let command = substitute(&self.command, variables).context(format_context!(""))?;
+        for argument in self.arguments.iter() {
+            options.add_argument(substitute(argument, variables).context(format_context!(""))?);
+        }
+        if let Some(directory) = self.working_directory.as_ref() {
+            options.set_working_directory(
+                substitute(directory, variables).context(format_context!(""))?,
+            );
+        }
+        for (key, value) in self.environment.iter() {
+            let value = substitute(value, variables).context(format_context!(""))?;
+            options
+                .environment
+                .push((key.as_str().into(), value.into()));
+        }
+        Ok((command, options))
+    }
+}
+
+impl Printer {
+    pub fn execute_template(
+        &mut self,
+        template: &CommandTemplate,
+        variables: &TemplateVariables,
+        options: ExecuteOptions,
+    ) -> anyhow::Result<Option<String>> {
+        let (command, options) = template
+            .resolve(variables, options)
+            .context(format_context!("while resolving {}", template.command))?;
+        self.execute_process(command.as_str(), options)
+    }
+}
+
+impl MultiProgressBar {
+    pub fn execute_template(
+        &mut self,
+        template: &CommandTemplate,
+        variables: &TemplateVariables,
+        options: ExecuteOptions,
+    ) -> anyhow::Result<Option<String>> {
+        let (command, options) = template
+            .resolve(variables, options)
+            .context(format_context!("while resolving {}", template.command))?;
+        self.execute_process(command.as_str(), options)
+    }
+}
@@ -0,0 +1,253 @@
+// Formatting that only needs a std::fmt::Write: no terminal, process or
+// printer state. The printer renders through these, so a web UI compiled to
+// WASM produces exactly the same text.
+
+use crate::{capabilities::Glyphs, format};
+use std::{
+    fmt::{self, Write},
+    time::Duration,
+};
+
+pub fn humanize_duration(duration: Duration) -> String {
+    let total_seconds = duration.as_secs();
+    if total_seconds >= 3600 {
+        format!(
+            "{}h {:02}m",
+            total_seconds / 3600,
+            (total_seconds % 3600) / 60
+        )
+    } else if total_seconds >= 60 {
+        format!("{}m {:02}s", total_seconds / 60, total_seconds % 60)
+    } else if duration.as_millis() >= 1000 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+pub fn humanize_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+pub fn humanize_count(count: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000_000_000, "G"), (1_000_000, "M"), (1_000, "k")];
+    for (threshold, suffix) in UNITS {
+        if count >= threshold {
+            return format!("{:.1}{suffix}", count as f64 / threshold as f64);
+        }
+    }
+    count.to_string()
+}
+
+// Builds markdown into any writer. Markdown on the printer writes through this.
+pub struct MarkdownText<Output: Write> {
+    output: Output,
+}
+
+impl<Output: Write> MarkdownText<Output> {
+    pub fn new(output: Output) -> Self {
+        Self { output }
+    }
+
+    pub fn into_inner(self) -> Output {
+        self.output
+    }
+
+    pub fn heading(&mut self, level: u8, content: &str) -> fmt::Result {
+        write!(self.output, "{} {content}\n\n", "#".repeat(level as usize))
+    }
+
+    pub fn list(&mut self, items: &[&str]) -> fmt::Result {
+        for item in items {
+            writeln!(self.output, "- {item}")?;
+        }
+        writeln!(self.output)
+    }
+
+    pub fn list_item(&mut self, level: u8, item: &str) -> fmt::Result {
+        let level = if level == 0 { 1_usize } else { level as usize };
+        writeln!(self.output, "{}- {item}", " ".repeat((level - 1) * 2))
+    }
+
+    pub fn bold(&mut self, content: &str) -> fmt::Result {
+        write!(self.output, "**{content}**")
+    }
+
+    pub fn italic(&mut self, content: &str) -> fmt::Result {
+        write!(self.output, "*{content}*")
+    }
+
+    pub fn strikethrough(&mut self, content: &str) -> fmt::Result {
+        write!(self.output, "~~{content}~~")
+    }
+
+    pub fn code(&mut self, content: &str) -> fmt::Result {
+        write!(self.output, "`{content}`")
+    }
+
+    pub fn code_block(&mut self, code_type: &str, content: &str) -> fmt::Result {
+        write!(self.output, "```{code_type}\n{content}\n```")
+    }
+
+    pub fn paragraph(&mut self, content: &str) -> fmt::Result {
+        write!(self.output, "{content}\n\n")
+    }
+
+    pub fn table(&mut self, table: &Table) -> fmt::Result {
+        table.write_markdown(&mut self.output)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn escape_table_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+impl Table {
+    pub fn new<Header: AsRef<str>>(headers: &[Header]) -> Self {
+        Self {
+            headers: headers
+                .iter()
+                .map(|header| header.as_ref().to_string())
+                .collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    // Missing cells are left empty and extra cells are dropped
+    pub fn add_row<Cell: AsRef<str>>(&mut self, cells: &[Cell]) {
+        let mut row: Vec<String> = cells
+            .iter()
+            .take(self.headers.len())
+            .map(|cell| cell.as_ref().to_string())
+            .collect();
+        row.resize(self.headers.len(), String::new());
+        self.rows.push(row);
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        self.headers
+            .iter()
+            .enumerate()
+            .map(|(column, header)| {
+                self.rows
+                    .iter()
+                    .map(|row| format::visible_width(&row[column]))
+                    .chain(std::iter::once(format::visible_width(header)))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    // Columns padded to their widest cell with a dashed rule under the headers
+    pub fn write_plain<Output: Write>(&self, output: &mut Output, indent: &str) -> fmt::Result {
+        let widths = self.column_widths();
+        let write_row = |output: &mut Output, cells: &[String]| {
+            let mut line = String::from(indent);
+            for (cell, width) in cells.iter().zip(widths.iter()) {
+                let padding = width - format::visible_width(cell);
+                write!(line, "{cell}{:padding$}  ", "")?;
+            }
+            writeln!(output, "{}", line.trim_end())
+        };
+        write_row(output, &self.headers)?;
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        write_row(output, &rule)?;
+        for row in self.rows.iter() {
+            write_row(output, row)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_markdown<Output: Write>(&self, output: &mut Output) -> fmt::Result {
+        let write_row = |output: &mut Output, cells: &mut dyn Iterator<Item = String>| {
+            for cell in cells {
+                write!(output, "| {cell} ")?;
+            }
+            writeln!(output, "|")
+        };
+        write_row(
+            output,
+            &mut self.headers.iter().map(|header| escape_table_cell(header)),
+        )?;
+        write_row(output, &mut self.headers.iter().map(|_| "---".to_string()))?;
+        for row in self.rows.iter() {
+            write_row(output, &mut row.iter().map(|cell| escape_table_cell(cell)))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TreeNode {
+    pub label: String,
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn child(mut self, child: TreeNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    // The label on the first line, then each child on box drawing guides
+    pub fn write<Output: Write>(&self, output: &mut Output, indent: &str) -> fmt::Result {
+        self.write_with(output, indent, &Glyphs::UNICODE)
+    }
+
+    pub(crate) fn write_with<Output: Write>(
+        &self,
+        output: &mut Output,
+        indent: &str,
+        glyphs: &Glyphs,
+    ) -> fmt::Result {
+        writeln!(output, "{indent}{}", self.label)?;
+        self.write_children(output, indent, glyphs, &mut String::new())
+    }
+
+    fn write_children<Output: Write>(
+        &self,
+        output: &mut Output,
+        indent: &str,
+        glyphs: &Glyphs,
+        guides: &mut String,
+    ) -> fmt::Result {
+        for (index, child) in self.children.iter().enumerate() {
+            let is_last = index + 1 == self.children.len();
+            let branch = if is_last {
+                glyphs.tree_last
+            } else {
+                glyphs.tree_branch
+            };
+            writeln!(output, "{indent}{guides}{branch}{}", child.label)?;
+            let length = guides.len();
+            guides.push_str(if is_last { "    " } else { glyphs.tree_guide });
+            child.write_children(output, indent, glyphs, guides)?;
+            guides.truncate(length);
+        }
+        Ok(())
+    }
+}
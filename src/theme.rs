@@ -0,0 +1,475 @@
+use crate::{
+    capabilities::{Capabilities, Glyphs},
+    HeadingStyle, Level,
+};
+#[cfg(feature = "toml")]
+use anyhow::Context;
+#[cfg(feature = "toml")]
+use anyhow_source_location::format_context;
+use owo_colors::{OwoColorize, Stream::Stdout};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeColor {
+    #[default]
+    Default,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Fixed(u8),
+    Rgb(u8, u8, u8),
+}
+
+// How many colors the terminal can show. Theme colors beyond the depth are
+// replaced with the nearest color it has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorDepth {
+    Ansi16,
+    #[default]
+    Ansi256,
+    Truecolor,
+}
+
+const NAMED_COLORS: [(ThemeColor, (u8, u8, u8)); 8] = [
+    (ThemeColor::Black, (0, 0, 0)),
+    (ThemeColor::Red, (205, 0, 0)),
+    (ThemeColor::Green, (0, 205, 0)),
+    (ThemeColor::Yellow, (205, 205, 0)),
+    (ThemeColor::Blue, (0, 0, 238)),
+    (ThemeColor::Magenta, (205, 0, 205)),
+    (ThemeColor::Cyan, (0, 205, 205)),
+    (ThemeColor::White, (229, 229, 229)),
+];
+
+const BRIGHT_COLORS: [(u8, u8, u8); 8] = [
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+// channel values of the xterm 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ThemeColor {
+    pub fn paint(&self, text: &str) -> String {
+        match self {
+            ThemeColor::Default => text.to_string(),
+            ThemeColor::Black => text
+                .if_supports_color(Stdout, |text| text.black())
+                .to_string(),
+            ThemeColor::Red => text
+                .if_supports_color(Stdout, |text| text.red())
+                .to_string(),
+            ThemeColor::Green => text
+                .if_supports_color(Stdout, |text| text.green())
+                .to_string(),
+            ThemeColor::Yellow => text
+                .if_supports_color(Stdout, |text| text.yellow())
+                .to_string(),
+            ThemeColor::Blue => text
+                .if_supports_color(Stdout, |text| text.blue())
+                .to_string(),
+            ThemeColor::Magenta => text
+                .if_supports_color(Stdout, |text| text.magenta())
+                .to_string(),
+            ThemeColor::Cyan => text
+                .if_supports_color(Stdout, |text| text.cyan())
+                .to_string(),
+            ThemeColor::White => text
+                .if_supports_color(Stdout, |text| text.white())
+                .to_string(),
+            ThemeColor::Fixed(color) => text
+                .if_supports_color(Stdout, |text| {
+                    text.color(owo_colors::XtermColors::from(*color))
+                })
+                .to_string(),
+            ThemeColor::Rgb(red, green, blue) => text
+                .if_supports_color(Stdout, |text| text.truecolor(*red, *green, *blue))
+                .to_string(),
+        }
+    }
+
+    // Approximate color on the standard xterm palette, none for the default
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        match self {
+            ThemeColor::Default => None,
+            ThemeColor::Rgb(red, green, blue) => Some((*red, *green, *blue)),
+            ThemeColor::Fixed(index) => Some(xterm_rgb(*index)),
+            named => NAMED_COLORS
+                .iter()
+                .find(|(color, _)| color == named)
+                .map(|(_, rgb)| *rgb),
+        }
+    }
+
+    pub fn downgrade(&self, depth: ColorDepth) -> ThemeColor {
+        match (self, depth) {
+            (_, ColorDepth::Truecolor) => *self,
+            (ThemeColor::Rgb(red, green, blue), ColorDepth::Ansi256) => {
+                ThemeColor::Fixed(xterm_index((*red, *green, *blue)))
+            }
+            // the first 16 entries are the named colors and their bright forms
+            (ThemeColor::Fixed(index), ColorDepth::Ansi16) if *index < 16 => {
+                NAMED_COLORS[usize::from(*index % 8)].0
+            }
+            (ThemeColor::Fixed(_) | ThemeColor::Rgb(..), ColorDepth::Ansi16) => {
+                let rgb = self.rgb().unwrap_or_default();
+                NAMED_COLORS
+                    .iter()
+                    .min_by_key(|(_, named)| distance(rgb, *named))
+                    .map(|(color, _)| *color)
+                    .unwrap_or_default()
+            }
+            _ => *self,
+        }
+    }
+
+    // Color `at` (0 to 1) of the way along evenly spaced stops
+    pub fn blend(stops: &[ThemeColor], at: f32) -> ThemeColor {
+        let stops: Vec<(u8, u8, u8)> = stops.iter().filter_map(ThemeColor::rgb).collect();
+        let (Some(first), Some(last)) = (stops.first(), stops.last()) else {
+            return ThemeColor::Default;
+        };
+        let position = at.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        if stops.len() == 1 || position <= 0.0 {
+            return ThemeColor::Rgb(first.0, first.1, first.2);
+        }
+        if position >= (stops.len() - 1) as f32 {
+            return ThemeColor::Rgb(last.0, last.1, last.2);
+        }
+        let index = position as usize;
+        let (from, to) = (stops[index], stops[index + 1]);
+        let fraction = position - index as f32;
+        let channel = |from: u8, to: u8| {
+            (f32::from(from) + (f32::from(to) - f32::from(from)) * fraction).round() as u8
+        };
+        ThemeColor::Rgb(
+            channel(from.0, to.0),
+            channel(from.1, to.1),
+            channel(from.2, to.2),
+        )
+    }
+
+    // Bold and in this color, written straight into a formatter
+    pub(crate) fn bold<'a>(&self, text: &'a str) -> BoldText<'a> {
+        let style = owo_colors::Style::new().bold();
+        let style = match self {
+            ThemeColor::Default => style,
+            ThemeColor::Black => style.black(),
+            ThemeColor::Red => style.red(),
+            ThemeColor::Green => style.green(),
+            ThemeColor::Yellow => style.yellow(),
+            ThemeColor::Blue => style.blue(),
+            ThemeColor::Magenta => style.magenta(),
+            ThemeColor::Cyan => style.cyan(),
+            ThemeColor::White => style.white(),
+            ThemeColor::Fixed(color) => style.color(owo_colors::XtermColors::from(*color)),
+            ThemeColor::Rgb(red, green, blue) => style.truecolor(*red, *green, *blue),
+        };
+        BoldText { style, text }
+    }
+
+    // name understood by indicatif templates, e.g. `{bar:.cyan/blue}`
+    #[cfg(feature = "progress")]
+    pub(crate) fn template_name(&self) -> Option<String> {
+        match self {
+            ThemeColor::Default => None,
+            ThemeColor::Black => Some("black".to_string()),
+            ThemeColor::Red => Some("red".to_string()),
+            ThemeColor::Green => Some("green".to_string()),
+            ThemeColor::Yellow => Some("yellow".to_string()),
+            ThemeColor::Blue => Some("blue".to_string()),
+            ThemeColor::Magenta => Some("magenta".to_string()),
+            ThemeColor::Cyan => Some("cyan".to_string()),
+            ThemeColor::White => Some("white".to_string()),
+            ThemeColor::Fixed(color) => Some(color.to_string()),
+            // templates only take palette colors
+            ThemeColor::Rgb(red, green, blue) => {
+                Some(xterm_index((*red, *green, *blue)).to_string())
+            }
+        }
+    }
+}
+
+fn distance(left: (u8, u8, u8), right: (u8, u8, u8)) -> u32 {
+    let channel = |left: u8, right: u8| (i32::from(left) - i32::from(right)).unsigned_abs().pow(2);
+    channel(left.0, right.0) + channel(left.1, right.1) + channel(left.2, right.2)
+}
+
+fn xterm_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=7 => NAMED_COLORS[usize::from(index)].1,
+        8..=15 => BRIGHT_COLORS[usize::from(index - 8)],
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_LEVELS[usize::from(cube / 36)],
+                CUBE_LEVELS[usize::from(cube / 6 % 6)],
+                CUBE_LEVELS[usize::from(cube % 6)],
+            )
+        }
+        _ => {
+            let gray = 8 + 10 * (index - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+// nearest entry of the color cube or the gray ramp, skipping the first 16
+// which terminals often remap
+fn xterm_index(rgb: (u8, u8, u8)) -> u8 {
+    let level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|level| CUBE_LEVELS[*level].abs_diff(channel))
+            .unwrap_or_default() as u8
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(rgb, xterm_rgb(gray)) < distance(rgb, xterm_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+pub(crate) struct BoldText<'a> {
+    style: owo_colors::Style,
+    text: &'a str,
+}
+
+impl std::fmt::Display for BoldText<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{}",
+            self.text
+                .if_supports_color(Stdout, |text| text.style(self.style))
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelColors {
+    pub trace: ThemeColor,
+    pub debug: ThemeColor,
+    pub message: ThemeColor,
+    pub info: ThemeColor,
+    pub app: ThemeColor,
+    pub warning: ThemeColor,
+    pub error: ThemeColor,
+}
+
+impl Default for LevelColors {
+    fn default() -> Self {
+        Self {
+            trace: ThemeColor::Default,
+            debug: ThemeColor::Default,
+            message: ThemeColor::Default,
+            info: ThemeColor::Default,
+            app: ThemeColor::Default,
+            warning: ThemeColor::Yellow,
+            error: ThemeColor::Red,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub levels: LevelColors,
+    pub heading: ThemeColor,
+    pub subheading: ThemeColor,
+    pub heading_style: HeadingStyle,
+    pub success: ThemeColor,
+    pub progress_bar: ThemeColor,
+    pub progress_background: ThemeColor,
+    // two or more stops draw the filled part of the bar as a gradient
+    pub progress_gradient: Vec<ThemeColor>,
+    pub progress_chars: Option<String>,
+    pub indent_guide: String,
+    #[serde(skip)]
+    pub(crate) glyphs: Glyphs,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            levels: LevelColors::default(),
+            heading: ThemeColor::Yellow,
+            subheading: ThemeColor::Default,
+            heading_style: HeadingStyle::default(),
+            success: ThemeColor::Green,
+            progress_bar: ThemeColor::Cyan,
+            progress_background: ThemeColor::Blue,
+            progress_gradient: Vec::new(),
+            progress_chars: None,
+            indent_guide: "│".to_string(),
+            glyphs: Glyphs::default(),
+        }
+    }
+}
+
+impl Theme {
+    #[cfg(feature = "toml")]
+    pub fn from_toml(content: &str) -> anyhow::Result<Self> {
+        toml::from_str(content).context(format_context!("while parsing theme"))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .context(format_context!("while reading theme {}", path.display()))?;
+        Self::from_toml(content.as_str()).context(format_context!("{}", path.display()))
+    }
+
+    pub fn level_color(&self, level: Level) -> ThemeColor {
+        match level {
+            Level::Trace => self.levels.trace,
+            Level::Debug => self.levels.debug,
+            Level::Message => self.levels.message,
+            Level::Info => self.levels.info,
+            Level::App => self.levels.app,
+            Level::Warning => self.levels.warning,
+            Level::Error => self.levels.error,
+            Level::Silent => ThemeColor::Default,
+        }
+    }
+
+    // every color replaced with the nearest one available at `depth`
+    pub fn downgraded(&self, depth: ColorDepth) -> Theme {
+        let levels = &self.levels;
+        Theme {
+            levels: LevelColors {
+                trace: levels.trace.downgrade(depth),
+                debug: levels.debug.downgrade(depth),
+                message: levels.message.downgrade(depth),
+                info: levels.info.downgrade(depth),
+                app: levels.app.downgrade(depth),
+                warning: levels.warning.downgrade(depth),
+                error: levels.error.downgrade(depth),
+            },
+            heading: self.heading.downgrade(depth),
+            subheading: self.subheading.downgrade(depth),
+            success: self.success.downgrade(depth),
+            progress_bar: self.progress_bar.downgrade(depth),
+            progress_background: self.progress_background.downgrade(depth),
+            ..self.clone()
+        }
+    }
+
+    // colors and symbols the terminal can show
+    pub(crate) fn supported(&self, capabilities: &Capabilities) -> Theme {
+        Theme {
+            glyphs: capabilities.glyphs(),
+            ..self.downgraded(capabilities.color_depth())
+        }
+    }
+
+    // a custom guide is kept unless it needs unicode the terminal lacks
+    pub(crate) fn indent_guide(&self) -> &str {
+        if self.glyphs == Glyphs::ASCII && !self.indent_guide.is_ascii() {
+            self.glyphs.indent_guide
+        } else {
+            self.indent_guide.as_str()
+        }
+    }
+
+    #[cfg(feature = "progress")]
+    pub(crate) fn is_gradient(&self) -> bool {
+        self.progress_gradient
+            .iter()
+            .filter(|color| color.rgb().is_some())
+            .count()
+            >= 2
+    }
+
+    pub(crate) fn heading_color(&self, level: usize) -> ThemeColor {
+        if level == 1 {
+            self.heading
+        } else {
+            self.subheading
+        }
+    }
+
+    // the `{bar}` placeholder with the theme's colors applied
+    #[cfg(feature = "progress")]
+    pub(crate) fn bar_placeholder(&self) -> String {
+        match (
+            self.progress_bar.template_name(),
+            self.progress_background.template_name(),
+        ) {
+            (Some(bar), Some(background)) => format!("{{bar:.{bar}/{background}}}"),
+            (Some(bar), None) => format!("{{bar:.{bar}}}"),
+            (None, _) => "{bar}".to_string(),
+        }
+    }
+}
+
+pub const PALETTE_ENV_VAR: &str = "PRINTER_PALETTE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    #[default]
+    Default,
+    Deuteranopia,
+    SolarizedLight,
+}
+
+impl Palette {
+    // reads PRINTER_PALETTE, e.g. `deuteranopia` or `solarized-light`
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var(PALETTE_ENV_VAR).ok()?;
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "default" => Some(Palette::Default),
+            "deuteranopia" => Some(Palette::Deuteranopia),
+            "solarized_light" => Some(Palette::SolarizedLight),
+            _ => None,
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        match self {
+            Palette::Default => Theme::default(),
+            // Okabe-Ito colors: blue and orange instead of green and red
+            Palette::Deuteranopia => Theme {
+                levels: LevelColors {
+                    warning: ThemeColor::Fixed(220),
+                    error: ThemeColor::Fixed(208),
+                    ..Default::default()
+                },
+                heading: ThemeColor::Fixed(39),
+                success: ThemeColor::Fixed(39),
+                progress_bar: ThemeColor::Fixed(39),
+                progress_background: ThemeColor::Fixed(208),
+                ..Default::default()
+            },
+            Palette::SolarizedLight => Theme {
+                levels: LevelColors {
+                    warning: ThemeColor::Fixed(136),
+                    error: ThemeColor::Fixed(160),
+                    ..Default::default()
+                },
+                heading: ThemeColor::Fixed(33),
+                success: ThemeColor::Fixed(64),
+                progress_bar: ThemeColor::Fixed(37),
+                progress_background: ThemeColor::Fixed(247),
+                ..Default::default()
+            },
+        }
+    }
+}
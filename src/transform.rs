@@ -0,0 +1,46 @@
+use crate::{Level, Printer};
+use std::sync::Arc;
+
+// The parts of a log line a transformer may rewrite before colors are applied
+#[derive(Debug, Clone)]
+pub struct LogParts {
+    pub tag: String,
+    pub message: String,
+}
+
+type Transformer = Arc<dyn Fn(&mut LogParts) + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct LevelTransformers {
+    transformers: Vec<(Level, Transformer)>,
+}
+
+impl LevelTransformers {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.transformers.is_empty()
+    }
+
+    pub(crate) fn apply(&self, level: Level, parts: &mut LogParts) {
+        for (transformer_level, transformer) in self.transformers.iter() {
+            if *transformer_level == level {
+                transformer(parts);
+            }
+        }
+    }
+}
+
+impl Printer {
+    // e.g. prefix errors with a glyph or uppercase the tags of a level
+    pub fn add_level_transformer<Function>(&mut self, level: Level, transformer: Function)
+    where
+        Function: Fn(&mut LogParts) + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.transformers)
+            .transformers
+            .push((level, Arc::new(transformer)));
+    }
+
+    pub fn clear_level_transformers(&mut self) {
+        self.transformers = Arc::new(LevelTransformers::default());
+    }
+}
@@ -0,0 +1,186 @@
+use crossterm::{cursor, terminal, ExecutableCommand, QueueableCommand};
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+const BAR_WIDTH: usize = 20;
+const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+#[derive(Default)]
+struct TuiState {
+    lines: Vec<String>,
+    jobs: Vec<(usize, indicatif::ProgressBar)>,
+    next_id: usize,
+}
+
+pub struct TuiRenderer {
+    state: Arc<Mutex<TuiState>>,
+    is_running: Arc<AtomicBool>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+pub(crate) struct TuiJob {
+    renderer: Arc<TuiRenderer>,
+    id: usize,
+}
+
+impl TuiJob {
+    pub(crate) fn println(&self, message: &str) {
+        self.renderer.println(message);
+    }
+}
+
+impl Drop for TuiJob {
+    fn drop(&mut self) {
+        self.renderer.finish_job(self.id);
+    }
+}
+
+fn is_supported() -> bool {
+    is_supported_with(
+        std::env::var("TERM").ok().as_deref(),
+        console::Term::stdout().is_term(),
+    )
+}
+
+pub(crate) fn is_supported_with(term: Option<&str>, is_terminal: bool) -> bool {
+    term != Some("dumb") && is_terminal
+}
+
+fn format_job(progress: &indicatif::ProgressBar, width: usize) -> String {
+    let position = progress.position();
+    let bar = match progress.length() {
+        Some(length) if length > 0 => {
+            let filled = ((position.min(length) * BAR_WIDTH as u64) / length) as usize;
+            format!(
+                "[{}{}] {position}/{length}",
+                "#".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled)
+            )
+        }
+        _ => format!("[{}]", "*".repeat(BAR_WIDTH)),
+    };
+    let line = format!(
+        "{:>4}s {bar} {} {}",
+        progress.elapsed().as_secs(),
+        progress.prefix(),
+        progress.message()
+    );
+    line.chars().take(width).collect()
+}
+
+fn format_finished_job(progress: &indicatif::ProgressBar) -> String {
+    format!(
+        "{} {} ({})",
+        progress.prefix(),
+        progress.message().trim_end(),
+        crate::format::humanize_duration(progress.elapsed())
+    )
+}
+
+impl TuiRenderer {
+    // Returns None when the terminal can't host the alternate screen so the
+    // caller keeps using the regular indicatif rendering
+    pub fn start() -> Option<Arc<Self>> {
+        if !is_supported() {
+            return None;
+        }
+
+        let mut stdout = std::io::stdout();
+        if stdout.execute(terminal::EnterAlternateScreen).is_err() {
+            return None;
+        }
+        let _ = stdout.execute(cursor::Hide);
+
+        let state = Arc::new(Mutex::new(TuiState::default()));
+        let is_running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let state = state.clone();
+            let is_running = is_running.clone();
+            std::thread::spawn(move || {
+                while is_running.load(Ordering::Relaxed) {
+                    let _ = Self::render(&state);
+                    std::thread::sleep(REFRESH_INTERVAL);
+                }
+            })
+        };
+
+        Some(Arc::new(Self {
+            state,
+            is_running,
+            thread: Some(thread),
+        }))
+    }
+
+    pub(crate) fn add_job(self: &Arc<Self>, progress: indicatif::ProgressBar) -> TuiJob {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.jobs.push((id, progress));
+        TuiJob {
+            renderer: self.clone(),
+            id,
+        }
+    }
+
+    pub fn println(&self, message: &str) {
+        let mut state = self.state.lock().unwrap();
+        for line in message.trim_end_matches('\n').lines() {
+            state.lines.push(line.to_string());
+        }
+    }
+
+    fn finish_job(&self, id: usize) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(index) = state.jobs.iter().position(|(job_id, _)| *job_id == id) {
+            let (_, progress) = state.jobs.remove(index);
+            state.lines.push(format_finished_job(&progress));
+        }
+    }
+
+    fn render(state: &Mutex<TuiState>) -> std::io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        let state = state.lock().unwrap();
+
+        let job_rows = state.jobs.len().min(height);
+        let line_rows = height - job_rows;
+        let first_line = state.lines.len().saturating_sub(line_rows);
+
+        let mut stdout = std::io::stdout();
+        stdout.queue(terminal::Clear(terminal::ClearType::All))?;
+        for (row, line) in state.lines[first_line..].iter().enumerate() {
+            stdout.queue(cursor::MoveTo(0, row as u16))?;
+            write!(stdout, "{}", line.chars().take(width).collect::<String>())?;
+        }
+        for (row, (_, progress)) in state.jobs.iter().take(job_rows).enumerate() {
+            stdout.queue(cursor::MoveTo(0, (line_rows + row) as u16))?;
+            write!(stdout, "{}", format_job(progress, width))?;
+        }
+        stdout.flush()
+    }
+}
+
+impl Drop for TuiRenderer {
+    fn drop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        let mut stdout = std::io::stdout();
+        let _ = stdout.execute(cursor::Show);
+        let _ = stdout.execute(terminal::LeaveAlternateScreen);
+
+        // the alternate screen is discarded on exit so replay what scrolled by
+        let state = self.state.lock().unwrap();
+        for line in state.lines.iter() {
+            let _ = writeln!(stdout, "{line}");
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    session::{EventSink, RecordedEvent, SessionEvent},
+    Printer, RenderMode,
+};
+#[cfg(feature = "progress")]
+use indicatif::TermLike;
+use js_sys::Function;
+use std::{
+    fmt::Debug,
+    io::{Result as IoResult, Write},
+    sync::Mutex,
+};
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+const WASM_WIDTH: usize = 120;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console, js_name = log)]
+    fn console_log(line: &str);
+}
+
+// JS values are tied to the thread that created them. wasm32 without the
+// atomics feature only has that one thread, so these never actually move.
+struct JsCallback(Function);
+
+unsafe impl Send for JsCallback {}
+unsafe impl Sync for JsCallback {}
+
+impl JsCallback {
+    fn call(&self, argument: &str) {
+        let _ = self.0.call1(&JsValue::NULL, &JsValue::from_str(argument));
+    }
+}
+
+// `output` receives each printed line, e.g. to append it to the DOM, and
+// defaults to console.log. `progress` receives every progress bar event as
+// the JSON written to session files.
+#[derive(Default)]
+pub struct WasmCallbacks {
+    output: Option<Function>,
+    progress: Option<Function>,
+}
+
+impl WasmCallbacks {
+    pub fn output(mut self, callback: Function) -> Self {
+        self.output = Some(callback);
+        self
+    }
+
+    pub fn progress(mut self, callback: Function) -> Self {
+        self.progress = Some(callback);
+        self
+    }
+}
+
+// Output is passed on a line at a time. There is no cursor so bars are
+// reported through the progress callback instead of being drawn.
+struct WasmTerm {
+    output: Option<JsCallback>,
+    pending: Mutex<String>,
+}
+
+impl WasmTerm {
+    fn emit(&self, line: &str) {
+        match self.output.as_ref() {
+            Some(output) => output.call(line),
+            None => console_log(line),
+        }
+    }
+
+    fn push(&self, text: &str) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.push_str(text);
+        while let Some(end) = pending.find('\n') {
+            let line: String = pending.drain(..=end).collect();
+            self.emit(line.trim_end_matches('\n'));
+        }
+    }
+
+    // emits a partial last line
+    fn flush_pending(&self) {
+        let line = std::mem::take(&mut *self.pending.lock().unwrap());
+        if !line.is_empty() {
+            self.emit(line.as_str());
+        }
+    }
+}
+
+impl Debug for WasmTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WasmTerm")
+    }
+}
+
+impl Write for WasmTerm {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.push(String::from_utf8_lossy(buf).as_ref());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.flush_pending();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "progress")]
+impl TermLike for WasmTerm {
+    fn write_line(&self, line: &str) -> IoResult<()> {
+        self.push(line);
+        self.push("\n");
+        Ok(())
+    }
+
+    fn clear_line(&self) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_up(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_down(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_left(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_right(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn width(&self) -> u16 {
+        WASM_WIDTH as u16
+    }
+
+    fn height(&self) -> u16 {
+        128
+    }
+
+    fn flush(&self) -> IoResult<()> {
+        self.flush_pending();
+        Ok(())
+    }
+
+    fn write_str(&self, text: &str) -> IoResult<()> {
+        self.push(text);
+        Ok(())
+    }
+}
+
+struct ProgressSink {
+    callback: JsCallback,
+}
+
+impl EventSink for ProgressSink {
+    fn event(&mut self, event: &RecordedEvent) {
+        if matches!(event.event, SessionEvent::Output { .. }) {
+            return;
+        }
+        if let Ok(json) = serde_json::to_string(event) {
+            self.callback.call(json.as_str());
+        }
+    }
+}
+
+impl Printer {
+    pub fn new_wasm(callbacks: WasmCallbacks) -> Self {
+        let term = WasmTerm {
+            output: callbacks.output.map(JsCallback),
+            pending: Mutex::new(String::new()),
+        };
+        let mut printer = Self::new_with_writer(Box::new(term), RenderMode::Plain, WASM_WIDTH);
+        if let Some(progress) = callbacks.progress {
+            printer.add_event_sink(Box::new(ProgressSink {
+                callback: JsCallback(progress),
+            }));
+        }
+        printer
+    }
+}
@@ -0,0 +1,497 @@
+use crate::{
+    format, keyboard::Controls, ExecuteOptions, FinishStyle, Level, MultiProgress, Printer,
+    RenderMode, Section,
+};
+use anyhow::Context;
+use anyhow_source_location::format_context;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+type Fingerprint = BTreeMap<PathBuf, (Option<SystemTime>, u64)>;
+
+// Reruns a command when the files it depends on change. Paths are watched
+// recursively when they are directories, and patterns may use `*` and `?`
+// within a component and `**` across components, e.g. `src/**/*.rs`.
+#[derive(Debug, Clone)]
+pub struct Watch {
+    patterns: Vec<String>,
+    debounce: Duration,
+    poll_interval: Duration,
+    max_runs: Option<usize>,
+    is_clear: bool,
+    is_diff: bool,
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            debounce: Duration::from_millis(300),
+            poll_interval: Duration::from_millis(250),
+            max_runs: None,
+            is_clear: true,
+            is_diff: false,
+        }
+    }
+}
+
+impl Watch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn path(mut self, pattern: &str) -> Self {
+        self.patterns.push(pattern.to_string());
+        self
+    }
+
+    // a burst of changes, like a save of many files, starts a single run once
+    // nothing has changed for this long
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn max_runs(mut self, max_runs: usize) -> Self {
+        self.max_runs = Some(max_runs);
+        self
+    }
+
+    // leaves earlier runs on screen instead of replacing them
+    pub fn keep_previous(mut self) -> Self {
+        self.is_clear = false;
+        self
+    }
+
+    // after the first run only the lines of stdout that differ from the
+    // previous run are printed, added ones prefixed with `+` and removed ones
+    // with `-`
+    pub fn diff_output(mut self) -> Self {
+        self.is_diff = true;
+        self
+    }
+
+    fn fingerprint(&self) -> Fingerprint {
+        let mut fingerprint = Fingerprint::new();
+        for pattern in self.patterns.iter() {
+            for path in expand(pattern) {
+                let metadata = std::fs::metadata(&path).ok();
+                let stamp = (
+                    metadata
+                        .as_ref()
+                        .and_then(|metadata| metadata.modified().ok()),
+                    metadata.map(|metadata| metadata.len()).unwrap_or_default(),
+                );
+                fingerprint.insert(path, stamp);
+            }
+        }
+        fingerprint
+    }
+
+    // Polls until something changed and then stayed quiet for the debounce
+    // time. None when cancelled first.
+    fn wait_for_change(
+        &self,
+        previous: &Fingerprint,
+        controls: &Controls,
+    ) -> Option<(Fingerprint, Vec<PathBuf>)> {
+        let mut current = previous.clone();
+        let mut last_change: Option<Instant> = None;
+        loop {
+            if controls.is_cancel_requested() {
+                return None;
+            }
+            std::thread::sleep(self.poll_interval);
+            let next = self.fingerprint();
+            if next != current {
+                current = next;
+                last_change = Some(Instant::now());
+            }
+            if let Some(last_change) = last_change {
+                if last_change.elapsed() >= self.debounce && current != *previous {
+                    let changed = changed_paths(previous, &current);
+                    return Some((current, changed));
+                }
+            }
+        }
+    }
+}
+
+fn changed_paths(previous: &Fingerprint, current: &Fingerprint) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = current
+        .iter()
+        .filter(|(path, stamp)| previous.get(*path) != Some(stamp))
+        .map(|(path, _)| path.clone())
+        .collect();
+    changed.extend(
+        previous
+            .keys()
+            .filter(|path| !current.contains_key(*path))
+            .cloned(),
+    );
+    changed.sort();
+    changed
+}
+
+// diffs larger than this after trimming the common lines are shown as a
+// full replacement rather than spending quadratic time on them
+const DIFF_MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, PartialEq, Eq)]
+enum LineChange<'a> {
+    Added(&'a str),
+    Removed(&'a str),
+}
+
+// A line diff based on the longest common subsequence, in output order
+fn diff_lines<'a>(previous: &'a str, current: &'a str) -> Vec<LineChange<'a>> {
+    let previous: Vec<&str> = previous.lines().collect();
+    let current: Vec<&str> = current.lines().collect();
+    let prefix = previous
+        .iter()
+        .zip(current.iter())
+        .take_while(|(before, after)| before == after)
+        .count();
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(before, after)| before == after)
+        .count();
+    let before = &previous[prefix..previous.len() - suffix];
+    let after = &current[prefix..current.len() - suffix];
+
+    let mut changes = Vec::new();
+    if before.len().saturating_mul(after.len()) > DIFF_MAX_CELLS {
+        changes.extend(before.iter().map(|line| LineChange::Removed(line)));
+        changes.extend(after.iter().map(|line| LineChange::Added(line)));
+        return changes;
+    }
+    // common[i][j] is the LCS length of before[i..] and after[j..]
+    let width = after.len() + 1;
+    let mut common = vec![0usize; (before.len() + 1) * width];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i * width + j] = if before[i] == after[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            i += 1;
+            j += 1;
+        } else if j < after.len()
+            && (i == before.len() || common[i * width + j + 1] >= common[(i + 1) * width + j])
+        {
+            changes.push(LineChange::Added(after[j]));
+            j += 1;
+        } else {
+            changes.push(LineChange::Removed(before[i]));
+            i += 1;
+        }
+    }
+    changes
+}
+
+fn is_wildcard(component: &str) -> bool {
+    component.contains(['*', '?'])
+}
+
+// Files matched by a path or pattern. The walk starts at the components
+// before the first wildcard and only enters directories the rest of the
+// pattern can match. Symlinked directories are not followed, and wildcards
+// skip names starting with `.` unless the pattern component does too, like a
+// shell. `**` does not descend into directories tagged with CACHEDIR.TAG,
+// which build tools such as cargo put in their output directories, though a
+// pattern can still start in one.
+pub(crate) fn expand(pattern: &str) -> Vec<PathBuf> {
+    let components: Vec<String> = Path::new(pattern)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let split = components
+        .iter()
+        .position(|component| is_wildcard(component))
+        .unwrap_or(components.len());
+    let base: PathBuf = components[..split].iter().collect();
+    let base = if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    };
+    let mut files = Vec::new();
+    if split == components.len() {
+        walk(&base, &mut files);
+    } else {
+        walk_pattern(&base, &components[split..], &mut files);
+    }
+    files
+}
+
+fn is_real_directory(path: &Path) -> bool {
+    std::fs::symlink_metadata(path)
+        .map(|metadata| metadata.is_dir())
+        .unwrap_or(false)
+}
+
+// (path, name, is a directory) without following symlinks
+fn read_entries(path: &Path) -> Vec<(PathBuf, String, bool)> {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| {
+            let is_directory = entry
+                .file_type()
+                .map(|file_type| file_type.is_dir())
+                .unwrap_or(false);
+            let name = entry.file_name().to_string_lossy().into_owned();
+            (entry.path(), name, is_directory)
+        })
+        .collect()
+}
+
+fn walk(path: &Path, files: &mut Vec<PathBuf>) {
+    if is_real_directory(path) {
+        for (entry, _, _) in read_entries(path) {
+            walk(&entry, files);
+        }
+    } else {
+        // missing files are kept so creating them counts as a change
+        files.push(path.to_path_buf());
+    }
+}
+
+fn walk_pattern(path: &Path, pattern: &[String], files: &mut Vec<PathBuf>) {
+    let Some((first, rest)) = pattern.split_first() else {
+        if !is_real_directory(path) && path.exists() {
+            files.push(path.to_path_buf());
+        }
+        return;
+    };
+    if first == "**" {
+        walk_pattern(path, rest, files);
+        for (entry, name, is_directory) in read_entries(path) {
+            if name.starts_with('.') {
+                continue;
+            }
+            if is_directory {
+                if !entry.join("CACHEDIR.TAG").is_file() {
+                    walk_pattern(&entry, pattern, files);
+                }
+            } else if rest.is_empty() {
+                files.push(entry);
+            }
+        }
+    } else if is_wildcard(first) {
+        let is_hidden_allowed = first.starts_with('.');
+        for (entry, name, is_directory) in read_entries(path) {
+            if (is_hidden_allowed || !name.starts_with('.'))
+                && matches_wildcard(first.as_bytes(), name.as_bytes())
+                && (rest.is_empty() || is_directory)
+            {
+                walk_pattern(&entry, rest, files);
+            }
+        }
+    } else {
+        walk_pattern(&path.join(first), rest, files);
+    }
+}
+
+fn matches_wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            matches_wildcard(&pattern[1..], text)
+                || (!text.is_empty() && matches_wildcard(pattern, &text[1..]))
+        }
+        (Some(b'?'), Some(_)) => matches_wildcard(&pattern[1..], &text[1..]),
+        (Some(expected), Some(actual)) if expected == actual => {
+            matches_wildcard(&pattern[1..], &text[1..])
+        }
+        _ => false,
+    }
+}
+
+impl Printer {
+    // Runs the command, then again each time a watched file changes, until
+    // the run limit or a cancel request. Each run gets a numbered section with
+    // the command's output. On a terminal the section of the previous run is
+    // cleared first. Returns the number of runs.
+    pub fn watch(
+        &mut self,
+        watch: &Watch,
+        command: &str,
+        options: ExecuteOptions,
+    ) -> anyhow::Result<usize> {
+        let controls = self.controls();
+        let _operation = controls.begin_operation();
+        let is_clearing = watch.is_clear
+            && self.render_mode == RenderMode::Interactive
+            && self.capabilities.is_cursor_control;
+        let mut fingerprint = watch.fingerprint();
+        let mut changed = Vec::new();
+        let mut drawn_lines = 0;
+        let mut number = 0;
+        let mut previous_stdout: Option<String> = None;
+        loop {
+            number += 1;
+            if is_clearing && drawn_lines > 0 {
+                self.clear_last_lines(drawn_lines)
+                    .context(format_context!(""))?;
+            }
+            let (result, output) = self.capture_and_print(|printer| {
+                printer.watch_run(
+                    watch,
+                    number,
+                    &changed,
+                    command,
+                    &options,
+                    previous_stdout.as_deref(),
+                )
+            });
+            // a failed run keeps the last output to compare against
+            if let Some(stdout) = result.context(format_context!("while running {command}"))? {
+                previous_stdout = Some(stdout);
+            }
+            drawn_lines = output.matches('\n').count();
+
+            let is_done = watch.max_runs.is_some_and(|max_runs| number >= max_runs);
+            if is_done || controls.is_cancel_requested() {
+                return Ok(number);
+            }
+            match watch.wait_for_change(&fingerprint, &controls) {
+                Some((next, paths)) => {
+                    fingerprint = next;
+                    changed = paths;
+                }
+                None => return Ok(number),
+            }
+        }
+    }
+
+    fn watch_run(
+        &mut self,
+        watch: &Watch,
+        number: usize,
+        changed: &[PathBuf],
+        command: &str,
+        options: &ExecuteOptions,
+        previous_stdout: Option<&str>,
+    ) -> anyhow::Result<Option<String>> {
+        let title = format!("run {number}: {}", options.get_full_command(command));
+        let section = Section::new(self, title.as_str()).context(format_context!(""))?;
+        if !changed.is_empty() {
+            let changed: Vec<String> = changed
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            section
+                .printer
+                .info("changed", &changed)
+                .context(format_context!(""))?;
+        }
+
+        // stdout is printed once the run is over rather than only flashing by
+        // as the bar message
+        let mut capturing = options.clone();
+        capturing.is_return_stdout = true;
+        let started = Instant::now();
+        let result = {
+            let mut multi_progress = MultiProgress::new(section.printer);
+            let mut progress_bar = multi_progress.add_progress("progress", None, None);
+            let result = progress_bar.execute_process(command, capturing);
+            progress_bar.finish_with(FinishStyle::Clear);
+            result
+        };
+        let elapsed = format::humanize_duration(started.elapsed());
+        let stdout = match result {
+            Ok(stdout) => {
+                let stdout = stdout.unwrap_or_default();
+                match previous_stdout.filter(|_| watch.is_diff) {
+                    Some(previous) => section
+                        .printer
+                        .watch_diff(previous, stdout.as_str())
+                        .context(format_context!(""))?,
+                    None => {
+                        let indent = section.printer.indentation();
+                        for line in stdout.lines() {
+                            section
+                                .printer
+                                .write(format!("{indent}{}\n", format::sanitize(line)).as_str())
+                                .context(format_context!(""))?;
+                        }
+                    }
+                }
+                section
+                    .printer
+                    .info("finished", &elapsed)
+                    .context(format_context!(""))?;
+                Some(stdout)
+            }
+            Err(error) => {
+                section
+                    .printer
+                    .log(Level::Error, format!("{error:#}").as_str())
+                    .context(format_context!(""))?;
+                None
+            }
+        };
+        if watch.max_runs.is_none() {
+            section
+                .printer
+                .info("watching", &watch.patterns.join(", "))
+                .context(format_context!(""))?;
+        }
+        Ok(stdout)
+    }
+
+    fn watch_diff(&mut self, previous: &str, current: &str) -> anyhow::Result<()> {
+        let changes = diff_lines(previous, current);
+        if changes.is_empty() {
+            return self
+                .info("output", &"unchanged")
+                .context(format_context!(""));
+        }
+        let indent = self.indentation();
+        for change in changes {
+            let line = match change {
+                LineChange::Added(line) => self
+                    .theme
+                    .success
+                    .paint(format!("+ {}", format::sanitize(line)).as_str()),
+                LineChange::Removed(line) => self
+                    .theme
+                    .levels
+                    .error
+                    .paint(format!("- {}", format::sanitize(line)).as_str()),
+            };
+            self.write(format!("{indent}{line}\n").as_str())
+                .context(format_context!(""))?;
+        }
+        Ok(())
+    }
+
+    fn clear_last_lines(&mut self, count: usize) -> anyhow::Result<()> {
+        let _lock = self.lock.lock().unwrap();
+        let writer = self.writer.lock().unwrap();
+        for _ in 0..count {
+            writer.move_cursor_up(1).context(format_context!(""))?;
+            writer.clear_line().context(format_context!(""))?;
+        }
+        Ok(())
+    }
+}
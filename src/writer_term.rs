@@ -0,0 +1,85 @@
+#[cfg(feature = "progress")]
+use indicatif::TermLike;
+use std::fmt::Debug;
+use std::io::{Result as IoResult, Write};
+use std::sync::Mutex;
+
+// Adapts any writer to back a Printer, and to TermLike for the progress
+// bars. Cursor movement is not representable on a plain stream so it is
+// ignored.
+pub struct WriterTerm<W: Write + Send> {
+    writer: Mutex<W>,
+    #[cfg(feature = "progress")]
+    width: u16,
+}
+
+impl<W: Write + Send> WriterTerm<W> {
+    pub fn new(writer: W, width: u16) -> Self {
+        #[cfg(not(feature = "progress"))]
+        let _ = width;
+        Self {
+            writer: Mutex::new(writer),
+            #[cfg(feature = "progress")]
+            width,
+        }
+    }
+}
+
+impl<W: Write + Send> Debug for WriterTerm<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WriterTerm")
+    }
+}
+
+impl<W: Write + Send> Write for WriterTerm<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.writer.get_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.get_mut().unwrap().flush()
+    }
+}
+
+#[cfg(feature = "progress")]
+impl<W: Write + Send> TermLike for WriterTerm<W> {
+    fn write_line(&self, line: &str) -> IoResult<()> {
+        writeln!(self.writer.lock().unwrap(), "{line}")
+    }
+
+    fn clear_line(&self) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_up(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_down(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_left(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn move_cursor_right(&self, _: usize) -> IoResult<()> {
+        Ok(())
+    }
+
+    fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn height(&self) -> u16 {
+        128
+    }
+
+    fn flush(&self) -> IoResult<()> {
+        self.writer.lock().unwrap().flush()
+    }
+
+    fn write_str(&self, text: &str) -> IoResult<()> {
+        self.writer.lock().unwrap().write_all(text.as_bytes())
+    }
+}